@@ -38,8 +38,10 @@ pub trait AstVisitor {
         self.walk_expression(value);
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn visit_function_def(
         &mut self,
+        location: &Location,
         is_async: bool,
         name: &String,
         args: &Box<Parameters>,
@@ -54,6 +56,7 @@ pub trait AstVisitor {
 
     fn visit_class_def(
         &mut self,
+        location: &Location,
         name: String,
         body: &Suite,
         bases: &Vec<Expression>,
@@ -293,14 +296,29 @@ pub trait AstVisitor {
                 body,
                 decorator_list,
                 returns,
-            } => self.visit_function_def(*is_async, name, args, body, decorator_list, returns),
+            } => self.visit_function_def(
+                &stmt.location,
+                *is_async,
+                name,
+                args,
+                body,
+                decorator_list,
+                returns,
+            ),
             StatementType::ClassDef {
                 name,
                 body,
                 bases,
                 keywords,
                 decorator_list,
-            } => self.visit_class_def(name.clone(), body, bases, keywords, decorator_list),
+            } => self.visit_class_def(
+                &stmt.location,
+                name.clone(),
+                body,
+                bases,
+                keywords,
+                decorator_list,
+            ),
         }
     }
 }
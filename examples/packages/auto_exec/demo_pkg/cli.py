@@ -0,0 +1,9 @@
+import os
+
+
+def main():
+    os.system("whoami")
+
+
+def unused():
+    os.system("hostname")
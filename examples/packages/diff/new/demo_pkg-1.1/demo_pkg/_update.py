@@ -0,0 +1,5 @@
+import base64
+
+
+def run():
+    exec(base64.b64decode("cHJpbnQoJ2hpJyk="))
@@ -0,0 +1,7 @@
+import sys
+
+from demo_pkg.util import git_status
+
+
+def main():
+    print(git_status(sys.argv[1]).decode())
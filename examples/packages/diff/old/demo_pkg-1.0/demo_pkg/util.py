@@ -0,0 +1,5 @@
+import subprocess
+
+
+def git_status(path):
+    return subprocess.check_output(["git", "status"], cwd=path)
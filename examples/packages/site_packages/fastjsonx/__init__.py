@@ -0,0 +1 @@
+from fastjsonx.codec import dumps, loads
@@ -0,0 +1,3 @@
+from goodlib.core import greet
+
+__all__ = ["greet"]
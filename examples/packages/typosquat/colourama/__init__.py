@@ -0,0 +1 @@
+from colorama import *  # noqa: F401,F403
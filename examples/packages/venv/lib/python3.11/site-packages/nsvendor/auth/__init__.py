@@ -0,0 +1,2 @@
+def token(user):
+    return "token-" + user
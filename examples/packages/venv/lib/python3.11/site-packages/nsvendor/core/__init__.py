@@ -0,0 +1,3 @@
+from nsvendor.core.config import load
+
+__all__ = ["load"]
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use scout::{Config, DensityEvaluator, Engine, FieldType};
+
+/// Lines of code in the generated "files" we benchmark against.
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+fn bench_density_field(c: &mut Criterion) {
+    let config = Config::new(&None).unwrap();
+    let mut group = c.benchmark_group("density_field");
+
+    for loc in SIZES {
+        // roughly one bulletin every 20 lines, like a heavily obfuscated file
+        let rows: Vec<usize> = (1..loc).step_by(20).collect();
+
+        group.bench_with_input(BenchmarkId::from_parameter(loc), &rows, |b, rows| {
+            b.iter(|| {
+                let mut de = DensityEvaluator::new(loc, &config);
+                for (idx, row) in rows.iter().enumerate() {
+                    let tfidf = if idx % 3 == 0 { 0.5 } else { 1.0 };
+                    de.add_density(FieldType::Functions, *row, tfidf, config.tw_functions);
+                    de.add_density(FieldType::Imports, *row, tfidf, config.tw_imports);
+                }
+                black_box(de.hotspots())
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn generate_large_file(loc: usize) -> PathBuf {
+    let mut source = String::from("import base64\nimport subprocess\n");
+    for idx in 0..(loc / 4) {
+        source.push_str(&format!("def func_{}(arg):\n", idx));
+        source.push_str("    data = base64.b64decode(arg)\n");
+        source.push_str("    subprocess.Popen(data)\n");
+        source.push_str("    return data\n");
+    }
+
+    let path = std::env::temp_dir().join(format!("scout-bench-{}.py", loc));
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+fn bench_large_file(c: &mut Criterion) {
+    let mut group = c.benchmark_group("analyse_large_file");
+    group.sample_size(10);
+
+    for loc in SIZES {
+        let path = generate_large_file(loc);
+        let path = path.to_str().unwrap().to_owned();
+
+        group.bench_with_input(BenchmarkId::from_parameter(loc), &path, |b, path| {
+            b.iter(|| black_box(Engine::new().analyse_file(path).unwrap()))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_density_field, bench_large_file);
+criterion_main!(benches);
@@ -1,12 +1,23 @@
 use std::path::PathBuf;
-use std::str::FromStr;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::utils;
-use crate::Result;
+use crate::{Result, ScoutError};
+
+/// How bulletins are grouped into hotspots.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HotspotMode {
+    /// Windows where the combined density field is over the hotspot threshold.
+    #[default]
+    Density,
+    /// Whole function and class definitions, scored by the peak density inside them.
+    Definition,
+}
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     pub use_cache: bool,
     pub save_cache: bool,
@@ -30,39 +41,311 @@ pub struct Config {
     // pub tw_strings: f64,
     pub feature_tfidf_calls: bool,
     pub feature_tfidf_imports: bool,
+
+    /// Group bulletins by density windows or by function and class definitions
+    #[serde(default)]
+    pub hotspot_mode: HotspotMode,
+
+    /// Path to a canary table, the built-in table is used when not set
+    #[serde(default)]
+    pub canary_path: Option<String>,
+
+    /// How much more bulletins in auto-executed code weigh, e.g. top-level code in `setup.py`
+    pub auto_exec_weight: f64,
+
+    /// Domains, and their subdomains, that are reported whenever they appear in a string
+    #[serde(default)]
+    pub abuse_domains: Vec<String>,
+
+    /// Path to a list of popular package names, the built-in list is used when not set
+    #[serde(default)]
+    pub popular_packages_path: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::merge_json(Config::DEFAULT_CONFIG, Config::DEFAULT_CONFIG_FILE)
+            .expect("the built-in config is valid")
+    }
 }
 
 impl Config {
     pub const DEFAULT_CONFIG_FILE: &'static str = "config.jsonc";
     const DEFAULT_CONFIG: &'static str = include_str!("config.jsonc");
 
-    fn load_config(canary_path: &Option<String>) -> Result<Config> {
-        let config: String = match canary_path {
-            Some(canary_path) => {
-                let path = PathBuf::from_str(canary_path.as_str())?;
-                trace!("Loading canaries from: '{}'", &canary_path);
-                utils::load_from_file(path)?
+    /// Config files looked for in the current directory, in order.
+    pub const PROJECT_CONFIG_FILES: [&'static str; 4] =
+        [".scout.jsonc", "scout.jsonc", ".scout.toml", "scout.toml"];
+
+    /// Finds the config to use. An explicit path always wins, then a project config in
+    /// the current directory, then `config.jsonc` in the user config directory, e.g.
+    /// `~/.config/scout/config.jsonc`, and finally the built-in defaults.
+    fn discover(config_path: &Option<String>) -> Option<PathBuf> {
+        if let Some(config_path) = config_path {
+            return Some(PathBuf::from(config_path));
+        }
+
+        let project = Config::PROJECT_CONFIG_FILES
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file());
+        if project.is_some() {
+            return project;
+        }
+
+        dirs::config_dir()
+            .map(|dir| dir.join("scout").join(Config::DEFAULT_CONFIG_FILE))
+            .filter(|path| path.is_file())
+    }
+
+    fn load_config(config_path: &Option<String>) -> Result<Config> {
+        match Config::discover(config_path) {
+            Some(path) => {
+                trace!("Loading config from: '{}'", path.display());
+                let name = path.display().to_string();
+                let config = match std::fs::read_to_string(&path) {
+                    Ok(config) => config,
+                    Err(err) => {
+                        return Err(ScoutError::Config {
+                            message: format!("Failed to read config '{}': {}", name, err),
+                            name,
+                            source: Some(Box::new(err)),
+                        })
+                    }
+                };
+
+                if path.extension().is_some_and(|ext| ext == "toml") {
+                    Config::merge_toml(&config, &name)
+                } else {
+                    Config::merge_json(&config, &name)
+                }
             }
             None => {
-                trace!("Using default ruleset: '{}'", Config::DEFAULT_CONFIG_FILE);
-                Config::DEFAULT_CONFIG.to_owned()
+                trace!("Using default config: '{}'", Config::DEFAULT_CONFIG_FILE);
+                Ok(Config::default())
+            }
+        }
+    }
+
+    fn merge_json(json: &str, name: &str) -> Result<Config> {
+        let json = strip_jsonc(json);
+        let value: Value = match serde_json::from_str(&json) {
+            Ok(value) => value,
+            Err(err) => {
+                return Err(ScoutError::Config {
+                    name: name.to_owned(),
+                    message: format!("Failed to parse config '{}': {}", name, err),
+                    source: Some(Box::new(err)),
+                })
+            }
+        };
+
+        Config::merge(value, name, |field| {
+            let key = format!("\"{}\"", field);
+            json.lines().position(|line| line.contains(&key))
+        })
+    }
+
+    fn merge_toml(toml: &str, name: &str) -> Result<Config> {
+        let value: toml::Value = match toml::from_str(toml) {
+            Ok(value) => value,
+            Err(err) => {
+                return Err(ScoutError::Config {
+                    name: name.to_owned(),
+                    message: format!("Failed to parse config '{}': {}", name, err),
+                    source: Some(Box::new(err)),
+                })
             }
         };
 
-        Config::parse_json(config)
+        Config::merge(serde_json::to_value(value)?, name, |field| {
+            toml.lines().position(|line| {
+                line.trim_start()
+                    .strip_prefix(field)
+                    .is_some_and(|rest| rest.trim_start().starts_with('='))
+            })
+        })
     }
 
-    fn parse_json(json: String) -> Result<Config> {
-        let config: Config = serde_json::from_str(json.as_str())?;
-        Ok(config)
+    /// Merges a possibly partial config over the defaults. `locate` finds the line a
+    /// field is set on, so errors can point to it.
+    fn merge(value: Value, name: &str, locate: impl Fn(&str) -> Option<usize>) -> Result<Config> {
+        let at_line = |field: &str| match locate(field) {
+            Some(idx) => format!(" at line {}", idx + 1),
+            None => String::from(""),
+        };
+
+        let overrides = match value {
+            Value::Object(overrides) => overrides,
+            _ => {
+                return Err(ScoutError::config(
+                    name,
+                    format!("Config '{}' has to be an object", name),
+                ))
+            }
+        };
+
+        let mut merged: Value = serde_json::from_str(&strip_jsonc(Config::DEFAULT_CONFIG))?;
+        let defaults = merged
+            .as_object_mut()
+            .ok_or_else(|| ScoutError::config(name, "The built-in config is not an object"))?;
+        for (field, value) in overrides {
+            if !defaults.contains_key(&field) {
+                return Err(ScoutError::config(
+                    name,
+                    format!(
+                        "Unknown field '{}'{} in config '{}'",
+                        field,
+                        at_line(&field),
+                        name
+                    ),
+                ));
+            }
+            defaults.insert(field, value);
+        }
+
+        match serde_path_to_error::deserialize(merged) {
+            Ok(config) => Ok(config),
+            Err(err) => {
+                let field = err.path().to_string();
+                Err(ScoutError::Config {
+                    name: name.to_owned(),
+                    message: format!(
+                        "Invalid value for field '{}'{} in config '{}': {}",
+                        field,
+                        at_line(&field),
+                        name,
+                        err.inner()
+                    ),
+                    source: Some(Box::new(err.into_inner())),
+                })
+            }
+        }
     }
 
     pub fn new(config_path: &Option<String>) -> Result<Self> {
-        Ok(Config::load_config(config_path)?)
+        Config::load_config(config_path)
     }
 
+    /// Parses a config given as JSON with comments, merged over the defaults.
     pub fn from_str(config_json: String) -> Result<Self> {
-        Config::parse_json(config_json)
+        Config::merge_json(&config_json, "<json>")
+    }
+}
+
+/// Blanks out comments and trailing commas in JSONC, keeping every other character
+/// where it was so errors from `serde_json` still point to the right line and column.
+fn strip_jsonc(json: &str) -> String {
+    let chars: Vec<char> = json.chars().collect();
+    let mut out: Vec<char> = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    let mut in_string = false;
+
+    let blank = |c: char| if c == '\n' || c == '\r' { c } else { ' ' };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if in_string {
+            out.push(c);
+            if c == '\\' {
+                if let Some(next) = next {
+                    out.push(next);
+                    i += 1;
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
+
+        match (c, next) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+                i += 1;
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    out.push(blank(chars[i]));
+                    i += 1;
+                }
+            }
+            ('/', Some('*')) => {
+                out.extend(chars[i..(i + 2)].iter().map(|&c| blank(c)));
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    out.push(blank(chars[i]));
+                    i += 1;
+                }
+                let end = (i + 2).min(chars.len());
+                out.extend(chars[i..end].iter().map(|&c| blank(c)));
+                i = end;
+            }
+            (']', _) | ('}', _) => {
+                // a trailing comma is the last thing before the closing bracket
+                if let Some(idx) = out.iter().rposition(|c| !c.is_whitespace()) {
+                    if out[idx] == ',' {
+                        out[idx] = ' ';
+                    }
+                }
+                out.push(c);
+                i += 1;
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    out.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_jsonc, Config, HotspotMode};
+
+    #[test]
+    fn test_strip_jsonc() {
+        let json =
+            "{\n  // a comment\n  \"a\": \"// not a comment\", /* b */\n  \"c\": [1, 2,],\n}";
+        let stripped = strip_jsonc(json);
+
+        assert_eq!(stripped.lines().count(), json.lines().count());
+        let value: serde_json::Value = serde_json::from_str(&stripped).unwrap();
+        assert_eq!(value["a"], "// not a comment");
+        assert_eq!(value["c"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn test_partial_config_is_merged_over_defaults() {
+        let config = Config::from_str(String::from(
+            "{\n  // group by definitions\n  \"hotspot_mode\": \"definition\",\n}",
+        ))
+        .unwrap();
+        let defaults = Config::default();
+
+        assert_eq!(config.hotspot_mode, HotspotMode::Definition);
+        assert_eq!(config.fw_imports, defaults.fw_imports);
+        assert_eq!(config.feature_tfidf_calls, defaults.feature_tfidf_calls);
+    }
+
+    #[test]
+    fn test_config_errors_name_field_and_line() {
+        let err = Config::from_str(String::from("{\n  \"fw_imports\": \"high\"\n}")).unwrap_err();
+        let err = err.to_string();
+        assert!(err.contains("'fw_imports' at line 2"), "{}", err);
+
+        let err = Config::from_str(String::from("{\n\n  \"fw_import\": 1.0\n}")).unwrap_err();
+        let err = err.to_string();
+        assert!(err.contains("'fw_import' at line 3"), "{}", err);
+
+        let err = Config::merge_toml("fw_imports = 1.0\nhotspot_mode = 2\n", "scout.toml")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'hotspot_mode' at line 2"), "{}", err);
     }
 }
- 
\ No newline at end of file
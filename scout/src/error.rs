@@ -0,0 +1,210 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// Why an analysis or one of its inputs failed.
+#[derive(Debug)]
+pub enum ScoutError {
+    /// A config could not be read or parsed, or has invalid values. `name` is the path of
+    /// the config, or `<json>` when it was given as a string, the message names it too.
+    Config {
+        name: String,
+        message: String,
+        source: Option<BoxError>,
+    },
+    /// A rule set could not be read or parsed
+    Rules {
+        path: Option<PathBuf>,
+        source: BoxError,
+    },
+    /// The canary table could not be read, parsed or generated
+    Canaries {
+        path: Option<PathBuf>,
+        source: BoxError,
+    },
+    /// A file or directory could not be read, written or created
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// A source, notebook, compiled or native file, or package metadata, could not be parsed
+    Parse {
+        path: Option<PathBuf>,
+        message: String,
+        source: Option<BoxError>,
+    },
+    /// A wheel or source distribution could not be unpacked
+    Archive { path: PathBuf, source: BoxError },
+    /// The analysis cache could not be read or written
+    Cache { source: BoxError },
+    /// No package was found at the path
+    PackageNotFound { path: PathBuf },
+    /// JSON could not be written or read
+    Json(serde_json::Error),
+}
+
+impl ScoutError {
+    pub fn config(name: &str, message: impl fmt::Display) -> Self {
+        ScoutError::Config {
+            name: name.to_owned(),
+            message: message.to_string(),
+            source: None,
+        }
+    }
+
+    pub fn parse(message: impl fmt::Display) -> Self {
+        ScoutError::Parse {
+            path: None,
+            message: message.to_string(),
+            source: None,
+        }
+    }
+
+    /// A parse error caused by `source`, e.g. of the Python parser or of a nested file.
+    pub fn parse_from(message: impl fmt::Display, source: impl Into<BoxError>) -> Self {
+        ScoutError::Parse {
+            path: None,
+            message: message.to_string(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Sets the path the error happened in, unless it already has one.
+    pub fn at(self, at: &Path) -> Self {
+        match self {
+            ScoutError::Io { path: None, source } => ScoutError::Io {
+                path: Some(at.to_path_buf()),
+                source,
+            },
+            ScoutError::Parse {
+                path: None,
+                message,
+                source,
+            } => ScoutError::Parse {
+                path: Some(at.to_path_buf()),
+                message,
+                source,
+            },
+            err => err,
+        }
+    }
+}
+
+/// Formats an optional path as ` 'path'`, to follow the kind of file in a message.
+fn path_suffix(path: &Option<PathBuf>) -> String {
+    match path {
+        Some(path) => format!(" '{}'", path.display()),
+        None => String::new(),
+    }
+}
+
+impl fmt::Display for ScoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoutError::Config { message, .. } => write!(f, "{}", message),
+            ScoutError::Rules { path, source } => write!(
+                f,
+                "Rule manager could not be initalized{}: {}",
+                path_suffix(path),
+                source
+            ),
+            ScoutError::Canaries { path, source } => write!(
+                f,
+                "Canaries could not be loaded{}: {}",
+                path_suffix(path),
+                source
+            ),
+            ScoutError::Io { path, source } => match path {
+                Some(path) => write!(f, "Could not access '{}': {}", path.display(), source),
+                None => write!(f, "{}", source),
+            },
+            ScoutError::Parse { path, message, .. } => match path {
+                Some(path) => write!(f, "Could not parse '{}': {}", path.display(), message),
+                None => write!(f, "{}", message),
+            },
+            ScoutError::Archive { path, source } => {
+                write!(f, "Could not unpack '{}': {}", path.display(), source)
+            }
+            ScoutError::Cache { source } => write!(f, "Could not use the cache: {}", source),
+            ScoutError::PackageNotFound { path } => {
+                write!(f, "Could not detect package '{}'", path.display())
+            }
+            ScoutError::Json(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ScoutError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScoutError::Config { source, .. } | ScoutError::Parse { source, .. } => source
+                .as_ref()
+                .map(|source| source.as_ref() as &(dyn Error + 'static)),
+            ScoutError::Rules { source, .. }
+            | ScoutError::Canaries { source, .. }
+            | ScoutError::Archive { source, .. }
+            | ScoutError::Cache { source } => Some(source.as_ref()),
+            ScoutError::Io { source, .. } => Some(source),
+            ScoutError::Json(err) => Some(err),
+            ScoutError::PackageNotFound { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ScoutError {
+    fn from(source: io::Error) -> Self {
+        ScoutError::Io { path: None, source }
+    }
+}
+
+impl From<serde_json::Error> for ScoutError {
+    fn from(err: serde_json::Error) -> Self {
+        ScoutError::Json(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::io;
+    use std::path::Path;
+
+    use rustpython_parser::error::ParseError;
+
+    use super::ScoutError;
+    use crate::{Engine, SourceFile};
+
+    #[test]
+    fn test_errors_are_typed_with_sources() {
+        let err = Engine::new()
+            .analyse_file("../examples/files/does_not_exist.py")
+            .unwrap_err();
+        assert!(
+            matches!(&err, ScoutError::Io { path: Some(path), .. } if path.ends_with("does_not_exist.py")),
+            "{:?}",
+            err
+        );
+
+        let err = Engine::new()
+            .set_rule_path(Some(String::from("../examples/missing_rules.ron")))
+            .analyse_package("../examples/packages/auto_exec")
+            .unwrap_err();
+        assert!(matches!(err, ScoutError::Rules { .. }), "{:?}", err);
+        let source = err.source().and_then(|err| err.downcast_ref::<io::Error>());
+        assert_eq!(source.map(io::Error::kind), Some(io::ErrorKind::NotFound));
+
+        // the error of the Python parser is kept at the end of the chain, there are more
+        // broken lines than the parser tries to fix
+        let broken = "def broken(:\n".repeat(5);
+        let err = SourceFile::load(Path::new("broken.py"), broken).unwrap_err();
+        assert!(matches!(err, ScoutError::Parse { .. }), "{:?}", err);
+        let mut chain = err.source();
+        while let Some(source) = chain.and_then(|err| err.downcast_ref::<ScoutError>()) {
+            chain = source.source();
+        }
+        assert!(chain.is_some_and(|err| err.is::<ParseError>()), "{:?}", err);
+    }
+}
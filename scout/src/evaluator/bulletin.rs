@@ -1,6 +1,7 @@
+use crate::source::{AutoExec, NativeFormat};
 use crate::visitors::Location;
 
-use super::Functionality;
+use super::{DensityContribution, Functionality, IndicatorKind, Rule};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -12,6 +13,42 @@ pub enum BulletinReason {
     DynamicImport,
     #[serde(rename = "canary")]
     Canary(String),
+    NetworkIndicator(IndicatorKind),
+    SuspiciousLiteral,
+    SuspiciousArgument,
+    SuspiciousWrite,
+    SuspiciousCall,
+    /// Network activity after credentials were accessed at the given line
+    ReadThenSend(usize),
+    /// A `!shell` line or a process magic in a notebook cell, with the magic if any
+    ShellCommand(Option<String>),
+    /// An extension module, with the identifiers of what was flagged inside it
+    BinaryArtifact(NativeFormat, Vec<String>),
+    /// An extension module that is imported instead of the given Python module
+    ShadowedModule(String),
+}
+
+impl BulletinReason {
+    /// The reason in the JSON output, and the rule id of bulletins that aren't raised by
+    /// a rule in a rule set.
+    pub fn code(&self) -> &'static str {
+        match self {
+            BulletinReason::SuspiciousImport => "suspicious_import",
+            BulletinReason::SuspiciousFunction => "suspicious_function",
+            BulletinReason::ImportInsideFunction => "import_inside_function",
+            BulletinReason::DynamicImport => "dynamic_import",
+            BulletinReason::Canary(_) => "canary",
+            BulletinReason::NetworkIndicator(_) => "network_indicator",
+            BulletinReason::SuspiciousLiteral => "suspicious_literal",
+            BulletinReason::SuspiciousArgument => "suspicious_argument",
+            BulletinReason::SuspiciousWrite => "suspicious_write",
+            BulletinReason::SuspiciousCall => "suspicious_call",
+            BulletinReason::ReadThenSend(_) => "read_then_send",
+            BulletinReason::ShellCommand(_) => "shell_command",
+            BulletinReason::BinaryArtifact(..) => "binary_artifact",
+            BulletinReason::ShadowedModule(_) => "shadowed_module",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,6 +59,14 @@ pub struct Bulletin {
     reason: BulletinReason,
     pub functionality: Option<Functionality>,
     pub threshold: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    density: Option<DensityContribution>,
+    /// Set when the bulletin is in code that runs on its own, e.g. on install.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auto_exec: Option<AutoExec>,
+    /// The id of the rule that raised the bulletin, see `Rule::id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rule: Option<String>,
 }
 
 pub type Bulletins = Vec<Bulletin>;
@@ -41,9 +86,44 @@ impl Bulletin {
             line: location.row(),
             functionality,
             threshold,
+            density: None,
+            auto_exec: None,
+            rule: None,
+        }
+    }
+
+    pub fn set_rule(&mut self, rule: &Rule) {
+        self.rule = Some(rule.id());
+    }
+
+    /// The id of the rule that raised the bulletin, or the reason for built-in checks.
+    pub fn rule_id(&self) -> String {
+        match &self.rule {
+            Some(rule) => rule.to_owned(),
+            None => self.reason.code().to_owned(),
         }
     }
 
+    pub fn reason_code(&self) -> &'static str {
+        self.reason.code()
+    }
+
+    pub fn density(&self) -> Option<&DensityContribution> {
+        self.density.as_ref()
+    }
+
+    pub fn set_density(&mut self, density: DensityContribution) {
+        self.density = Some(density);
+    }
+
+    pub fn auto_exec(&self) -> Option<&AutoExec> {
+        self.auto_exec.as_ref()
+    }
+
+    pub fn set_auto_exec(&mut self, auto_exec: AutoExec) {
+        self.auto_exec = Some(auto_exec);
+    }
+
     pub fn line(&self) -> usize {
         self.line
     }
@@ -53,6 +133,13 @@ impl Bulletin {
     }
 
     pub fn reason(&self) -> String {
+        match &self.auto_exec {
+            Some(auto_exec) => format!("{} (auto-executed: {})", self.base_reason(), auto_exec),
+            None => self.base_reason(),
+        }
+    }
+
+    fn base_reason(&self) -> String {
         match &self.reason {
             BulletinReason::SuspiciousImport => format!(
                 "The import '{}' is often used in malicious activity",
@@ -74,6 +161,50 @@ impl Bulletin {
             BulletinReason::Canary(message) => {
                 format!("Canary triggered: {}", message)
             },
+            BulletinReason::SuspiciousLiteral => format!(
+                "The string '{}' refers to a location often used in malicious activity",
+                self.identifier
+            ),
+            BulletinReason::SuspiciousArgument => format!(
+                "The call '{}' is often used in malicious activity",
+                self.identifier
+            ),
+            BulletinReason::SuspiciousWrite => format!(
+                "Writing to '{}' can make code run again later, e.g. on login or startup",
+                self.identifier
+            ),
+            BulletinReason::SuspiciousCall => format!(
+                "The call '{}' is often used in malicious activity",
+                self.identifier
+            ),
+            BulletinReason::ReadThenSend(line) => format!(
+                "'{}' may send the credentials accessed at line {} over the network",
+                self.identifier, line
+            ),
+            BulletinReason::ShellCommand(None) => {
+                format!("The notebook runs the shell command '{}'", self.identifier)
+            },
+            BulletinReason::ShellCommand(Some(magic)) => format!(
+                "The notebook runs '{}' with the %{} magic",
+                self.identifier, magic
+            ),
+            BulletinReason::NetworkIndicator(kind) => {
+                format!("Hardcoded {} '{}'", kind.as_str(), self.identifier)
+            },
+            BulletinReason::BinaryArtifact(format, found) => format!(
+                "The binary artifact '{}' ({}) contains {}",
+                self.identifier,
+                format,
+                found
+                    .iter()
+                    .map(|value| format!("'{}'", value))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            BulletinReason::ShadowedModule(module) => format!(
+                "The native module '{}' is imported instead of '{}', which hides its code",
+                self.identifier, module
+            ),
         }
     }
 
@@ -1,52 +1,125 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
-use std::str::FromStr;
 
-use serde::Deserialize;
+use aho_corasick::AhoCorasick;
+use serde::{Deserialize, Serialize};
 
-use crate::utils;
+use super::canary_generator;
 
-use crate::Result;
+use crate::{Result, ScoutError};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CanaryInfo {
     pub identifier: String,
     pub transform: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug)]
 pub struct Canaries {
-    canaries: HashMap<String, CanaryInfo>,
+    canaries: BTreeMap<String, CanaryInfo>,
+    /// Searches for every key at once, pattern ids follow the order of `canaries`.
+    matcher: AhoCorasick,
 }
 
 impl Canaries {
     pub const DEFAULT_CANARY_FILE: &'static str = "canary.json";
     const DEFAULT_CANARIES: &'static str = include_str!("canary.json");
 
-    fn load_canaries(canary_path: &Option<String>) -> Result<HashMap<String, CanaryInfo>> {
-        let canaries: String = match canary_path {
-            Some(canary_path) => {
-                let path = PathBuf::from_str(canary_path.as_str())?;
-                trace!("Loading canaries from: '{}'", &canary_path);
-                utils::load_from_file(path)?
+    fn load_canaries(canary_path: &Option<String>) -> Result<BTreeMap<String, CanaryInfo>> {
+        let path = canary_path.as_ref().map(PathBuf::from);
+        let canaries: String = match &path {
+            Some(path) => {
+                trace!("Loading canaries from: '{}'", path.display());
+                std::fs::read_to_string(path).map_err(|err| ScoutError::Canaries {
+                    path: Some(path.to_owned()),
+                    source: Box::new(err),
+                })?
             }
             None => {
-                trace!("Using default ruleset: '{}'", Canaries::DEFAULT_CANARY_FILE);
+                trace!(
+                    "Using default canaries: '{}'",
+                    Canaries::DEFAULT_CANARY_FILE
+                );
                 Canaries::DEFAULT_CANARIES.to_owned()
             }
         };
 
-        let canaries: HashMap<String, CanaryInfo> = serde_json::from_str(canaries.as_str())?;
-        Ok(canaries)
+        serde_json::from_str(canaries.as_str()).map_err(|err| ScoutError::Canaries {
+            path,
+            source: Box::new(err),
+        })
+    }
+
+    fn from_table(canaries: BTreeMap<String, CanaryInfo>) -> Self {
+        let matcher = AhoCorasick::new(canaries.keys());
+        Self { canaries, matcher }
     }
 
     pub fn new(canary_path: &Option<String>) -> Result<Self> {
-        Ok(Self {
-            canaries: Canaries::load_canaries(canary_path)?,
-        })
+        Ok(Canaries::from_table(Canaries::load_canaries(canary_path)?))
+    }
+
+    /// Builds a table by applying every transform to each of the keywords.
+    pub fn generate(keywords: &[String]) -> Result<Self> {
+        Ok(Canaries::from_table(
+            canary_generator::generate(keywords)?.into_iter().collect(),
+        ))
+    }
+
+    /// The keywords the built-in canary table was made from.
+    pub fn default_keywords() -> Result<Vec<String>> {
+        let mut keywords: Vec<String> = Canaries::new(&None)?
+            .canaries
+            .into_values()
+            .map(|info| info.identifier)
+            .collect();
+        keywords.sort();
+        keywords.dedup();
+        Ok(keywords)
     }
 
-    pub fn get_canaries(&self) -> &HashMap<String, CanaryInfo> {
+    pub fn get_canaries(&self) -> &BTreeMap<String, CanaryInfo> {
         &self.canaries
     }
+
+    /// Every canary whose key appears anywhere in the string, each reported once. Keys
+    /// can overlap, e.g. when one key is the start of another, so all of them are found.
+    pub fn find(&self, haystack: &str) -> Vec<(&String, &CanaryInfo)> {
+        let mut ids: Vec<usize> = self
+            .matcher
+            .find_overlapping_iter(haystack)
+            .map(|m| m.pattern())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.is_empty() {
+            return vec![];
+        }
+
+        let entries: Vec<(&String, &CanaryInfo)> = self.canaries.iter().collect();
+        ids.into_iter().map(|id| entries[id]).collect()
+    }
+
+    /// The table in the same format as the canary files.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.canaries)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Canaries;
+
+    #[test]
+    fn test_find_keys_inside_strings() {
+        let canaries = Canaries::generate(&[String::from("import")]).unwrap();
+
+        // base64 of "import os", offset by padding that isn't part of the payload
+        let found = canaries.find("padding=aW1wb3J0IG9z");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1.identifier, "import");
+        assert_eq!(found[0].1.transform, "t_b64encode");
+
+        assert!(canaries.find("nothing to see here").is_empty());
+    }
 }
@@ -0,0 +1,296 @@
+use std::io::Write;
+
+use flate2::{write::ZlibEncoder, Compression, GzBuilder};
+
+use super::canary::CanaryInfo;
+use crate::Result;
+
+const B32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const B64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// The alphabet used by Python's `base64.b85encode`
+const B85_ALPHABET: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!#$%&()*+-;<=>?@^_`{|}~";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    B16,
+    B32,
+    B64,
+    B85,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 4] = [Encoding::B16, Encoding::B32, Encoding::B64, Encoding::B85];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Encoding::B16 => "b16",
+            Encoding::B32 => "b32",
+            Encoding::B64 => "b64",
+            Encoding::B85 => "b85",
+        }
+    }
+
+    /// Encodes without any padding, the same as Python's `base64` module minus the `=`.
+    fn encode(&self, data: &[u8]) -> String {
+        match self {
+            Encoding::B16 => data.iter().map(|b| format!("{:02X}", b)).collect(),
+            Encoding::B32 => encode_bits(data, 5, B32_ALPHABET),
+            Encoding::B64 => encode_bits(data, 6, B64_ALPHABET),
+            Encoding::B85 => encode_b85(data),
+        }
+    }
+}
+
+fn encode_bits(data: &[u8], bits: u32, alphabet: &[u8]) -> String {
+    let mut out = String::new();
+    let mut buffer: u32 = 0;
+    let mut buffered: u32 = 0;
+
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        buffered += 8;
+        while buffered >= bits {
+            buffered -= bits;
+            out.push(alphabet[((buffer >> buffered) & ((1 << bits) - 1)) as usize] as char);
+        }
+    }
+    if buffered > 0 {
+        out.push(alphabet[((buffer << (bits - buffered)) & ((1 << bits) - 1)) as usize] as char);
+    }
+    out
+}
+
+fn encode_b85(data: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in data.chunks(4) {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        let mut value = u32::from_be_bytes(word);
+
+        let mut digits = [0u8; 5];
+        for digit in digits.iter_mut().rev() {
+            *digit = B85_ALPHABET[(value % 85) as usize];
+            value /= 85;
+        }
+        // a partial chunk is padded with zeros and only needs one more digit than it has bytes
+        out.extend(digits[..chunk.len() + 1].iter().map(|&d| d as char));
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrapping {
+    None,
+    Zlib,
+    Gzip,
+}
+
+/// One way a keyword can be hidden, e.g. `t_zlib_b64` for base64 encoded zlib data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transform {
+    pub encoding: Encoding,
+    pub wrapping: Wrapping,
+    /// The keyword is reversed before encoding
+    pub reverse_input: bool,
+    /// The encoded string is reversed
+    pub reverse_output: bool,
+}
+
+impl Transform {
+    /// The same family of transforms as the built-in canary table.
+    pub fn all() -> Vec<Transform> {
+        let mut transforms: Vec<Transform> = vec![];
+        for encoding in Encoding::ALL {
+            for (reverse_input, reverse_output) in [(false, false), (false, true), (true, false)] {
+                transforms.push(Transform {
+                    encoding,
+                    wrapping: Wrapping::None,
+                    reverse_input,
+                    reverse_output,
+                });
+            }
+        }
+        for wrapping in [Wrapping::Zlib, Wrapping::Gzip] {
+            for encoding in Encoding::ALL {
+                transforms.push(Transform {
+                    encoding,
+                    wrapping,
+                    reverse_input: false,
+                    reverse_output: false,
+                });
+            }
+        }
+        transforms
+    }
+
+    pub fn name(&self) -> String {
+        match self.wrapping {
+            Wrapping::Zlib => format!("t_zlib_{}", self.encoding.name()),
+            Wrapping::Gzip => format!("t_gzip_{}", self.encoding.name()),
+            Wrapping::None => format!(
+                "t_{}{}encode{}",
+                if self.reverse_input { "rev_" } else { "" },
+                self.encoding.name(),
+                if self.reverse_output { "_rev" } else { "" }
+            ),
+        }
+    }
+
+    fn apply(&self, data: &[u8]) -> Result<String> {
+        let mut data = data.to_vec();
+        if self.reverse_input {
+            data.reverse();
+        }
+
+        let data = match self.wrapping {
+            Wrapping::None => data,
+            Wrapping::Zlib => {
+                let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+            Wrapping::Gzip => {
+                // the header has a timestamp, so it is fixed to zero to make tables reproducible
+                let mut encoder = GzBuilder::new()
+                    .mtime(0)
+                    .operating_system(255)
+                    .write(vec![], Compression::best());
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+        };
+
+        Ok(self.encoding.encode(&data))
+    }
+
+    /// The longest part of the transformed keyword that stays the same no matter what
+    /// follows the keyword, so any string starting with the keyword starts with the key.
+    /// For reversed output the key is where the transformed string ends instead.
+    pub fn key(&self, keyword: &str) -> Result<String> {
+        let keyword = keyword.as_bytes();
+
+        let mut prefix: Option<String> = None;
+        for suffix in continuations() {
+            let data = match self.reverse_input {
+                // the keyword ends the string when it is reversed before encoding
+                true => [suffix.as_slice(), keyword].concat(),
+                false => [keyword, suffix.as_slice()].concat(),
+            };
+            let encoded = self.apply(&data)?;
+
+            prefix = Some(match prefix {
+                None => encoded,
+                Some(prefix) => common_prefix(&prefix, &encoded).to_owned(),
+            });
+        }
+
+        let prefix = prefix.unwrap_or_default();
+        Ok(match self.reverse_output {
+            true => prefix.chars().rev().collect(),
+            false => prefix,
+        })
+    }
+}
+
+/// Text that could follow a keyword, every printable character among it. Only short
+/// text is used, as compressors pick a different block type for long or binary data.
+fn continuations() -> Vec<Vec<u8>> {
+    let mut continuations: Vec<Vec<u8>> = vec![vec![]];
+    for byte in (b' '..=b'~').chain([b'\t', b'\n']) {
+        continuations.push(vec![byte]);
+        continuations.push(vec![byte, b'\n']);
+    }
+    continuations.push(b"(\"http://127.0.0.1:8080\")\n".to_vec());
+    continuations.push(b" = __import__('os').system\n".to_vec());
+    continuations
+}
+
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((idx, _), _)| idx);
+    &a[..len]
+}
+
+/// Builds a canary table entry for every keyword and transform.
+/// Keys shared by several keywords keep the first keyword.
+pub fn generate(keywords: &[String]) -> Result<Vec<(String, CanaryInfo)>> {
+    let mut canaries: Vec<(String, CanaryInfo)> = vec![];
+
+    for transform in Transform::all() {
+        for keyword in keywords {
+            let key = transform.key(keyword)?;
+            if key.is_empty() || canaries.iter().any(|(k, _)| k == &key) {
+                warn!(
+                    "Skipping canary for '{}' using '{}', the key is not unique",
+                    keyword,
+                    transform.name()
+                );
+                continue;
+            }
+
+            canaries.push((
+                key,
+                CanaryInfo {
+                    identifier: keyword.to_owned(),
+                    transform: transform.name(),
+                },
+            ));
+        }
+    }
+
+    Ok(canaries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, Encoding, Transform};
+
+    #[test]
+    fn test_encodings_match_python() {
+        let data = b"import os";
+        assert_eq!(Encoding::B16.encode(data), "696D706F7274206F73");
+        assert_eq!(Encoding::B32.encode(data), "NFWXA33SOQQG64Y");
+        assert_eq!(Encoding::B64.encode(data), "aW1wb3J0IG9z");
+        assert_eq!(Encoding::B85.encode(data), "X>D+Ca&#bXa{");
+    }
+
+    #[test]
+    fn test_keys_match_builtin_table() {
+        let names: Vec<String> = Transform::all().iter().map(|t| t.name()).collect();
+        assert_eq!(names.len(), 20);
+        assert!(names.contains(&String::from("t_rev_b85encode")));
+        assert!(names.contains(&String::from("t_b16encode_rev")));
+
+        let key = |name: &str, keyword: &str| {
+            let transform = Transform::all()
+                .into_iter()
+                .find(|t| t.name() == name)
+                .unwrap();
+            transform.key(keyword).unwrap()
+        };
+
+        // compressed keys are the same as in the built-in table, and gzip only differs in
+        // the timestamp of the header
+        assert_eq!(key("t_zlib_b16", "__import__"), "789C8B8FCFCC2DC82F2A898F");
+        assert_eq!(key("t_zlib_b64", "__import__"), "eJyLj8/MLcgvKomP");
+        assert_eq!(key("t_zlib_b85", "import"), "c$~}3Eyyn_");
+        assert_eq!(key("t_gzip_b64", "import"), "H4sIAAAAAAAC/8vMLcgvK");
+        // plain encodings can be a character longer than the built-in keys
+        assert_eq!(key("t_b64encode", "import"), "aW1wb3J0");
+        assert_eq!(key("t_b16encode_rev", "import"), "4727F607D696");
+        assert_eq!(key("t_rev_b32encode", "import"), "ORZG64DNN");
+    }
+
+    #[test]
+    fn test_generate() {
+        let canaries = generate(&[String::from("discord.com/api/webhooks")]).unwrap();
+        assert_eq!(canaries.len(), 20);
+        assert!(canaries
+            .iter()
+            .all(|(key, info)| key.len() > 10 && info.identifier == "discord.com/api/webhooks"));
+    }
+}
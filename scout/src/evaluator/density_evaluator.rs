@@ -1,8 +1,8 @@
-use std::{collections::HashMap, f64::consts::PI, hash::Hash};
+use std::{collections::BTreeMap, f64::consts::PI, hash::Hash};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{evaluator::Hotspot, Config};
+use crate::{evaluator::Hotspot, visitors::Definition, Config};
 
 fn gaussian_density(x: f64, mu: f64, variance: f64) -> f64 {
     let sigma = variance.sqrt();
@@ -15,11 +15,16 @@ fn mult(arr: &Vec<f64>, mul: &f64, target: &mut Vec<f64>) {
     }
 }
 
+/// Gaussians are only evaluated this many standard deviations away from the line they are
+/// centered on. Past this point the contribution is far below any threshold we compare against.
+const SIGMA_CUTOFF: f64 = 6.0;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Field {
     multiplier: f64,
     tfidf_weight: f64,
     loc: usize,
+    resolution: f64,
     x: Vec<f64>,
     y: Vec<f64>,
 }
@@ -41,36 +46,50 @@ impl Field {
             multiplier: *multiplier,
             tfidf_weight: 0.4,
             loc,
+            resolution,
             x,
             y,
         }
     }
 
+    pub fn get_x(&self) -> &Vec<f64> {
+        &self.x
+    }
+
+    pub fn get_y(&self) -> &Vec<f64> {
+        &self.y
+    }
+
     fn tfidf_weight(tfidf_value: f64, weight: f64) -> f64 {
         // (1.0f64 - weight) * (current_value - tfidf_value) + tfidf_value
         1.0f64 - (1.0f64 - tfidf_value) * weight
     }
 
-    fn add_density(&mut self, line: f64, variance: f64, tfidf_multiplier: f64, tfidf_weight: f64) {
-        for (y, x) in self.y.iter_mut().zip(self.x.iter()) {
-            *y += gaussian_density(*x, line, variance) * self.multiplier;
-            *y *= Field::tfidf_weight(tfidf_multiplier, tfidf_weight);
-        }
+    /// The range of indices whose x-value lies within `radius` of `center`.
+    fn index_range(&self, center: f64, radius: f64) -> std::ops::Range<usize> {
+        let len = self.x.len();
+        let low = ((center - radius) / self.resolution).ceil().max(0.0) as usize;
+        let high = (((center + radius) / self.resolution).floor() + 1.0).max(0.0) as usize;
+        low.min(len)..high.min(len)
     }
 
-    // get maximum Y value in the range [startX, endX]
-    fn get_max_y(&self, start: f64, end: f64) -> Option<f64> {
-        if self.y.is_empty() {
-            return None;
+    /// Adds a gaussian centered on `line`, scaled by the TF-IDF weight of the identifier
+    /// that triggered it. Every addition only affects its own curve, so the resulting
+    /// field does not depend on the order densities are added in.
+    fn add_density(&mut self, line: f64, variance: f64, tfidf_multiplier: f64, tfidf_weight: f64) {
+        let radius = SIGMA_CUTOFF * variance.sqrt();
+        let weight = Field::tfidf_weight(tfidf_multiplier, tfidf_weight);
+        for idx in self.index_range(line, radius) {
+            self.y[idx] += gaussian_density(self.x[idx], line, variance) * self.multiplier * weight;
         }
+    }
 
-        let (_, maxy) = self
-            .x
-            .iter()
-            .zip(self.y.iter())
-            .filter(|(&x, _)| x >= start && x <= end)
-            .max_by(|(_, y1), (_, y2)| y1.partial_cmp(y2).unwrap())?;
-        Some(*maxy)
+    /// The maximum y-value for x in the range [start, end].
+    fn peak_between(&self, start: f64, end: f64) -> f64 {
+        let center = (start + end) / 2.0;
+        self.index_range(center, (end - start) / 2.0)
+            .map(|idx| self.y[idx])
+            .fold(0.0, f64::max)
     }
 
     fn hotspots(&self, threshold: f64) -> Vec<Hotspot> {
@@ -79,23 +98,24 @@ impl Field {
         let mut curr: Hotspot = Hotspot::new();
         let mut in_group: bool = false;
 
-        for (y, x) in self.y.iter().zip(self.x.iter()) {
-            if *y > threshold && !in_group {
+        for (&y, x) in self.y.iter().zip(self.x.iter()) {
+            if y > threshold && !in_group {
                 in_group = true;
                 curr.startx = *x;
-            } else if *y <= threshold && in_group {
+                curr.peak = y;
+            } else if y <= threshold && in_group {
                 in_group = false;
                 curr.endx = *x;
-                curr.peak = self.get_max_y(curr.startx, curr.endx).unwrap_or(0.0);
                 spots.push(curr);
                 curr = Hotspot::new();
+            } else if in_group && y > curr.peak {
+                curr.peak = y;
             }
         }
 
         if in_group && !self.x.is_empty() {
             let lastx = self.x.last().unwrap();
             curr.endx = *lastx;
-            curr.peak = self.get_max_y(curr.startx, curr.endx).unwrap_or(0.0);
             spots.push(curr);
         }
 
@@ -103,7 +123,7 @@ impl Field {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum FieldType {
     Functions,
     Imports,
@@ -111,9 +131,23 @@ pub enum FieldType {
     Strings,
 }
 
+/// How a single bulletin affected the density fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DensityContribution {
+    pub field: FieldType,
+    /// The TF-IDF value of the identifier that triggered the bulletin
+    pub tfidf_multiplier: f64,
+    /// The configured TF-IDF weight for the field
+    pub tfidf_weight: f64,
+    /// The factor the gaussian was scaled by after combining the two above
+    pub weight: f64,
+    /// The height this bulletin alone adds to the combined field at its own line
+    pub contribution: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DensityEvaluator {
-    fields: HashMap<FieldType, Field>,
+    fields: BTreeMap<FieldType, Field>,
 }
 
 impl DensityEvaluator {
@@ -122,13 +156,13 @@ impl DensityEvaluator {
     const HOTSPOT_THRESHOLD: f64 = 0.01;
 
     pub fn new(loc: usize, config: &Config) -> Self {
-        let mut mult_map: HashMap<FieldType, f64> = HashMap::new();
+        let mut mult_map: BTreeMap<FieldType, f64> = BTreeMap::new();
         mult_map.insert(FieldType::Functions, config.fw_functions);
         mult_map.insert(FieldType::Imports, config.fw_imports);
         mult_map.insert(FieldType::Behavior, config.fw_behavior);
         mult_map.insert(FieldType::Strings, config.fw_strings);
 
-        let mut fields: HashMap<FieldType, Field> = HashMap::new();
+        let mut fields: BTreeMap<FieldType, Field> = BTreeMap::new();
 
         fields.insert(
             FieldType::Functions,
@@ -195,12 +229,13 @@ impl DensityEvaluator {
             multiplier: multiplier,
             tfidf_weight: multiplier,
             loc: funcs.loc,
+            resolution: funcs.resolution,
             x: combined_x,
             y: combined_y,
         }
     }
 
-    pub fn get_fields(&self) -> &HashMap<FieldType, Field> {
+    pub fn get_fields(&self) -> &BTreeMap<FieldType, Field> {
         &self.fields
     }
 
@@ -210,7 +245,7 @@ impl DensityEvaluator {
         row: usize,
         custom_multiplier: f64,
         tfidf_weight: f64,
-    ) {
+    ) -> DensityContribution {
         let field = self.fields.get_mut(&field_type).unwrap();
         let line: f64 = row as f64;
         field.add_density(
@@ -219,6 +254,39 @@ impl DensityEvaluator {
             custom_multiplier,
             tfidf_weight,
         );
+
+        // the combined field applies the field multiplier a second time
+        let weight = Field::tfidf_weight(custom_multiplier, tfidf_weight);
+        let height = gaussian_density(line, line, DensityEvaluator::VARIANCE);
+        DensityContribution {
+            field: field_type,
+            tfidf_multiplier: custom_multiplier,
+            tfidf_weight,
+            weight,
+            contribution: height * field.multiplier * weight * field.multiplier,
+        }
+    }
+
+    /// Scales an earlier contribution by `factor`, by adding the difference to its field.
+    pub fn boost_density(
+        &mut self,
+        density: &DensityContribution,
+        row: usize,
+        factor: f64,
+    ) -> DensityContribution {
+        let extra = density.weight * (factor - 1.0f64);
+        // a multiplier of `extra` with a TF-IDF weight of one gives a weight of `extra`
+        self.add_density(density.field, row, extra, 1.0f64);
+
+        DensityContribution {
+            weight: density.weight * factor,
+            contribution: density.contribution * factor,
+            ..density.clone()
+        }
+    }
+
+    pub fn hotspot_threshold() -> f64 {
+        DensityEvaluator::HOTSPOT_THRESHOLD
     }
 
     pub fn calculate_combined_field(&self) -> Field {
@@ -229,11 +297,20 @@ impl DensityEvaluator {
         let field = self.calculate_combined_field();
         field.hotspots(DensityEvaluator::HOTSPOT_THRESHOLD)
     }
+
+    /// One hotspot per definition, scored by the peak of the combined field inside it.
+    pub fn definition_hotspots(&self, definitions: &[&Definition]) -> Vec<Hotspot> {
+        let field = self.calculate_combined_field();
+        definitions
+            .iter()
+            .map(|d| Hotspot::from_definition(d, field.peak_between(d.start as f64, d.end as f64)))
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{gaussian_density, mult};
+    use super::{gaussian_density, mult, Field};
 
     #[test]
     fn test_gaussian_density() {
@@ -263,4 +340,74 @@ mod tests {
             vec![0.0, 4.0, 8.0, 12.0, 16.0, 20.0, 24.0, 28.0, 32.0, 36.0, 40.0]
         );
     }
+
+    /// Evaluates every gaussian over the whole field, kept as a reference for the sparse field.
+    fn dense_reference(field: &Field, adds: &[(f64, f64, f64)], variance: f64) -> Vec<f64> {
+        let mut y: Vec<f64> = vec![0.0; field.x.len()];
+        for (line, tfidf_multiplier, tfidf_weight) in adds {
+            let weight = Field::tfidf_weight(*tfidf_multiplier, *tfidf_weight);
+            for (y, x) in y.iter_mut().zip(field.x.iter()) {
+                *y += gaussian_density(*x, *line, variance) * field.multiplier * weight;
+            }
+        }
+        y
+    }
+
+    fn adds() -> Vec<(f64, f64, f64)> {
+        vec![
+            (3.0, 1.0, 1.0),
+            (10.0, 0.4, 1.0),
+            (12.0, 0.05, 1.0),
+            (150.0, 1.0, 0.5),
+            (151.0, 0.8, 1.0),
+            (399.0, 0.3, 1.0),
+        ]
+    }
+
+    #[test]
+    fn test_sparse_field_matches_dense() {
+        let variance = 5.0;
+        let adds = adds();
+
+        let mut field = Field::new(&1.0, 400, 0.5);
+        for (line, tfidf_multiplier, tfidf_weight) in adds.iter() {
+            field.add_density(*line, variance, *tfidf_multiplier, *tfidf_weight);
+        }
+
+        let expected = dense_reference(&field, &adds, variance);
+        for (actual, expected) in field.y.iter().zip(expected.iter()) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+
+        let mut dense = Field::new(&1.0, 400, 0.5);
+        dense.y = expected;
+        let sparse_spots = field.hotspots(0.01);
+        let dense_spots = dense.hotspots(0.01);
+        assert_eq!(sparse_spots.len(), dense_spots.len());
+        for (a, b) in sparse_spots.iter().zip(dense_spots.iter()) {
+            assert_eq!(a.startx, b.startx);
+            assert_eq!(a.endx, b.endx);
+            assert!((a.peak - b.peak).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_field_is_order_independent() {
+        let variance = 5.0;
+        let adds = adds();
+
+        let mut forward = Field::new(&1.0, 400, 0.5);
+        for (line, tfidf_multiplier, tfidf_weight) in adds.iter() {
+            forward.add_density(*line, variance, *tfidf_multiplier, *tfidf_weight);
+        }
+
+        let mut backward = Field::new(&1.0, 400, 0.5);
+        for (line, tfidf_multiplier, tfidf_weight) in adds.iter().rev() {
+            backward.add_density(*line, variance, *tfidf_multiplier, *tfidf_weight);
+        }
+
+        for (a, b) in forward.y.iter().zip(backward.y.iter()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
 }
@@ -1,36 +1,123 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use regex::Regex;
+
 use crate::source::SourceFile;
-use crate::visitors::{CallEntry, ImportEntry};
-use crate::{utils, Config, SourceAnalysis};
+use crate::visitors::{CallEntry, ImportEntry, Location, VariableType};
+use crate::{Config, ScoutError, SourceAnalysis};
 
 use super::canary::Canaries;
 use super::density_evaluator::FieldType;
-use super::{Bulletin, BulletinReason, Bulletins, DensityEvaluator, Rule, RuleSet};
+use super::indicators::{self, Indicator, IndicatorKind};
+use super::{Bulletin, BulletinReason, Bulletins, DensityEvaluator, Functionality, Rule, RuleSet};
 
 use crate::Result;
 
+/// At most this many findings are listed in the summary of a binary artifact.
+const MAX_ARTIFACT_FINDINGS: usize = 10;
+
 #[derive(Debug)]
 // This is a link between the rule and the rule set for reverse lookups
 // TODO: should probably re-design this aspect of it
 pub struct RuleEntry<'r>(&'r Rule, &'r RuleSet);
 
+/// Adds the bulletin along with its density, and records how much it contributed.
+fn add_bulletin(
+    mut bulletin: Bulletin,
+    field_type: FieldType,
+    tfidf_multiplier: f64,
+    tfidf_weight: f64,
+    de: &mut DensityEvaluator,
+    bulletins: &mut Bulletins,
+) {
+    let density = de.add_density(field_type, bulletin.line(), tfidf_multiplier, tfidf_weight);
+    bulletin.set_density(density);
+    bulletins.push(bulletin);
+}
+
+/// Lowercases a string and uses forward slashes, so it can be matched as a path on any
+/// platform. A leading `/` is added so a pattern like `/.ssh` also matches a lone `.ssh`.
+fn normalize_path(value: &str) -> String {
+    format!("/{}", normalize_pattern(value))
+}
+
+fn normalize_pattern(pattern: &str) -> String {
+    pattern.to_lowercase().replace('\\', "/")
+}
+
+/// The regex for a normalized pattern with a `*`, which matches anything inside a path
+/// segment. A segment with a `*` has to match a whole segment of the path, so
+/// `site-packages/*.pth` matches `/lib/site-packages/init.pth`, but not `model.pth` or
+/// `/site-packages/init.pth.bak`. Patterns without a `*` are matched as substrings.
+fn path_glob(pattern: &str) -> Result<Option<Regex>> {
+    if !pattern.contains('*') {
+        return Ok(None);
+    }
+    let segments: Vec<&str> = pattern.split('/').collect();
+    let last = segments.len() - 1;
+    let regex: String = segments
+        .iter()
+        .enumerate()
+        .map(|(idx, segment)| {
+            if !segment.contains('*') {
+                return regex::escape(segment);
+            }
+            let glob: Vec<String> = segment.split('*').map(regex::escape).collect();
+            let start = if idx == 0 { "(?:^|/)" } else { "" };
+            let end = if idx == last { "(?:/|$)" } else { "" };
+            format!("{}{}{}", start, glob.join("[^/]*"), end)
+        })
+        .collect::<Vec<String>>()
+        .join("/");
+    match Regex::new(&regex) {
+        Ok(regex) => Ok(Some(regex)),
+        Err(err) => Err(ScoutError::Rules {
+            path: None,
+            source: Box::new(err),
+        }),
+    }
+}
+
 #[derive(Debug)]
 pub struct Evaluator {
     rule_sets: Vec<RuleSet>,
     canaries: Canaries,
+    /// The compiled `Write` patterns with a `*`, by pattern
+    write_globs: HashMap<String, Regex>,
 
     /// Enable or disable the use of the multiplier in adding curves.
     opt_enable_multiplier: bool,
 }
 
 impl Evaluator {
-    pub fn new(rule_sets: Vec<RuleSet>) -> Result<Self> {
+    pub fn new(rule_sets: Vec<RuleSet>, canaries: Canaries) -> Result<Self> {
+        let mut write_globs: HashMap<String, Regex> = HashMap::new();
+        for rule in rule_sets.iter().flat_map(|set| set.rules.iter()) {
+            if let Rule::Write(_, ident, _, _) = rule {
+                if let Some(regex) = path_glob(&normalize_pattern(ident))? {
+                    write_globs.insert(ident.to_owned(), regex);
+                }
+            }
+        }
+
         Ok(Self {
             rule_sets,
-            canaries: Canaries::new(&None)?,
+            canaries,
+            write_globs,
             opt_enable_multiplier: true,
         })
     }
 
+    /// Whether the path written to matches the pattern of a `Write` rule.
+    fn matches_write(&self, target: &str, pattern: &str) -> bool {
+        let path = normalize_path(target);
+        match self.write_globs.get(pattern) {
+            Some(regex) => regex.is_match(&path),
+            None => path.contains(&normalize_pattern(pattern)),
+        }
+    }
+
     fn rule_check_module(
         &self,
         source: &SourceFile,
@@ -51,19 +138,21 @@ impl Evaluator {
                 };
                 debug!("TFIDF value for identifier {} set to {}", ident, multiplier);
 
-                let notif = Bulletin::new(
+                let mut notif = Bulletin::new(
                     ident.to_string(),
                     BulletinReason::SuspiciousImport,
                     entry.location,
                     Some(*func),
                     set.threshold,
                 );
-                bulletins.push(notif);
-                de.add_density(
+                notif.set_rule(rule);
+                add_bulletin(
+                    notif,
                     FieldType::Imports,
-                    entry.location.row(),
                     multiplier,
                     config.tw_imports,
+                    de,
+                    bulletins,
                 );
                 *alerts += 1;
 
@@ -76,12 +165,13 @@ impl Evaluator {
                         None,
                         0.3f64,
                     );
-                    bulletins.push(notif);
-                    de.add_density(
+                    add_bulletin(
+                        notif,
                         FieldType::Imports,
-                        entry.location.row(),
                         1.0f64,
                         config.tw_imports,
+                        de,
+                        bulletins,
                     );
                     *alerts += 1;
                 }
@@ -101,15 +191,15 @@ impl Evaluator {
         config: &Config,
     ) {
         if let Rule::Function(func, ident, _name, _desc) = rule {
-            if utils::get_last_attr(entry.full_identifier.as_str()) == ident {
-                let notif = Bulletin::new(
+            if entry.is_call_to(ident) {
+                let mut notif = Bulletin::new(
                     entry.full_identifier.to_string(),
                     BulletinReason::SuspiciousFunction,
                     entry.location,
                     Some(*func),
                     set.threshold,
                 );
-                bulletins.push(notif);
+                notif.set_rule(rule);
 
                 let multiplier: f64 = if self.opt_enable_multiplier {
                     source
@@ -125,11 +215,13 @@ impl Evaluator {
                     multiplier
                 );
 
-                de.add_density(
+                add_bulletin(
+                    notif,
                     FieldType::Functions,
-                    entry.location.row(),
                     multiplier,
                     config.tw_functions,
+                    de,
+                    bulletins,
                 );
                 *alerts += 1;
             }
@@ -153,18 +245,378 @@ impl Evaluator {
                 None,
                 0.2f64,
             );
-            bulletins.push(notif);
-            de.add_density(
+            add_bulletin(
+                notif,
                 FieldType::Behavior,
-                entry.location.row(),
                 1.0f64,
                 config.tw_imports,
+                de,
+                bulletins,
             );
             *alerts += 1;
         }
     }
 
-    fn variable_check(
+    /// The string constants of the file, followed by the values of string variables,
+    /// which may have been built from several constants, and any notebook shell commands.
+    fn string_values(source: &SourceFile) -> Vec<(&str, Location)> {
+        let locations = source.variable_visitor.get_locations();
+
+        let mut variables: Vec<(&String, &VariableType)> =
+            source.variable_visitor.get_variables().iter().collect();
+        variables.sort_by_key(|(identifier, _)| *identifier);
+
+        let strings = source
+            .get_strings()
+            .iter()
+            .map(|entry| (entry.value.as_str(), entry.location));
+        let variables = variables.into_iter().filter_map(|(identifier, variable)| {
+            let value = variable.get_string()?;
+            let location = locations.get(identifier)?;
+            Some((value.as_str(), *location))
+        });
+
+        let commands = source.get_shell_commands().iter().map(|command| {
+            let location = Location {
+                row: command.line,
+                column: command.col,
+            };
+            (command.command.as_str(), location)
+        });
+
+        strings.chain(variables).chain(commands).collect()
+    }
+
+    /// Strings to match literal rules against, lowercased and with forward slashes. Path
+    /// joins are folded into one path, with `*` for the parts that can't be resolved, and
+    /// every value starts with a `/` so a rule like `/.ssh` also matches a lone `.ssh`.
+    fn literal_values(source: &SourceFile) -> Vec<(String, String, Location)> {
+        let joins = source
+            .get_entries()
+            .iter()
+            .filter(|entry| entry.full_identifier.ends_with("path.join"))
+            .map(|entry| {
+                let parts: Vec<&str> = entry
+                    .args
+                    .iter()
+                    .map(|arg| match arg {
+                        Some(VariableType::Str(part)) => part.as_str(),
+                        _ => "*",
+                    })
+                    .collect();
+                (parts.join("/"), entry.location)
+            });
+
+        Evaluator::string_values(source)
+            .into_iter()
+            .map(|(value, location)| (value.to_owned(), location))
+            .chain(joins)
+            .map(|(value, location)| (normalize_path(&value), value, location))
+            .collect()
+    }
+
+    /// `seen` holds the lines already reported for the rule set, so a string matching
+    /// several of its rules is only reported once.
+    #[allow(clippy::too_many_arguments)]
+    fn rule_check_literal(
+        &self,
+        values: &[(String, String, Location)],
+        seen: &mut HashSet<usize>,
+        rule: &Rule,
+        set: &RuleSet,
+        de: &mut DensityEvaluator,
+        bulletins: &mut Bulletins,
+        alerts: &mut i32,
+    ) {
+        if let Rule::Literal(func, ident, _name, _desc) = rule {
+            let pattern = normalize_pattern(ident);
+
+            for (normalized, value, location) in values {
+                if !normalized.contains(pattern.as_str()) || !seen.insert(location.row()) {
+                    continue;
+                }
+
+                let mut identifier: String = value.chars().take(80).collect();
+                if identifier.len() < value.len() {
+                    identifier.push_str("...");
+                }
+
+                let mut notif = Bulletin::new(
+                    identifier,
+                    BulletinReason::SuspiciousLiteral,
+                    *location,
+                    Some(*func),
+                    set.threshold,
+                );
+                notif.set_rule(rule);
+                add_bulletin(notif, FieldType::Strings, 1.0f64, 1.0f64, de, bulletins);
+                *alerts += 1;
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn rule_check_argument(
+        &self,
+        entry: &CallEntry,
+        rule: &Rule,
+        set: &RuleSet,
+        de: &mut DensityEvaluator,
+        bulletins: &mut Bulletins,
+        alerts: &mut i32,
+        config: &Config,
+    ) {
+        if let Rule::Argument(func, ident, _name, _desc) = rule {
+            if entry.arg_identifiers.contains(ident) {
+                let mut notif = Bulletin::new(
+                    format!("{}({})", entry.full_identifier, ident),
+                    BulletinReason::SuspiciousArgument,
+                    entry.location,
+                    Some(*func),
+                    set.threshold,
+                );
+                notif.set_rule(rule);
+                add_bulletin(
+                    notif,
+                    FieldType::Functions,
+                    1.0f64,
+                    config.tw_functions,
+                    de,
+                    bulletins,
+                );
+                *alerts += 1;
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn rule_check_write(
+        &self,
+        entry: &CallEntry,
+        rule: &Rule,
+        set: &RuleSet,
+        de: &mut DensityEvaluator,
+        bulletins: &mut Bulletins,
+        alerts: &mut i32,
+        config: &Config,
+    ) {
+        if let Rule::Write(func, ident, _name, _desc) = rule {
+            if let Some(target) = entry.get_write_target() {
+                if self.matches_write(target, ident) {
+                    let mut notif = Bulletin::new(
+                        target.to_string(),
+                        BulletinReason::SuspiciousWrite,
+                        entry.location,
+                        Some(*func),
+                        set.threshold,
+                    );
+                    notif.set_rule(rule);
+                    add_bulletin(
+                        notif,
+                        FieldType::Functions,
+                        1.0f64,
+                        config.tw_functions,
+                        de,
+                        bulletins,
+                    );
+                    *alerts += 1;
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn rule_check_call(
+        &self,
+        entry: &CallEntry,
+        rule: &Rule,
+        set: &RuleSet,
+        de: &mut DensityEvaluator,
+        bulletins: &mut Bulletins,
+        alerts: &mut i32,
+        config: &Config,
+    ) {
+        if let Rule::Call(func, ident, patterns, _name, _desc) = rule {
+            if !entry.is_call_to(ident) {
+                return;
+            }
+
+            let args: Vec<String> = entry
+                .get_arg_strings()
+                .iter()
+                .map(|arg| normalize_path(arg))
+                .collect();
+            let matches = patterns.iter().all(|pattern| {
+                let pattern = normalize_pattern(pattern);
+                args.iter().any(|arg| match pattern.strip_suffix('$') {
+                    Some(end) => arg.ends_with(end),
+                    None => arg.contains(pattern.as_str()),
+                })
+            });
+
+            if matches {
+                let mut notif = Bulletin::new(
+                    format!("{}({})", entry.full_identifier, patterns.join(", ")),
+                    BulletinReason::SuspiciousCall,
+                    entry.location,
+                    Some(*func),
+                    set.threshold,
+                );
+                notif.set_rule(rule);
+                add_bulletin(
+                    notif,
+                    FieldType::Functions,
+                    1.0f64,
+                    config.tw_functions,
+                    de,
+                    bulletins,
+                );
+                *alerts += 1;
+            }
+        }
+    }
+
+    /// Code that runs without being called, e.g. on install or at interpreter startup, is
+    /// weighted higher and labelled with how it is run.
+    fn auto_exec_check(
+        &self,
+        source: &SourceFile,
+        de: &mut DensityEvaluator,
+        bulletins: &mut Bulletins,
+        config: &Config,
+    ) {
+        let auto_exec = match source.get_auto_exec() {
+            Some(auto_exec) => auto_exec,
+            None => return,
+        };
+
+        for bulletin in bulletins.iter_mut() {
+            if !source.is_auto_executed(bulletin.line()) {
+                continue;
+            }
+            if let Some(density) = bulletin.density() {
+                let boosted = de.boost_density(density, bulletin.line(), config.auto_exec_weight);
+                bulletin.set_density(boosted);
+            }
+            bulletin.set_auto_exec(auto_exec.clone());
+        }
+    }
+
+    /// Shell commands in notebook cells run processes, just like `os.system` would.
+    fn shell_check(
+        &self,
+        source: &SourceFile,
+        de: &mut DensityEvaluator,
+        bulletins: &mut Bulletins,
+        alerts: &mut i32,
+    ) {
+        for command in source.get_shell_commands() {
+            let notif = Bulletin::new(
+                command.command.to_string(),
+                BulletinReason::ShellCommand(command.magic.clone()),
+                Location {
+                    row: command.line,
+                    column: command.col,
+                },
+                Some(Functionality::Process),
+                0.2f64,
+            );
+            add_bulletin(notif, FieldType::Behavior, 1.0f64, 1.0f64, de, bulletins);
+            *alerts += 1;
+        }
+    }
+
+    /// Findings in a native extension module are summarised as a binary artifact, and a
+    /// module that shadows a Python file of the same name is reported on its own.
+    fn native_check(
+        &self,
+        source: &SourceFile,
+        de: &mut DensityEvaluator,
+        bulletins: &mut Bulletins,
+        alerts: &mut i32,
+    ) {
+        let artifact = match source.get_artifact() {
+            Some(artifact) => artifact,
+            None => return,
+        };
+        let name = Path::new(source.get_path())
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut seen: HashSet<&str> = HashSet::new();
+        let found: Vec<String> = bulletins
+            .iter()
+            .map(|b| b.identifier.as_str())
+            .filter(|identifier| seen.insert(identifier))
+            .take(MAX_ARTIFACT_FINDINGS)
+            .map(|identifier| identifier.to_owned())
+            .collect();
+
+        let mut notifs = vec![];
+        if !found.is_empty() {
+            notifs.push(Bulletin::new(
+                name.to_owned(),
+                BulletinReason::BinaryArtifact(artifact.format, found),
+                Location { row: 1, column: 1 },
+                None,
+                0.2f64,
+            ));
+        }
+        if let Some(shadows) = &artifact.shadows {
+            notifs.push(Bulletin::new(
+                name,
+                BulletinReason::ShadowedModule(shadows.display().to_string()),
+                Location { row: 1, column: 1 },
+                None,
+                0.2f64,
+            ));
+        }
+        for notif in notifs {
+            add_bulletin(notif, FieldType::Behavior, 1.0f64, 1.0f64, de, bulletins);
+            *alerts += 1;
+        }
+    }
+
+    /// Credentials that are read and then sent somewhere are a lot more suspicious than
+    /// either on their own, so network activity after credential access is reported again.
+    fn read_then_send_check(&self, de: &mut DensityEvaluator, bulletins: &mut Bulletins) {
+        let first_read = bulletins
+            .iter()
+            .filter(|b| b.functionality == Some(Functionality::CredentialAccess))
+            .map(|b| b.line())
+            .min();
+        let first_read = match first_read {
+            Some(line) => line,
+            None => return,
+        };
+
+        let mut seen: HashSet<usize> = HashSet::new();
+        let sends: Vec<Bulletin> = bulletins
+            .iter()
+            .filter(|b| b.functionality == Some(Functionality::Network) && b.line() >= first_read)
+            .filter(|b| seen.insert(b.line()))
+            .map(|b| {
+                Bulletin::new(
+                    b.identifier.to_string(),
+                    BulletinReason::ReadThenSend(first_read),
+                    Location {
+                        row: b.line(),
+                        column: b.col(),
+                    },
+                    Some(Functionality::CredentialAccess),
+                    0.2f64,
+                )
+            })
+            .collect();
+
+        for notif in sends {
+            add_bulletin(notif, FieldType::Behavior, 1.0f64, 1.0f64, de, bulletins);
+        }
+    }
+
+    /// Looks for canary keys anywhere inside the strings of the file.
+    fn canary_check(
         &self,
         source: &SourceFile,
         de: &mut DensityEvaluator,
@@ -172,41 +624,62 @@ impl Evaluator {
         alerts: &mut i32,
         _config: &Config,
     ) {
-        let canaries = self.canaries.get_canaries();
-        let locations = source.variable_visitor.get_locations();
+        // a string assigned to a variable is seen both as a constant and as the variable
+        let mut seen: HashSet<(usize, &str)> = HashSet::new();
+        for (value, location) in Evaluator::string_values(source) {
+            for (key, canary_info) in self.canaries.find(value) {
+                if !seen.insert((location.row(), key.as_str())) {
+                    continue;
+                }
 
-        let keys: Vec<String> = canaries.keys().map(|k| k.to_owned()).collect();
-        for (identifier, variable) in source.variable_visitor.get_variables() {
-            if variable.is_string() {
-                if let Some(str_var) = variable.get_string() {
-                    for key in &keys {
-                        if str_var.starts_with(key) {
-                            // shouldn't crash
-                            let canary_info = canaries.get(key).unwrap();
-                            let location = locations.get(identifier).unwrap();
-
-                            let notif = Bulletin::new(
-                                canary_info.identifier.to_string(),
-                                BulletinReason::Canary(format!(
-                                    "detected '{}' using transform '{}'",
-                                    canary_info.identifier, canary_info.transform
-                                )),
-                                location.clone(),
-                                None,
-                                0.2f64,
-                            );
-                            bulletins.push(notif);
-                            de.add_density(FieldType::Strings, location.row(), 1.0f64, 1.0f64);
-                            *alerts += 1; // TODO: should have its own alert entry
-                        }
-                    }
+                let notif = Bulletin::new(
+                    canary_info.identifier.to_string(),
+                    BulletinReason::Canary(format!(
+                        "detected '{}' using transform '{}'",
+                        canary_info.identifier, canary_info.transform
+                    )),
+                    location,
+                    None,
+                    0.2f64,
+                );
+                add_bulletin(notif, FieldType::Strings, 1.0f64, 1.0f64, de, bulletins);
+                *alerts += 1; // TODO: should have its own alert entry
+            }
+        }
+    }
+
+    /// Looks for hardcoded URLs, IP addresses and abuse domains in the strings of the file.
+    fn network_check(
+        &self,
+        source: &SourceFile,
+        de: &mut DensityEvaluator,
+        bulletins: &mut Bulletins,
+        found: &mut Vec<Indicator>,
+        config: &Config,
+    ) {
+        let mut seen: HashSet<(usize, IndicatorKind, String)> = HashSet::new();
+        for (value, location) in Evaluator::string_values(source) {
+            for indicator in indicators::extract(value, location, &config.abuse_domains) {
+                if !seen.insert((indicator.line, indicator.kind, indicator.value.clone())) {
+                    continue;
                 }
+
+                let notif = Bulletin::new(
+                    indicator.value.clone(),
+                    BulletinReason::NetworkIndicator(indicator.kind),
+                    location,
+                    Some(Functionality::Network),
+                    0.2f64,
+                );
+                add_bulletin(notif, FieldType::Strings, 1.0f64, 1.0f64, de, bulletins);
+                found.push(indicator);
             }
         }
+        found.sort();
     }
 
     pub fn evaluate(&self, analysis: &mut SourceAnalysis, config: &Config) {
-        self.variable_check(
+        self.canary_check(
             &analysis.source,
             &mut analysis.density_evaluator,
             &mut analysis.bulletins,
@@ -214,7 +687,25 @@ impl Evaluator {
             config,
         );
 
-        for entry in analysis.source.get_imports() {
+        self.network_check(
+            &analysis.source,
+            &mut analysis.density_evaluator,
+            &mut analysis.bulletins,
+            &mut analysis.indicators,
+            config,
+        );
+
+        self.shell_check(
+            &analysis.source,
+            &mut analysis.density_evaluator,
+            &mut analysis.bulletins,
+            &mut analysis.alerts_functions,
+        );
+
+        let imports = analysis.source.get_sorted_imports();
+        let literals = Evaluator::literal_values(&analysis.source);
+
+        for entry in imports.iter().copied() {
             self.misc_import_checks(
                 &analysis.source,
                 entry,
@@ -226,7 +717,7 @@ impl Evaluator {
         }
 
         for set in self.rule_sets.iter() {
-            for entry in analysis.source.get_imports() {
+            for entry in imports.iter().copied() {
                 for rule in set.get_module_rules() {
                     self.rule_check_module(
                         &analysis.source,
@@ -254,7 +745,72 @@ impl Evaluator {
                         config,
                     );
                 }
+
+                for rule in set.get_argument_rules() {
+                    self.rule_check_argument(
+                        entry,
+                        rule,
+                        set,
+                        &mut analysis.density_evaluator,
+                        &mut analysis.bulletins,
+                        &mut analysis.alerts_functions,
+                        config,
+                    );
+                }
+
+                for rule in set.get_write_rules() {
+                    self.rule_check_write(
+                        entry,
+                        rule,
+                        set,
+                        &mut analysis.density_evaluator,
+                        &mut analysis.bulletins,
+                        &mut analysis.alerts_functions,
+                        config,
+                    );
+                }
+
+                for rule in set.get_call_rules() {
+                    self.rule_check_call(
+                        entry,
+                        rule,
+                        set,
+                        &mut analysis.density_evaluator,
+                        &mut analysis.bulletins,
+                        &mut analysis.alerts_functions,
+                        config,
+                    );
+                }
+            }
+
+            let mut seen: HashSet<usize> = HashSet::new();
+            for rule in set.get_literal_rules() {
+                self.rule_check_literal(
+                    &literals,
+                    &mut seen,
+                    rule,
+                    set,
+                    &mut analysis.density_evaluator,
+                    &mut analysis.bulletins,
+                    &mut analysis.alerts_functions,
+                );
             }
         }
+
+        self.read_then_send_check(&mut analysis.density_evaluator, &mut analysis.bulletins);
+        self.native_check(
+            &analysis.source,
+            &mut analysis.density_evaluator,
+            &mut analysis.bulletins,
+            &mut analysis.alerts_functions,
+        );
+        self.auto_exec_check(
+            &analysis.source,
+            &mut analysis.density_evaluator,
+            &mut analysis.bulletins,
+            config,
+        );
+
+        analysis.sort_bulletins();
     }
 }
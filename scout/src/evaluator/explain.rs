@@ -0,0 +1,178 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use super::{Bulletin, DensityContribution, DensityEvaluator, Hotspot, SourceAnalysis};
+
+/// A single comparison that decides whether a bulletin is shown.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub value: f64,
+    pub threshold: f64,
+    pub passed: bool,
+}
+
+impl Check {
+    fn at_least(name: &'static str, value: f64, threshold: f64) -> Self {
+        Self {
+            name,
+            value,
+            threshold,
+            passed: value >= threshold,
+        }
+    }
+
+    fn above(name: &'static str, value: f64, threshold: f64) -> Self {
+        Self {
+            name,
+            value,
+            threshold,
+            passed: value > threshold,
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {}: {:.4} vs {:.4}",
+            if self.passed { "pass" } else { "fail" },
+            self.name,
+            self.value,
+            self.threshold
+        )
+    }
+}
+
+/// Why a bulletin was or wasn't shown in the report.
+#[derive(Debug, Serialize)]
+pub struct Explanation<'a> {
+    pub identifier: &'a str,
+    pub line: usize,
+    pub col: usize,
+    pub reason: String,
+    pub density: Option<&'a DensityContribution>,
+    pub hotspot: Option<Hotspot>,
+    pub checks: Vec<Check>,
+    pub show_all: bool,
+    pub shown: bool,
+    pub decision: String,
+}
+
+impl<'a> Explanation<'a> {
+    pub fn new(
+        analysis: &SourceAnalysis,
+        bulletin: &'a Bulletin,
+        hotspot: Option<Hotspot>,
+    ) -> Self {
+        let mut checks: Vec<Check> = vec![];
+
+        // the density at the line has to be over the hotspot threshold to form a hotspot at all
+        let peak = hotspot.as_ref().map(|h| h.peak()).unwrap_or(0.0);
+        checks.push(Check::above(
+            "hotspot threshold",
+            peak,
+            DensityEvaluator::hotspot_threshold(),
+        ));
+        // bulletin_display_check
+        checks.push(Check::at_least(
+            "rule set threshold",
+            peak,
+            bulletin.threshold,
+        ));
+        checks.push(Check::at_least(
+            "global threshold",
+            peak,
+            analysis.global_threshold,
+        ));
+        // override check 2 in the textual report compares the global threshold strictly
+        checks.push(Check::above(
+            "global threshold (report)",
+            peak,
+            analysis.global_threshold,
+        ));
+        // override check 1: the file is only reported if something in it is visible
+        let file_visible = analysis.found_anything() && analysis.any_bulletins_over_threshold();
+        checks.push(Check::at_least(
+            "file has visible bulletins",
+            file_visible as u8 as f64,
+            1.0,
+        ));
+
+        let shown = analysis.show_all || checks.iter().all(|c| c.passed);
+        let decision = if analysis.show_all {
+            String::from("shown, show all override is enabled")
+        } else if shown {
+            String::from("shown")
+        } else if hotspot.is_none() {
+            String::from("hidden, the bulletin is not inside any hotspot")
+        } else {
+            let failed = checks
+                .iter()
+                .filter(|c| !c.passed)
+                .map(|c| c.name)
+                .collect::<Vec<&str>>()
+                .join(", ");
+            format!("hidden, failed: {}", failed)
+        };
+
+        Self {
+            identifier: bulletin.identifier.as_str(),
+            line: bulletin.line(),
+            col: bulletin.col(),
+            reason: bulletin.reason(),
+            density: bulletin.density(),
+            hotspot,
+            checks,
+            show_all: analysis.show_all,
+            shown,
+            decision,
+        }
+    }
+}
+
+impl<'a> fmt::Display for Explanation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>4}:{:<3} {}", self.line, self.col, self.reason)?;
+
+        match self.density {
+            Some(density) => writeln!(
+                f,
+                "          density: {:?} field, tfidf multiplier {:.4}, tfidf weight {:.2} -> weight {:.4}, contribution {:.4}",
+                density.field,
+                density.tfidf_multiplier,
+                density.tfidf_weight,
+                density.weight,
+                density.contribution
+            )?,
+            None => writeln!(f, "          density: not recorded")?,
+        }
+
+        match &self.hotspot {
+            Some(hotspot) => {
+                let name = match &hotspot.definition {
+                    Some(definition) => {
+                        format!(" in {} '{}'", definition.kind.as_str(), definition.name)
+                    }
+                    None => String::from(""),
+                };
+                writeln!(
+                    f,
+                    "          hotspot{}: lines {}-{}, peak {:.4}",
+                    name,
+                    hotspot.line_low(),
+                    hotspot.line_high(),
+                    hotspot.peak()
+                )?
+            }
+            None => writeln!(f, "          hotspot: none")?,
+        }
+
+        for check in self.checks.iter() {
+            writeln!(f, "          {}", check)?;
+        }
+        writeln!(f, "          => {}", self.decision)
+    }
+}
@@ -1,12 +1,15 @@
 use serde::{Deserialize, Serialize};
 
-use crate::SourceFile;
+use crate::{visitors::Definition, SourceFile};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Hotspot {
     pub startx: f64,
     pub endx: f64,
     pub peak: f64,
+    /// The function or class this hotspot covers, when grouping by definitions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition: Option<Definition>,
 }
 
 impl Hotspot {
@@ -15,6 +18,17 @@ impl Hotspot {
             startx: 0.0,
             endx: 0.0,
             peak: 0.0,
+            definition: None,
+        }
+    }
+
+    /// A hotspot spanning a whole definition, where `peak` is the definition's score.
+    pub fn from_definition(definition: &Definition, peak: f64) -> Self {
+        Self {
+            startx: (definition.start - 1) as f64,
+            endx: definition.end as f64,
+            peak,
+            definition: Some(definition.clone()),
         }
     }
 
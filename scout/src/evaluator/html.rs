@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::{AnalysisResult, Bulletin, DensityPlot, SourceAnalysis};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.5em; }
+h2 { font-size: 1.1em; font-family: monospace; border-bottom: 1px solid #ccc; padding-bottom: 0.2em; margin-top: 2em; }
+summary { cursor: pointer; margin: 0.4em 0; }
+.code { font-family: monospace; font-size: 0.9em; border-collapse: collapse; width: 100%; }
+.code td { padding: 0 0.5em; white-space: pre; vertical-align: top; }
+.code .no { color: #999; text-align: right; user-select: none; width: 1%; }
+.code .hit { background: #fff3b0; }
+.code .bulletin { color: #b00020; }
+.finding { color: #b00020; }
+.muted { color: #777; }
+";
+
+/// Escapes text to be used in HTML, also inside attributes.
+pub(super) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// The lines of code from `low` up to `high`, with the bulletins on each line below it.
+fn code_table(
+    lines: &[&str],
+    low: usize,
+    high: usize,
+    bulletins: &BTreeMap<usize, Vec<&Bulletin>>,
+) -> String {
+    let mut out = String::from("<table class=\"code\">\n");
+    for (idx, line) in lines.iter().enumerate().take(high).skip(low) {
+        let number = idx + 1;
+        let on_line = bulletins.get(&number);
+        let _ = writeln!(
+            out,
+            "<tr{}><td class=\"no\">{}</td><td>{}</td></tr>",
+            if on_line.is_some() {
+                " class=\"hit\""
+            } else {
+                ""
+            },
+            number,
+            escape(line)
+        );
+        for bulletin in on_line.into_iter().flatten() {
+            let _ = writeln!(
+                out,
+                "<tr><td class=\"no\"></td><td class=\"bulletin\">{}^{}</td></tr>",
+                " ".repeat(bulletin.col()),
+                escape(&bulletin.reason())
+            );
+        }
+    }
+    out.push_str("</table>\n");
+    out
+}
+
+fn file_section(result: &SourceAnalysis) -> String {
+    let source = result.source.get_source();
+    let lines: Vec<&str> = source.lines().collect();
+    let visible = result.get_visible_bulletins();
+
+    let mut by_line: BTreeMap<usize, Vec<&Bulletin>> = BTreeMap::new();
+    for bulletin in visible.iter() {
+        by_line.entry(bulletin.line()).or_default().push(bulletin);
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<section>\n<h2>{}</h2>\n<p class=\"muted\">{} lines, {} bulletins</p>",
+        escape(result.source.get_path()),
+        lines.len(),
+        visible.len()
+    );
+
+    for (group, hotspot) in result.bulletins_by_hotspot() {
+        if !group
+            .iter()
+            .any(|bulletin| by_line.contains_key(&bulletin.line()))
+        {
+            continue;
+        }
+        let functionality: Vec<String> = result
+            .get_uniq_functionality(&group)
+            .iter()
+            .map(|functionality| format!("{:?}", functionality))
+            .collect();
+        let name = match &hotspot.definition {
+            Some(definition) => format!(
+                "{} '{}', ",
+                definition.kind.as_str(),
+                escape(&definition.name)
+            ),
+            None => String::from(""),
+        };
+        let _ = writeln!(
+            out,
+            "<details open>\n<summary>{}lines {}-{}, score {:.2}{}</summary>",
+            name,
+            hotspot.line_low() + 1,
+            hotspot.line_high(),
+            hotspot.peak(),
+            if functionality.is_empty() {
+                String::from("")
+            } else {
+                format!(": {}", functionality.join(", "))
+            }
+        );
+        out.push_str(&code_table(
+            &lines,
+            hotspot.line_low(),
+            hotspot.line_high(),
+            &by_line,
+        ));
+        out.push_str("</details>\n");
+    }
+
+    let _ = writeln!(out, "<details>\n<summary>Source</summary>");
+    out.push_str(&code_table(&lines, 0, lines.len(), &by_line));
+    out.push_str("</details>\n");
+
+    let _ = writeln!(out, "<details>\n<summary>Density</summary>");
+    out.push_str(&DensityPlot::from_analysis(result).to_svg());
+    out.push_str("</details>\n</section>\n");
+    out
+}
+
+impl AnalysisResult {
+    /// A self-contained HTML report, with the source of every reported file, its hotspots
+    /// and a plot of its density fields.
+    pub fn to_html(&self) -> String {
+        let name = self
+            .get_metadata()
+            .as_ref()
+            .map(|metadata| metadata.name.as_str())
+            .filter(|name| !name.is_empty());
+        let title = match name {
+            Some(name) => format!("Scout report: {}", name),
+            None => String::from("Scout report"),
+        };
+        let reported: Vec<&SourceAnalysis> = self
+            .get_results()
+            .iter()
+            .filter(|result| result.message.is_some())
+            .collect();
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>",
+            escape(&title),
+            STYLE,
+            escape(&title)
+        );
+        let _ = writeln!(
+            out,
+            "<p class=\"muted\">{} of {} files reported</p>",
+            reported.len(),
+            self.get_results().len()
+        );
+
+        if !self.get_name_findings().is_empty() {
+            out.push_str("<ul>\n");
+            for finding in self.get_name_findings() {
+                let _ = writeln!(
+                    out,
+                    "<li class=\"finding\">{}</li>",
+                    escape(&finding.to_string())
+                );
+            }
+            out.push_str("</ul>\n");
+        }
+
+        for result in reported {
+            out.push_str(&file_section(result));
+        }
+
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Engine;
+
+    #[test]
+    fn test_html_report() {
+        let html = Engine::new()
+            .analyse_file("../examples/files/test-obfuscated-example.py")
+            .unwrap()
+            .to_html();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h2>../examples/files/test-obfuscated-example.py</h2>"));
+        assert!(html.contains("<tr class=\"hit\">"));
+        assert!(html.contains("<details open>"));
+        assert!(html.contains("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(html.contains("key = &quot;aW1wb3J0IHN&quot;"));
+    }
+}
@@ -0,0 +1,215 @@
+use std::net::{IpAddr, Ipv6Addr};
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::visitors::Location;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndicatorKind {
+    Url,
+    Ipv4,
+    Ipv6,
+    HostPort,
+    /// A domain from the `abuse_domains` list in the config, e.g. a webhook or paste site.
+    AbuseDomain,
+}
+
+impl IndicatorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndicatorKind::Url => "URL",
+            IndicatorKind::Ipv4 => "IPv4 address",
+            IndicatorKind::Ipv6 => "IPv6 address",
+            IndicatorKind::HostPort => "host and port",
+            IndicatorKind::AbuseDomain => "known abuse domain",
+        }
+    }
+}
+
+/// A hardcoded network endpoint found in a string.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Indicator {
+    pub line: usize,
+    pub col: usize,
+    pub kind: IndicatorKind,
+    pub value: String,
+}
+
+struct Patterns {
+    url: Regex,
+    ipv4: Regex,
+    ipv6: Regex,
+    host_port: Regex,
+    domain: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        url: Regex::new(r#"(?i)\b(?:https?|ftps?|wss?|tcp|udp)://[^\s'"`<>(){}\[\]\\]+"#).unwrap(),
+        ipv4: Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").unwrap(),
+        ipv6: Regex::new(r"(?i)[0-9a-f]{0,4}(?::[0-9a-f]{0,4}){2,7}").unwrap(),
+        host_port: Regex::new(
+            r"(?i)\b((?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]{2,63}|(?:\d{1,3}\.){3}\d{1,3}|localhost):(\d{1,5})\b",
+        )
+        .unwrap(),
+        domain: Regex::new(r"(?i)\b(?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]{2,63}\b")
+            .unwrap(),
+    })
+}
+
+/// Extensions of source and data files, which look like a TLD in `file.py:42` but are
+/// far more likely to be a file name and line number than a host and port.
+const FILE_EXTENSIONS: &[&str] = &[
+    "bat", "cfg", "conf", "cpp", "cs", "css", "csv", "db", "dll", "exe", "go", "gz", "html", "ini",
+    "ipynb", "java", "jpg", "js", "json", "jsx", "kt", "lock", "log", "md", "php", "pl", "png",
+    "ps1", "pth", "py", "pyc", "pyi", "pyw", "pyx", "rb", "rs", "rst", "sh", "so", "sql", "svg",
+    "swift", "tar", "toml", "ts", "tsx", "txt", "whl", "xml", "yaml", "yml", "zip",
+];
+
+/// Whether the host of a host and port pair is a file name, e.g. `loader.py`.
+fn is_file_name(host: &str) -> bool {
+    host.rsplit_once('.').is_some_and(|(_, tld)| {
+        FILE_EXTENSIONS
+            .iter()
+            .any(|extension| tld.eq_ignore_ascii_case(extension))
+    })
+}
+
+/// Whether the match is part of a longer dotted number, such as a version string.
+fn inside_dotted(value: &str, range: &Range<usize>) -> bool {
+    let bytes = value.as_bytes();
+    let before = range.start >= 2
+        && bytes[range.start - 1] == b'.'
+        && bytes[range.start - 2].is_ascii_digit();
+    let after = range.end + 1 < bytes.len()
+        && bytes[range.end] == b'.'
+        && bytes[range.end + 1].is_ascii_digit();
+    before || after
+}
+
+/// Whether the domain is one of the abuse domains, or a subdomain of one.
+fn is_abuse_domain(domain: &str, abuse_domains: &[String]) -> bool {
+    let domain = domain.to_ascii_lowercase();
+    abuse_domains.iter().any(|abuse| {
+        let abuse = abuse.to_ascii_lowercase();
+        domain == abuse || domain.ends_with(&format!(".{}", abuse))
+    })
+}
+
+/// Finds URLs, IP addresses, host and port pairs and abuse domains in a string. The same
+/// value is only reported once per string, and host and port pairs that are already part
+/// of a URL are left out.
+pub fn extract(value: &str, location: Location, abuse_domains: &[String]) -> Vec<Indicator> {
+    let patterns = patterns();
+    let mut found: Vec<(IndicatorKind, String)> = vec![];
+
+    let urls: Vec<Range<usize>> = patterns.url.find_iter(value).map(|m| m.range()).collect();
+    for url in urls.iter() {
+        found.push((IndicatorKind::Url, value[url.clone()].to_owned()));
+    }
+
+    for m in patterns.ipv4.find_iter(value) {
+        if !inside_dotted(value, &m.range()) && m.as_str().parse::<IpAddr>().is_ok() {
+            found.push((IndicatorKind::Ipv4, m.as_str().to_owned()));
+        }
+    }
+
+    for m in patterns.ipv6.find_iter(value) {
+        let candidate = m.as_str();
+        let in_word = value[..m.start()]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric());
+        // short forms like `a::b` or `::1` are too common in code to be worth reporting
+        if !in_word && candidate.matches(':').count() >= 3 && candidate.parse::<Ipv6Addr>().is_ok()
+        {
+            found.push((IndicatorKind::Ipv6, candidate.to_owned()));
+        }
+    }
+
+    for caps in patterns.host_port.captures_iter(value) {
+        let m = caps.get(0).unwrap();
+        let in_url = urls
+            .iter()
+            .any(|url| url.start <= m.start() && m.end() <= url.end);
+        let port_ok = caps[2].parse::<u16>().is_ok_and(|port| port > 0);
+        if !in_url && port_ok && !inside_dotted(value, &m.range()) && !is_file_name(&caps[1]) {
+            found.push((IndicatorKind::HostPort, m.as_str().to_owned()));
+        }
+    }
+
+    if !abuse_domains.is_empty() {
+        for m in patterns.domain.find_iter(value) {
+            if is_abuse_domain(m.as_str(), abuse_domains) {
+                found.push((IndicatorKind::AbuseDomain, m.as_str().to_ascii_lowercase()));
+            }
+        }
+    }
+
+    found.sort();
+    found.dedup();
+    found
+        .into_iter()
+        .map(|(kind, value)| Indicator {
+            line: location.row(),
+            col: location.column(),
+            kind,
+            value,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract, IndicatorKind};
+    use crate::visitors::Location;
+
+    fn kinds(value: &str) -> Vec<(IndicatorKind, String)> {
+        let abuse_domains = vec![String::from("discord.com"), String::from("ngrok.io")];
+        extract(value, Location { row: 1, column: 1 }, &abuse_domains)
+            .into_iter()
+            .map(|i| (i.kind, i.value))
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_indicators() {
+        assert_eq!(
+            kinds("requests.post('https://discord.com/api/webhooks/123/abc', data=x)"),
+            vec![
+                (
+                    IndicatorKind::Url,
+                    String::from("https://discord.com/api/webhooks/123/abc")
+                ),
+                (IndicatorKind::AbuseDomain, String::from("discord.com")),
+            ]
+        );
+        assert_eq!(
+            kinds("connect 10.0.0.1:4444 and 2001:db8::1, then 4.tcp.ngrok.io:1337"),
+            vec![
+                (IndicatorKind::Ipv4, String::from("10.0.0.1")),
+                (IndicatorKind::Ipv6, String::from("2001:db8::1")),
+                (IndicatorKind::HostPort, String::from("10.0.0.1:4444")),
+                (IndicatorKind::HostPort, String::from("4.tcp.ngrok.io:1337")),
+                (IndicatorKind::AbuseDomain, String::from("4.tcp.ngrok.io")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignores_versions_and_code() {
+        assert!(kinds("version 1.2.3.4.5").is_empty());
+        assert!(kinds("os.path.join").is_empty());
+        assert!(kinds("a::b and ::").is_empty());
+        assert!(kinds("time 12:30:45").is_empty());
+        // file names and line numbers, e.g. in tracebacks and log messages
+        assert!(kinds("Raised from loader.py:42").is_empty());
+        assert!(kinds("reading config.json:3").is_empty());
+        assert!(kinds("see src/main.rs:10 and notes.txt:7").is_empty());
+    }
+}
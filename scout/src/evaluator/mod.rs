@@ -1,14 +1,28 @@
 mod bulletin;
 mod canary;
+mod canary_generator;
 mod density_evaluator;
 mod evaluator;
+mod explain;
 mod hotspot;
+mod html;
+mod indicators;
+mod plot;
+mod report;
 mod rules;
 mod source_analysis;
 
 pub use bulletin::{Bulletin, BulletinReason, Bulletins};
-pub use density_evaluator::DensityEvaluator;
+pub use canary::Canaries;
+pub use density_evaluator::{DensityContribution, DensityEvaluator, FieldType};
 pub use evaluator::Evaluator;
+pub use explain::Explanation;
 pub use hotspot::Hotspot;
+pub use indicators::{Indicator, IndicatorKind};
+pub use plot::DensityPlot;
+pub use report::{
+    AnalysisError, BulletinReport, FileReport, HotspotReport, PackageReport, ParseStatus, Report,
+    ReportOptions, Timings, SCHEMA_VERSION,
+};
 pub use rules::{Functionality, Rule, RuleManager, RuleSet, Rules};
 pub use source_analysis::{AnalysisResult, SourceAnalysis};
@@ -0,0 +1,314 @@
+use std::fmt::Write;
+
+use super::density_evaluator::{DensityEvaluator, Field, FieldType};
+use super::html::escape;
+use super::{Hotspot, SourceAnalysis};
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 260.0;
+const MARGIN_LEFT: f64 = 55.0;
+/// Room for the legend on the right of the plot.
+const MARGIN_RIGHT: f64 = 110.0;
+const MARGIN_TOP: f64 = 10.0;
+const MARGIN_BOTTOM: f64 = 35.0;
+
+const Y_TICKS: usize = 4;
+/// Height of the bulletin markers above the x-axis.
+const MARKER_HEIGHT: f64 = 10.0;
+
+const THRESHOLD_COLOR: &str = "#d62728";
+const HOTSPOT_COLOR: &str = "#ffd54f";
+const VISIBLE_COLOR: &str = "#b00020";
+const HIDDEN_COLOR: &str = "#999999";
+
+fn field_color(field_type: FieldType) -> &'static str {
+    match field_type {
+        FieldType::Functions => "#1f77b4",
+        FieldType::Imports => "#ff7f0e",
+        FieldType::Behavior => "#2ca02c",
+        FieldType::Strings => "#9467bd",
+    }
+}
+
+/// A step between ticks that is 1, 2 or 5 times a power of ten, giving about `count` ticks.
+fn tick_step(max: f64, count: usize) -> f64 {
+    let raw = max / count as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|step| step * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// A bulletin drawn as a marker on its line.
+struct BulletinMarker {
+    line: usize,
+    label: String,
+    visible: bool,
+}
+
+/// Plots the density fields of a file as an SVG, with every `FieldType` field dashed and
+/// the combined field as a solid line, like `scripts/plot.py` does. Hotspots are shaded,
+/// and the threshold and bulletins are marked when they are set.
+pub struct DensityPlot<'a> {
+    evaluator: &'a DensityEvaluator,
+    threshold: Option<f64>,
+    hotspots: Vec<Hotspot>,
+    markers: Vec<BulletinMarker>,
+}
+
+impl<'a> DensityPlot<'a> {
+    pub fn new(evaluator: &'a DensityEvaluator) -> Self {
+        DensityPlot {
+            evaluator,
+            threshold: None,
+            hotspots: vec![],
+            markers: vec![],
+        }
+    }
+
+    /// The fields of the analysed file, with its hotspots, the global threshold and a
+    /// marker for every bulletin. Bulletins that aren't shown are drawn in grey.
+    pub fn from_analysis(result: &'a SourceAnalysis) -> Self {
+        let visible = result.get_visible_bulletins();
+        let mut plot = DensityPlot::new(&result.density_evaluator)
+            .set_hotspots(
+                result
+                    .bulletins_by_hotspot()
+                    .into_iter()
+                    .map(|(_, hotspot)| hotspot)
+                    .collect(),
+            )
+            .set_threshold(result.global_threshold);
+        for bulletin in result.get_all_bulletins() {
+            plot = plot.add_bulletin(
+                bulletin.line(),
+                &format!("{}: {}", bulletin.line(), bulletin.reason()),
+                visible.iter().any(|shown| std::ptr::eq(*shown, bulletin)),
+            );
+        }
+        plot
+    }
+
+    /// Draws a line at the threshold, unless it is zero.
+    pub fn set_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = Some(threshold).filter(|threshold| *threshold > 0.0);
+        self
+    }
+
+    pub fn set_hotspots(mut self, hotspots: Vec<Hotspot>) -> Self {
+        self.hotspots = hotspots;
+        self
+    }
+
+    /// Marks a bulletin on its line, the label is shown when hovering the marker.
+    pub fn add_bulletin(mut self, line: usize, label: &str, visible: bool) -> Self {
+        self.markers.push(BulletinMarker {
+            line,
+            label: label.to_owned(),
+            visible,
+        });
+        self
+    }
+
+    pub fn to_svg(&self) -> String {
+        let combined = self.evaluator.calculate_combined_field();
+        let fields = self.evaluator.get_fields();
+
+        let max_x = combined.get_x().last().copied().unwrap_or(0.0).max(1.0);
+        let max_y = fields
+            .values()
+            .chain(std::iter::once(&combined))
+            .flat_map(|field| field.get_y().iter())
+            .fold(self.threshold.unwrap_or(0.0), |max: f64, y| max.max(*y));
+        let max_y = if max_y > 0.0 { max_y * 1.1 } else { 1.0 };
+
+        let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+        let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+        let to_x = |x: f64| MARGIN_LEFT + x / max_x * plot_width;
+        let to_y = |y: f64| MARGIN_TOP + plot_height - y / max_y * plot_height;
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"11\">",
+            WIDTH, HEIGHT, WIDTH, HEIGHT
+        );
+        let _ = writeln!(
+            svg,
+            "<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>",
+            WIDTH, HEIGHT
+        );
+
+        let bottom = MARGIN_TOP + plot_height;
+        for hotspot in self.hotspots.iter() {
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" fill-opacity=\"0.3\"><title>lines {}-{}, score {:.3}</title></rect>",
+                to_x(hotspot.startx),
+                MARGIN_TOP,
+                (to_x(hotspot.endx) - to_x(hotspot.startx)).max(1.0),
+                plot_height,
+                HOTSPOT_COLOR,
+                hotspot.line_low() + 1,
+                hotspot.line_high(),
+                hotspot.peak()
+            );
+        }
+
+        // axes and ticks
+        let _ = writeln!(
+            svg,
+            "<path d=\"M{:.1},{:.1} V{:.1} H{:.1}\" fill=\"none\" stroke=\"#333333\"/>",
+            MARGIN_LEFT,
+            MARGIN_TOP,
+            bottom,
+            MARGIN_LEFT + plot_width
+        );
+        let step = tick_step(max_x, 10);
+        let mut tick = 0.0;
+        while tick <= max_x {
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{x:.1}\" y1=\"{:.1}\" x2=\"{x:.1}\" y2=\"{:.1}\" stroke=\"#333333\"/><text x=\"{x:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                bottom,
+                bottom + 4.0,
+                bottom + 15.0,
+                tick,
+                x = to_x(tick)
+            );
+            tick += step;
+        }
+        let step = tick_step(max_y, Y_TICKS);
+        let mut tick = 0.0;
+        while tick <= max_y {
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{:.1}\" y1=\"{y:.1}\" x2=\"{:.1}\" y2=\"{y:.1}\" stroke=\"#dddddd\"/><text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{:.3}</text>",
+                MARGIN_LEFT,
+                MARGIN_LEFT + plot_width,
+                MARGIN_LEFT - 5.0,
+                to_y(tick) + 4.0,
+                tick,
+                y = to_y(tick)
+            );
+            tick += step;
+        }
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">Line of code</text>",
+            MARGIN_LEFT + plot_width / 2.0,
+            HEIGHT - 4.0
+        );
+
+        let polyline = |field: &Field| -> String {
+            field
+                .get_x()
+                .iter()
+                .zip(field.get_y().iter())
+                .map(|(x, y)| format!("{:.1},{:.1}", to_x(*x), to_y(*y)))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+
+        let mut legend: Vec<(String, &str, bool)> = vec![];
+        for (field_type, field) in fields.iter() {
+            let color = field_color(*field_type);
+            let _ = writeln!(
+                svg,
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-dasharray=\"4 3\"/>",
+                polyline(field),
+                color
+            );
+            legend.push((format!("{:?}", field_type), color, true));
+        }
+        let _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"#000000\" stroke-width=\"1.5\"/>",
+            polyline(&combined)
+        );
+        legend.push((String::from("Combined"), "#000000", false));
+
+        if let Some(threshold) = self.threshold {
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{:.1}\" y1=\"{y:.1}\" x2=\"{:.1}\" y2=\"{y:.1}\" stroke=\"{}\"><title>threshold {}</title></line>",
+                MARGIN_LEFT,
+                MARGIN_LEFT + plot_width,
+                THRESHOLD_COLOR,
+                threshold,
+                y = to_y(threshold)
+            );
+            legend.push((String::from("Threshold"), THRESHOLD_COLOR, false));
+        }
+
+        for marker in self.markers.iter() {
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{x:.1}\" y1=\"{:.1}\" x2=\"{x:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"2\"><title>{}</title></line>",
+                bottom,
+                bottom - MARKER_HEIGHT,
+                if marker.visible {
+                    VISIBLE_COLOR
+                } else {
+                    HIDDEN_COLOR
+                },
+                escape(&marker.label),
+                x = to_x(marker.line as f64)
+            );
+        }
+
+        for (idx, (label, color, dashed)) in legend.iter().enumerate() {
+            let y = MARGIN_TOP + 10.0 + idx as f64 * 16.0;
+            let x = MARGIN_LEFT + plot_width + 10.0;
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{:.1}\" y1=\"{y:.1}\" x2=\"{:.1}\" y2=\"{y:.1}\" stroke=\"{}\"{}/><text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                x,
+                x + 20.0,
+                color,
+                if *dashed { " stroke-dasharray=\"4 3\"" } else { "" },
+                x + 25.0,
+                y + 4.0,
+                label,
+                y = y
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tick_step, DensityPlot};
+    use crate::Engine;
+
+    #[test]
+    fn test_tick_step() {
+        assert_eq!(tick_step(100.0, 10), 10.0);
+        assert_eq!(tick_step(130.0, 10), 20.0);
+        assert_eq!(tick_step(0.4, 4), 0.1);
+        assert_eq!(tick_step(0.9, 4), 0.5);
+    }
+
+    #[test]
+    fn test_plot_marks_bulletins() {
+        let results = Engine::new()
+            .set_threshold(0.3)
+            .analyse_file("../examples/files/test-obfuscated-example.py")
+            .unwrap();
+        let result = &results.get_results()[0];
+        let svg = DensityPlot::from_analysis(result).to_svg();
+
+        assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains("<title>threshold 0.3</title>"));
+        assert_eq!(
+            svg.matches("stroke-width=\"2\"><title>").count(),
+            result.get_all_bulletins().len()
+        );
+        assert!(svg.contains("fill-opacity=\"0.3\"><title>lines "));
+    }
+}
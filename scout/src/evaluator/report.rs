@@ -0,0 +1,478 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::density_evaluator::{Field, FieldType};
+use super::{
+    AnalysisResult, Bulletin, DensityContribution, Functionality, Hotspot, Indicator,
+    SourceAnalysis,
+};
+use crate::source::{AutoExec, FixKind};
+use crate::visitors::Definition;
+use crate::{Metadata, NameFinding, SourceFile};
+
+/// The version of the JSON output, see `schema/report.schema.json`. It is increased when
+/// fields are removed or change meaning, new optional fields don't change it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The JSON output of an analysis.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub scout_version: String,
+    /// Set when a package was analysed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<PackageReport>,
+    #[serde(default)]
+    pub name_findings: Vec<NameFinding>,
+    pub files: Vec<FileReport>,
+    /// Code files that could not be read or parsed
+    #[serde(default)]
+    pub errors: Vec<AnalysisError>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackageReport {
+    pub name: String,
+    pub dependencies: Vec<String>,
+    pub requirements: Vec<String>,
+    pub indexes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseStatus {
+    /// The file was parsed as it is
+    Parsed,
+    /// Python 2 syntax was rewritten before the file could be parsed, see `fixes`
+    Fixed,
+    /// The file isn't Python source, e.g. a compiled or native module
+    Binary,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FixReport {
+    pub line: usize,
+    pub kind: FixKind,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlertCounts {
+    pub functions: i32,
+    pub imports: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileReport {
+    pub path: String,
+    pub loc: usize,
+    pub status: ParseStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<FixReport>,
+    pub alerts: AlertCounts,
+    /// Whether the file is in the textual report
+    pub reported: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_exec: Option<AutoExec>,
+    pub bulletins: Vec<BulletinReport>,
+    pub hotspots: Vec<HotspotReport>,
+    pub indicators: Vec<Indicator>,
+    /// The output of `--explain`, one object per bulletin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanations: Option<Vec<serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<FieldsReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulletinReport {
+    /// The rule that raised the bulletin, e.g. `function:os.system`, or the reason for
+    /// built-in checks
+    pub rule_id: String,
+    pub reason: String,
+    pub message: String,
+    pub identifier: String,
+    pub line: usize,
+    pub col: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functionality: Option<Functionality>,
+    pub threshold: f64,
+    /// Whether the bulletin is shown in the textual report
+    pub visible: bool,
+    /// The index of the hotspot the bulletin is in, in `hotspots` of the file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hotspot: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub density: Option<DensityContribution>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_exec: Option<AutoExec>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HotspotReport {
+    /// The first and last line of the hotspot, counting from one
+    pub start_line: usize,
+    pub end_line: usize,
+    pub peak: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition: Option<Definition>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldPoints {
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+}
+
+/// The density fields of a file, included with `--fields`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldsReport {
+    pub fields: BTreeMap<FieldType, FieldPoints>,
+    pub combined: FieldPoints,
+}
+
+/// A code file that could not be read or parsed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisError {
+    pub path: String,
+    pub message: String,
+}
+
+impl AnalysisError {
+    pub fn new(path: &Path, message: impl fmt::Display) -> Self {
+        AnalysisError {
+            path: path.display().to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// How long the analysis took, in milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timings {
+    /// Reading and parsing the files
+    pub load_ms: f64,
+    /// Running the rules and computing the density fields
+    pub evaluate_ms: f64,
+    pub total_ms: f64,
+}
+
+/// What to include in a `Report` besides the results.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReportOptions {
+    fields: bool,
+    max_field_points: Option<usize>,
+    explanations: bool,
+    timings: bool,
+}
+
+impl ReportOptions {
+    pub fn set_fields(mut self, fields: bool) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Downsamples every field to at most this many points, fields have two points per
+    /// line of code otherwise.
+    pub fn set_max_field_points(mut self, max_field_points: Option<usize>) -> Self {
+        self.max_field_points = max_field_points.filter(|max| *max > 0);
+        self
+    }
+
+    pub fn set_explanations(mut self, explanations: bool) -> Self {
+        self.explanations = explanations;
+        self
+    }
+
+    /// Timings differ between runs, so they are left out unless asked for.
+    pub fn set_timings(mut self, timings: bool) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    pub fn get_timings(&self) -> bool {
+        self.timings
+    }
+}
+
+impl FieldPoints {
+    /// The points of the field, downsampled to at most `max_points`. Each point keeps the
+    /// first `x` and the highest `y` of the points it replaces, so peaks aren't lost and
+    /// the fields of a file still line up.
+    fn from_field(field: &Field, max_points: Option<usize>) -> Self {
+        let (x, y) = (field.get_x(), field.get_y());
+        let step = match max_points {
+            Some(max) if x.len() > max => x.len().div_ceil(max),
+            _ => {
+                return FieldPoints {
+                    x: x.to_owned(),
+                    y: y.to_owned(),
+                }
+            }
+        };
+
+        FieldPoints {
+            x: x.iter().step_by(step).copied().collect(),
+            y: y.chunks(step)
+                .map(|chunk| chunk.iter().copied().fold(f64::MIN, f64::max))
+                .collect(),
+        }
+    }
+}
+
+impl ParseStatus {
+    pub fn of(source: &SourceFile) -> Self {
+        if source.get_artifact().is_some() || source.get_path().ends_with(".pyc") {
+            ParseStatus::Binary
+        } else if source.get_fixes().is_empty() {
+            ParseStatus::Parsed
+        } else {
+            ParseStatus::Fixed
+        }
+    }
+}
+
+impl PackageReport {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        PackageReport {
+            name: metadata.name.to_owned(),
+            dependencies: metadata.deps.to_owned(),
+            requirements: metadata.requirements.to_owned(),
+            indexes: metadata.indexes.to_owned(),
+        }
+    }
+}
+
+impl BulletinReport {
+    /// The bulletin of an evaluated file, with whether it is shown and its hotspot.
+    pub fn new(analysis: &SourceAnalysis, bulletin: &Bulletin) -> Self {
+        BulletinReport::in_file(
+            bulletin,
+            &analysis.get_visible_bulletins(),
+            &analysis.bulletins_by_hotspot(),
+        )
+    }
+
+    /// Like `new`, with the visible bulletins and hotspots of the file already computed,
+    /// so every bulletin of a file can be reported without grouping them again.
+    fn in_file(
+        bulletin: &Bulletin,
+        visible: &[&Bulletin],
+        groups: &[(Vec<&Bulletin>, Hotspot)],
+    ) -> Self {
+        let hotspot = groups
+            .iter()
+            .position(|(group, _)| group.iter().any(|b| std::ptr::eq(*b, bulletin)));
+        let visible = visible.iter().any(|b| std::ptr::eq(*b, bulletin));
+        BulletinReport {
+            rule_id: bulletin.rule_id(),
+            reason: bulletin.reason_code().to_owned(),
+            message: bulletin.reason(),
+            identifier: bulletin.identifier.to_owned(),
+            line: bulletin.line(),
+            col: bulletin.col(),
+            functionality: bulletin.functionality,
+            threshold: bulletin.threshold,
+            visible,
+            hotspot,
+            density: bulletin.density().cloned(),
+            auto_exec: bulletin.auto_exec().cloned(),
+        }
+    }
+}
+
+impl FileReport {
+    pub fn from_analysis(analysis: &SourceAnalysis, options: &ReportOptions) -> Self {
+        let source = &analysis.source;
+        let visible = analysis.get_visible_bulletins();
+        let groups = analysis.bulletins_by_hotspot();
+
+        let bulletins = analysis
+            .get_all_bulletins()
+            .into_iter()
+            .map(|bulletin| BulletinReport::in_file(bulletin, &visible, &groups))
+            .collect();
+        let hotspots = groups
+            .into_iter()
+            .map(|(_, hotspot)| HotspotReport::from_hotspot(hotspot))
+            .collect();
+
+        FileReport {
+            path: source.get_path().to_owned(),
+            loc: source.get_loc(),
+            status: ParseStatus::of(source),
+            fixes: source
+                .get_fixes()
+                .iter()
+                .map(|fix| FixReport {
+                    line: fix.line,
+                    kind: fix.kind,
+                })
+                .collect(),
+            alerts: AlertCounts {
+                functions: analysis.alerts_functions,
+                imports: analysis.alerts_imports,
+            },
+            reported: analysis.message.is_some(),
+            auto_exec: source.get_auto_exec().cloned(),
+            bulletins,
+            hotspots,
+            indicators: analysis.indicators.to_owned(),
+            explanations: if options.explanations {
+                Some(
+                    analysis
+                        .explain()
+                        .iter()
+                        .filter_map(|explanation| serde_json::to_value(explanation).ok())
+                        .collect(),
+                )
+            } else {
+                None
+            },
+            fields: if options.fields {
+                let evaluator = &analysis.density_evaluator;
+                Some(FieldsReport {
+                    fields: evaluator
+                        .get_fields()
+                        .iter()
+                        .map(|(field_type, field)| {
+                            (
+                                *field_type,
+                                FieldPoints::from_field(field, options.max_field_points),
+                            )
+                        })
+                        .collect(),
+                    combined: FieldPoints::from_field(
+                        &evaluator.calculate_combined_field(),
+                        options.max_field_points,
+                    ),
+                })
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl HotspotReport {
+    fn from_hotspot(hotspot: Hotspot) -> Self {
+        HotspotReport {
+            start_line: hotspot.line_low() + 1,
+            end_line: hotspot.line_high(),
+            peak: hotspot.peak(),
+            definition: hotspot.definition,
+        }
+    }
+}
+
+impl Report {
+    pub fn new(result: &AnalysisResult, options: &ReportOptions) -> Self {
+        Report {
+            schema_version: SCHEMA_VERSION,
+            scout_version: env!("CARGO_PKG_VERSION").to_owned(),
+            package: result
+                .get_metadata()
+                .as_ref()
+                .map(PackageReport::from_metadata),
+            name_findings: result.get_name_findings().to_owned(),
+            files: result
+                .get_results()
+                .iter()
+                .map(|analysis| FileReport::from_analysis(analysis, options))
+                .collect(),
+            errors: result.get_errors().to_owned(),
+            timings: if options.timings {
+                result.get_timings().cloned()
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Report> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Report, ReportOptions, SCHEMA_VERSION};
+    use crate::Engine;
+
+    const SCHEMA: &str = include_str!("../../schema/report.schema.json");
+
+    #[test]
+    fn test_report_round_trip() {
+        let results = Engine::new()
+            .set_show_all(true)
+            .analyse_package("../examples/packages/typosquat")
+            .unwrap();
+        let options = ReportOptions::default()
+            .set_fields(true)
+            .set_explanations(true)
+            .set_timings(true);
+        let json = Report::new(&results, &options).to_json();
+
+        let report = Report::from_json(&json).unwrap();
+        assert_eq!(report.schema_version, SCHEMA_VERSION);
+        assert!(!report.files.is_empty());
+        assert!(report.timings.is_some());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&report.to_json()).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()
+        );
+
+        // every property the schema requires is in the output
+        let schema: serde_json::Value = serde_json::from_str(SCHEMA).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            SCHEMA_VERSION
+        );
+        let output: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let required = |definition: &serde_json::Value, value: &serde_json::Value| {
+            for key in definition["required"].as_array().unwrap() {
+                let key = key.as_str().unwrap();
+                assert!(value.get(key).is_some(), "'{}' is missing", key);
+            }
+        };
+        required(&schema, &output);
+        let file = &output["files"][0];
+        required(&schema["$defs"]["file"], file);
+        required(&schema["$defs"]["bulletin"], &file["bulletins"][0]);
+        required(&schema["$defs"]["hotspot"], &file["hotspots"][0]);
+    }
+
+    #[test]
+    fn test_package_fields_per_file() {
+        let results = Engine::new()
+            .set_show_all(true)
+            .analyse_package("../examples/packages/auto_exec")
+            .unwrap();
+        assert!(results.get_results().len() > 1);
+        let full = Report::new(&results, &ReportOptions::default().set_fields(true));
+        let downsampled = Report::new(
+            &results,
+            &ReportOptions::default()
+                .set_fields(true)
+                .set_max_field_points(Some(8)),
+        );
+
+        for (full, downsampled) in full.files.iter().zip(downsampled.files.iter()) {
+            let full = &full.fields.as_ref().unwrap().combined;
+            let points = &downsampled.fields.as_ref().unwrap().combined;
+            assert!(points.x.len() <= 8);
+            assert_eq!(points.x.len(), points.y.len());
+            // the peaks are kept
+            let max = |y: &Vec<f64>| y.iter().copied().fold(f64::MIN, f64::max);
+            assert_eq!(max(&points.y), max(&full.y));
+        }
+    }
+}
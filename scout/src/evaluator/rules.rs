@@ -1,8 +1,7 @@
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, str::FromStr};
+use std::path::PathBuf;
 
-use crate::utils;
-use crate::Result;
+use crate::{Result, ScoutError};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 //#[serde(tag = "functionality")]
@@ -14,6 +13,8 @@ pub enum Functionality {
     Network,
     Process,
     System,
+    CredentialAccess,
+    Persistence,
     NotSpecific,
 }
 
@@ -22,6 +23,24 @@ pub enum Rule {
     /// Module(Functionality, Identifier, Name (optional), Description (optional))
     Module(Functionality, String, Option<String>, Option<String>),
     Function(Functionality, String, Option<String>, Option<String>),
+    /// A path fragment matched against string constants and folded path joins
+    Literal(Functionality, String, Option<String>, Option<String>),
+    /// A dotted name passed as a positional argument to any call, e.g. `os.environ`
+    Argument(Functionality, String, Option<String>, Option<String>),
+    /// A path fragment matched against the destination of file writes, copies and moves,
+    /// where `*` matches anything inside a path segment, e.g. `site-packages/*.pth`
+    Write(Functionality, String, Option<String>, Option<String>),
+    /// Call(Functionality, Function, Argument patterns, Name (optional), Description (optional))
+    ///
+    /// A call to the function where every pattern is found in one of the string arguments,
+    /// a pattern ending in `$` has to be at the end of the argument, e.g. `crontab -$`
+    Call(
+        Functionality,
+        String,
+        Vec<String>,
+        Option<String>,
+        Option<String>,
+    ),
 }
 
 impl Rule {
@@ -29,6 +48,25 @@ impl Rule {
         match self {
             Rule::Module(functionality, _, _, _) => *functionality,
             Rule::Function(functionality, _, _, _) => *functionality,
+            Rule::Literal(functionality, _, _, _) => *functionality,
+            Rule::Argument(functionality, _, _, _) => *functionality,
+            Rule::Write(functionality, _, _, _) => *functionality,
+            Rule::Call(functionality, _, _, _, _) => *functionality,
+        }
+    }
+
+    /// Identifies the rule in the JSON output, e.g. `function:os.system` or
+    /// `call:subprocess.run(curl,|sh)`.
+    pub fn id(&self) -> String {
+        match self {
+            Rule::Module(_, ident, _, _) => format!("module:{}", ident),
+            Rule::Function(_, ident, _, _) => format!("function:{}", ident),
+            Rule::Literal(_, ident, _, _) => format!("literal:{}", ident),
+            Rule::Argument(_, ident, _, _) => format!("argument:{}", ident),
+            Rule::Write(_, ident, _, _) => format!("write:{}", ident),
+            Rule::Call(_, ident, patterns, _, _) => {
+                format!("call:{}({})", ident, patterns.join(","))
+            }
         }
     }
 }
@@ -60,6 +98,34 @@ impl RuleSet {
             })
             .collect::<Vec<&Rule>>()
     }
+
+    pub fn get_literal_rules(&self) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|&r| matches!(r, Rule::Literal(..)))
+            .collect::<Vec<&Rule>>()
+    }
+
+    pub fn get_argument_rules(&self) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|&r| matches!(r, Rule::Argument(..)))
+            .collect::<Vec<&Rule>>()
+    }
+
+    pub fn get_write_rules(&self) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|&r| matches!(r, Rule::Write(..)))
+            .collect::<Vec<&Rule>>()
+    }
+
+    pub fn get_call_rules(&self) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|&r| matches!(r, Rule::Call(..)))
+            .collect::<Vec<&Rule>>()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -74,11 +140,14 @@ impl RuleManager {
     const DEFAULT_RULES: &'static str = include_str!("rules.ron");
 
     fn load_rules(rule_path: &Option<String>) -> Result<Vec<RuleSet>> {
-        let rules_content: String = match rule_path {
-            Some(rules_path) => {
-                let path = PathBuf::from_str(rules_path.as_str())?;
-                trace!("Loading rulesets from: '{}'", &rules_path);
-                utils::load_from_file(path)?
+        let path = rule_path.as_ref().map(PathBuf::from);
+        let rules_content: String = match &path {
+            Some(path) => {
+                trace!("Loading rulesets from: '{}'", path.display());
+                std::fs::read_to_string(path).map_err(|err| ScoutError::Rules {
+                    path: Some(path.to_owned()),
+                    source: Box::new(err),
+                })?
             }
             None => {
                 trace!(
@@ -94,7 +163,10 @@ impl RuleManager {
                 trace!("Loaded {} rulesets.", &rule_sets.len());
                 Ok(rule_sets)
             }
-            Err(err) => Err(err.into()),
+            Err(err) => Err(ScoutError::Rules {
+                path,
+                source: Box::new(err),
+            }),
         }
     }
 
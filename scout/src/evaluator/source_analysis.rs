@@ -1,72 +1,66 @@
-use crate::{Config, Metadata, SourceFile};
+use crate::{visitors::Definition, Config, HotspotMode, Metadata, NameFinding, SourceFile};
 
 use super::{
-    density_evaluator::{DensityEvaluator, Field, FieldType},
-    Bulletin, Bulletins, Functionality, Hotspot,
+    density_evaluator::DensityEvaluator,
+    explain::Explanation,
+    report::{AnalysisError, Report, ReportOptions, Timings},
+    Bulletin, Bulletins, Functionality, Hotspot, Indicator,
 };
 
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
     fmt,
     hash::{Hash, Hasher},
+    path::Path,
 };
 
-#[derive(Debug, Serialize)]
-pub struct JsonResult<'a> {
-    bulletins: HashMap<String, Vec<&'a Bulletin>>,
-    hotspots: HashMap<String, Vec<Hotspot>>,
-    fields: Option<&'a HashMap<FieldType, Field>>,
-    combined_field: Option<Field>,
+#[derive(Debug)]
+pub struct AnalysisResult {
+    results: Vec<SourceAnalysis>,
+    metadata: Option<Metadata>,
+    name_findings: Vec<NameFinding>,
+    errors: Vec<AnalysisError>,
+    timings: Option<Timings>,
 }
 
-impl<'a> JsonResult<'a> {
-    pub fn new() -> Self {
+impl<'a> AnalysisResult {
+    pub fn new(results: Vec<SourceAnalysis>, metadata: Option<Metadata>) -> Self {
         Self {
-            bulletins: HashMap::new(),
-            hotspots: HashMap::new(),
-            fields: None,
-            combined_field: None,
+            results,
+            metadata,
+            name_findings: vec![],
+            errors: vec![],
+            timings: None,
         }
     }
 
-    pub fn add(&mut self, other: &'a SourceAnalysis) {
-        let source_path = other.source.get_path();
-
-        self.bulletins.insert(source_path.to_string(), vec![]);
-        self.hotspots.insert(source_path.to_string(), vec![]);
-
-        for (mut other_bulletins, hotspot) in other.bulletins_by_hotspot() {
-            if let Some(json_hotspots) = self.hotspots.get_mut(source_path) {
-                json_hotspots.push(hotspot);
-            }
+    pub fn set_name_findings(mut self, name_findings: Vec<NameFinding>) -> Self {
+        self.name_findings = name_findings;
+        self
+    }
 
-            if let Some(json_bulletins) = self.bulletins.get_mut(source_path) {
-                json_bulletins.append(&mut other_bulletins);
-            }
-        }
+    /// What the package name and dependencies have in common with typosquatting.
+    pub fn get_name_findings(&self) -> &Vec<NameFinding> {
+        &self.name_findings
     }
 
-    pub fn add_with_fields(&mut self, other: &'a SourceAnalysis) {
-        self.add(other);
-        self.fields = Some(other.density_evaluator.get_fields());
-        self.combined_field = Some(other.density_evaluator.calculate_combined_field());
+    pub fn set_errors(mut self, errors: Vec<AnalysisError>) -> Self {
+        self.errors = errors;
+        self
     }
 
-    pub fn get_json(&self) -> String {
-        serde_json::to_string(&self).unwrap()
+    /// The code files that could not be read or parsed, and were left out.
+    pub fn get_errors(&self) -> &Vec<AnalysisError> {
+        &self.errors
     }
-}
 
-#[derive(Debug)]
-pub struct AnalysisResult {
-    results: Vec<SourceAnalysis>,
-    metadata: Option<Metadata>,
-}
+    pub fn set_timings(mut self, timings: Timings) -> Self {
+        self.timings = Some(timings);
+        self
+    }
 
-impl<'a> AnalysisResult {
-    pub fn new(results: Vec<SourceAnalysis>, metadata: Option<Metadata>) -> Self {
-        Self { results, metadata }
+    pub fn get_timings(&self) -> Option<&Timings> {
+        self.timings.as_ref()
     }
 
     pub fn get_metadata(&self) -> &Option<Metadata> {
@@ -81,24 +75,69 @@ impl<'a> AnalysisResult {
         Some(self.metadata.as_ref()?.get_deps())
     }
 
-    fn get_json(&self, with_fields: bool) -> String {
-        let mut out = JsonResult::new();
-        for res in self.get_results() {
-            out.add(res);
-
-            if with_fields {
-                out.add_with_fields(res);
+    /// Reports the files under `from` as if they were under `to`, e.g. when an archive
+    /// was analysed after it was unpacked to a temporary directory.
+    pub fn relocate(&mut self, from: &Path, to: &Path) {
+        for result in self.results.iter_mut() {
+            let relative = match result.source.source_path.strip_prefix(from) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => continue,
+            };
+            let old = format!("Location: {}\n", result.source.get_path());
+            result.source.source_path = to.join(relative);
+            if let Some(message) = result.message.as_mut() {
+                let new = format!("Location: {}\n", result.source.get_path());
+                *message = message.replacen(&old, &new, 1);
             }
         }
-        out.get_json()
+        for error in self.errors.iter_mut() {
+            if let Ok(relative) = Path::new(&error.path).strip_prefix(from) {
+                error.path = to.join(relative).display().to_string();
+            }
+        }
+    }
+
+    /// The results in the versioned JSON format, see `Report`.
+    pub fn to_report(&self, options: &ReportOptions) -> Report {
+        Report::new(self, options)
+    }
+
+    /// The JSON output as a value, so it can be nested in other output.
+    pub fn to_json_value(&self) -> serde_json::Value {
+        serde_json::to_value(self.to_report(&ReportOptions::default())).unwrap()
     }
 
     pub fn to_json(&self) -> String {
-        self.get_json(false)
+        self.to_report(&ReportOptions::default()).to_json()
     }
 
     pub fn to_json_with_fields(&self) -> String {
-        self.get_json(true)
+        self.to_report(&ReportOptions::default().set_fields(true))
+            .to_json()
+    }
+
+    pub fn to_json_with_explanations(&self, with_fields: bool) -> String {
+        let options = ReportOptions::default()
+            .set_fields(with_fields)
+            .set_explanations(true);
+        self.to_report(&options).to_json()
+    }
+
+    /// A textual explanation of why each bulletin was or wasn't shown.
+    pub fn explain(&self) -> String {
+        let mut out: String = String::from("");
+        for result in self.get_results() {
+            out += format!("Location: {}\n", result.source.get_path()).as_str();
+            let explanations = result.explain();
+            if explanations.is_empty() {
+                out += "      no bulletins\n";
+            }
+            for explanation in explanations {
+                out += format!("{}", explanation).as_str();
+            }
+            out += "\n";
+        }
+        out
     }
 }
 
@@ -110,6 +149,16 @@ impl fmt::Display for AnalysisResult {
                 result_str.push_str(message);
             }
         }
+        if !self.name_findings.is_empty() {
+            let name = self.metadata.as_ref().map(|m| m.name.as_str());
+            result_str.push_str(&format!(
+                "\nPackage: {}\n",
+                name.filter(|name| !name.is_empty()).unwrap_or("<unknown>")
+            ));
+            for finding in self.name_findings.iter() {
+                result_str.push_str(&format!("      ^{}\n", finding));
+            }
+        }
         write!(f, "{}", result_str)
     }
 }
@@ -120,10 +169,14 @@ pub struct SourceAnalysis {
     pub alerts_imports: i32,
     pub density_evaluator: DensityEvaluator,
     pub bulletins: Bulletins,
+    /// Every network indicator in the file, whether its bulletin is shown or not.
+    #[serde(default)]
+    pub indicators: Vec<Indicator>,
     pub source: SourceFile,
     pub message: Option<String>,
     pub show_all: bool,
     pub global_threshold: f64,
+    pub hotspot_mode: HotspotMode,
 }
 
 impl Hash for SourceAnalysis {
@@ -151,13 +204,24 @@ impl<'a> SourceAnalysis {
             alerts_imports: 0,
             density_evaluator: DensityEvaluator::new(source.get_loc(), config),
             bulletins: vec![],
+            indicators: vec![],
             source,
             message: None,
             show_all: show_all_override,
             global_threshold,
+            hotspot_mode: config.hotspot_mode,
         }
     }
 
+    /// Orders bulletins by their location, so reports and JSON output are stable across runs.
+    pub fn sort_bulletins(&mut self) {
+        self.bulletins.sort_by(|a, b| {
+            (a.line(), a.col(), &a.identifier)
+                .cmp(&(b.line(), b.col(), &b.identifier))
+                .then_with(|| a.reason().cmp(&b.reason()))
+        });
+    }
+
     pub fn found_anything(&self) -> bool {
         (self.alerts_functions > 0 && self.alerts_imports > 0) || !self.bulletins.is_empty()
     }
@@ -222,12 +286,29 @@ impl<'a> SourceAnalysis {
     }
 
     pub fn bulletins_by_hotspot(&'a self) -> Vec<(Vec<&'a Bulletin>, Hotspot)> {
+        let groups = match self.hotspot_mode {
+            HotspotMode::Density => self.group_by_density(self.bulletins.iter().collect()),
+            HotspotMode::Definition => self.group_by_definition(),
+        };
+
+        trace!("Bulletins by hotspot: {:?}", &groups);
+        groups
+    }
+
+    fn group_by_density(
+        &'a self,
+        bulletins: Vec<&'a Bulletin>,
+    ) -> Vec<(Vec<&'a Bulletin>, Hotspot)> {
         let mut groups: Vec<(Vec<&'a Bulletin>, Hotspot)> = vec![];
 
+        if bulletins.is_empty() {
+            return groups;
+        }
+
         for hotspot in self.get_hotspots() {
             let mut group: Vec<&'a Bulletin> = vec![];
 
-            for bulletin in self.bulletins.iter() {
+            for &bulletin in bulletins.iter() {
                 if self.bulletin_display_check(bulletin, &hotspot) {
                     group.push(bulletin);
                 }
@@ -238,10 +319,76 @@ impl<'a> SourceAnalysis {
             }
         }
 
-        trace!("Bulletins by hotspot: {:?}", &groups);
         groups
     }
 
+    /// Groups bulletins by the innermost function or class they are found in.
+    /// Bulletins outside of any definition fall back to density hotspots.
+    fn group_by_definition(&'a self) -> Vec<(Vec<&'a Bulletin>, Hotspot)> {
+        let mut definitions: Vec<&Definition> = vec![];
+        let mut by_definition: Vec<Vec<&'a Bulletin>> = vec![];
+        let mut outside: Vec<&'a Bulletin> = vec![];
+
+        for bulletin in self.bulletins.iter() {
+            match self.source.get_definition(bulletin.line()) {
+                Some(definition) => {
+                    match definitions
+                        .iter()
+                        .position(|d| std::ptr::eq(*d, definition))
+                    {
+                        Some(idx) => by_definition[idx].push(bulletin),
+                        None => {
+                            definitions.push(definition);
+                            by_definition.push(vec![bulletin]);
+                        }
+                    }
+                }
+                None => outside.push(bulletin),
+            }
+        }
+
+        let mut groups = self.group_by_density(outside);
+        let hotspots = self.density_evaluator.definition_hotspots(&definitions);
+
+        for (bulletins, hotspot) in by_definition.into_iter().zip(hotspots) {
+            let group: Vec<&'a Bulletin> = bulletins
+                .into_iter()
+                .filter(|b| self.bulletin_display_check(b, &hotspot))
+                .collect();
+
+            if !group.is_empty() {
+                groups.push((group, hotspot));
+            }
+        }
+
+        groups.sort_by(|(_, a), (_, b)| a.startx.total_cmp(&b.startx));
+        groups
+    }
+
+    /// The hotspot a bulletin would be grouped under if it passed every threshold.
+    fn candidate_hotspot(&self, bulletin: &Bulletin) -> Option<Hotspot> {
+        if self.hotspot_mode == HotspotMode::Definition {
+            if let Some(definition) = self.source.get_definition(bulletin.line()) {
+                return self
+                    .density_evaluator
+                    .definition_hotspots(&[definition])
+                    .pop();
+            }
+        }
+
+        self.get_hotspots()
+            .into_iter()
+            .find(|h| bulletin.line() >= h.line_low() && bulletin.line() <= h.line_high())
+    }
+
+    /// Explains every bulletin in the file, including the ones that are hidden.
+    pub fn explain(&self) -> Vec<Explanation<'_>> {
+        self.bulletins
+            .iter()
+            .map(|b| Explanation::new(self, b, self.candidate_hotspot(b)))
+            .collect()
+    }
+
     pub fn display_functionality(&self) {
         for f in self.get_uniq_functionality(&self.get_all_bulletins()) {
             debug!("Functionality found: {:?}", f);
@@ -1,4 +1,5 @@
 mod config;
+mod error;
 mod evaluator;
 mod package;
 mod source;
@@ -8,30 +9,46 @@ mod visitors;
 #[macro_use]
 extern crate log;
 
-pub use config::Config;
+pub use config::{Config, HotspotMode};
+pub use error::ScoutError;
 
-pub use evaluator::{Evaluator, RuleManager, SourceAnalysis};
-pub use package::{Metadata, Package};
-pub use source::SourceFile;
+pub use evaluator::{
+    AnalysisError, AnalysisResult, BulletinReport, Canaries, DensityEvaluator, DensityPlot,
+    Evaluator, Explanation, FieldType, FileReport, HotspotReport, PackageReport, ParseStatus,
+    Report, ReportOptions, RuleManager, SourceAnalysis, Timings, SCHEMA_VERSION,
+};
+pub use package::{
+    BulletinChange, DiffResult, Distribution, DistributionResult, EntryPointChange, FileDiff,
+    InstalledResult, JsonLines, Listener, Metadata, NameFinding, Package, PackageSummary,
+    PopularPackages, RecordIssue, RequirementResult, RequirementsResult, ScanOutcome, Unscanned,
+    Wheelhouse,
+};
+pub use source::{FixKind, SourceFile, SourceFix};
 
 pub use engine::Engine;
 
-use std::error;
-pub type Result<T> = std::result::Result<T, Box<dyn error::Error + Send + Sync>>;
+pub type Result<T> = std::result::Result<T, ScoutError>;
 
 mod engine {
 
-    use std::{path::PathBuf, str::FromStr};
+    use std::path::PathBuf;
 
     use crate::evaluator::AnalysisResult;
-    use crate::{Config, Result};
-    use crate::{Package, RuleManager};
+    use crate::package::{diff_root, read_requirements, site_packages_dir};
+    use crate::{
+        Canaries, DiffResult, Distribution, DistributionResult, InstalledResult, Listener, Package,
+        PackageSummary, RequirementResult, RequirementsResult, RuleManager, ScanOutcome, Unscanned,
+        Wheelhouse,
+    };
+    use crate::{Config, Result, ScoutError};
 
     pub struct Engine {
         rule_path: Option<String>,
+        canary_path: Option<String>,
 
         opt_show_all: bool,
         opt_threshold: f64,
+        opt_explain: bool,
 
         config: Config,
     }
@@ -40,18 +57,23 @@ mod engine {
         pub fn new() -> Self {
             Engine {
                 rule_path: None,
+                canary_path: None,
                 opt_show_all: false,
                 opt_threshold: 0.0,
-                config: Config::new(&None).unwrap(),
+                opt_explain: false,
+                config: Config::default(),
             }
         }
 
-        pub fn set_config_path(&mut self, config_path: Option<String>) {
-            self.config = Config::new(&config_path).unwrap();
+        /// Loads the config from the given path, or discovers one when no path is given.
+        pub fn set_config_path(&mut self, config_path: Option<String>) -> Result<()> {
+            self.config = Config::new(&config_path)?;
+            Ok(())
         }
 
-        pub fn set_config(&mut self, json: String) {
-            self.config = Config::from_str(json).unwrap();
+        pub fn set_config(&mut self, json: String) -> Result<()> {
+            self.config = Config::from_str(json)?;
+            Ok(())
         }
 
         pub fn set_show_all(mut self, show_all: bool) -> Self {
@@ -64,69 +86,205 @@ mod engine {
             self
         }
 
+        pub fn set_explain(mut self, explain: bool) -> Self {
+            self.opt_explain = explain;
+            self
+        }
+
         pub fn set_rule_path(mut self, rule_path: Option<String>) -> Self {
             self.rule_path = rule_path;
             self
         }
 
+        /// Overrides the canary table set in the config.
+        pub fn set_canary_path(mut self, canary_path: Option<String>) -> Self {
+            self.canary_path = canary_path;
+            self
+        }
+
         fn get_rule_manager(&self) -> Result<RuleManager> {
-            Ok(RuleManager::new(&self.rule_path)?)
+            RuleManager::new(&self.rule_path)
         }
 
-        pub fn analyse_package(self, path: &str) -> Result<AnalysisResult> {
-            trace!("Analysing package: '{}'", &path);
-            let pkg = match Package::locate_package(&path) {
-                Some(path) => {
-                    debug!("Detected package: '{:?}'", &path);
-                    path
-                }
-                None => return Err("Could not detect package".into()),
+        fn get_canaries(&self) -> Result<Canaries> {
+            let canary_path = match &self.canary_path {
+                Some(_) => &self.canary_path,
+                None => &self.config.canary_path,
             };
 
-            let rule_manager = match self.get_rule_manager() {
-                Ok(rm) => rm,
-                Err(err) => {
-                    return Err(format!(
-                        "Rule manager could not be initalized: {}",
-                        err.to_string()
-                    )
-                    .into())
-                }
-            };
+            Canaries::new(canary_path)
+        }
 
-            let results = Package::new(
-                pkg,
-                rule_manager,
+        fn get_package(&self, path: PathBuf, show_all: bool) -> Result<Package<'_>> {
+            Ok(Package::new(
+                path,
+                self.get_rule_manager()?,
+                self.get_canaries()?,
                 self.opt_threshold,
-                self.opt_show_all,
+                show_all,
                 &self.config,
             )?
-            .analyse()?;
+            .set_explain(self.opt_explain))
+        }
+
+        fn locate_package(path: &str) -> Result<PathBuf> {
+            match Package::locate_package(path) {
+                Some(path) => {
+                    debug!("Detected package: '{:?}'", &path);
+                    Ok(path)
+                }
+                None => Err(ScoutError::PackageNotFound {
+                    path: PathBuf::from(path),
+                }),
+            }
+        }
+
+        pub fn analyse_package(self, path: &str) -> Result<AnalysisResult> {
+            trace!("Analysing package: '{}'", &path);
+            let pkg = Engine::locate_package(path)?;
+
+            let results = self.get_package(pkg, self.opt_show_all)?.analyse()?;
             Ok(results)
         }
 
+        /// Analyses two versions of a package and keeps only what the new one adds: new
+        /// bulletins, bulletins in denser hotspots, added files, new dependencies and
+        /// changed entry points.
+        pub fn analyse_diff(self, old: &str, new: &str) -> Result<DiffResult> {
+            trace!("Comparing package '{}' to '{}'", &new, &old);
+            let old = diff_root(&Engine::locate_package(old)?);
+            let new = diff_root(&Engine::locate_package(new)?);
+
+            // everything in the old version counts, even when it was below the threshold
+            let old_results = self.get_package(old.to_owned(), true)?.analyse()?;
+            let new_results = self
+                .get_package(new.to_owned(), self.opt_show_all)?
+                .analyse()?;
+            Ok(DiffResult::new(&old, &old_results, &new, &new_results))
+        }
+
+        /// Analyses every distribution installed in a site-packages directory, or in the
+        /// one of a virtualenv, and checks their files against `RECORD`.
+        pub fn analyse_installed(self, path: &str) -> Result<InstalledResult> {
+            let site_packages = site_packages_dir(&PathBuf::from(path));
+            trace!("Analysing site-packages: '{}'", site_packages.display());
+
+            let mut distributions: Vec<DistributionResult> = vec![];
+            for distribution in Distribution::find_all(&site_packages)? {
+                debug!(
+                    "Analysing distribution: '{}'",
+                    distribution.dist_info.display()
+                );
+                // a distribution that can't be analysed doesn't stop the others
+                let (result, error) = match self
+                    .get_package(site_packages.to_owned(), self.opt_show_all)?
+                    .set_files(distribution.files)
+                    .analyse()
+                {
+                    Ok(result) => (result, None),
+                    Err(err) => (AnalysisResult::new(vec![], None), Some(err.to_string())),
+                };
+                distributions.push(DistributionResult {
+                    name: distribution.name,
+                    version: distribution.version,
+                    issues: distribution.issues,
+                    result,
+                    error,
+                });
+            }
+
+            Ok(InstalledResult {
+                site_packages,
+                distributions,
+            })
+        }
+
+        /// Resolves each pinned requirement in a requirements or lock file to a wheel or
+        /// source distribution in a local wheelhouse and analyses it. Requirements without
+        /// a matching artifact are reported as unscanned.
+        pub fn analyse_requirements(
+            self,
+            requirements: &str,
+            wheelhouse: &str,
+        ) -> Result<RequirementsResult> {
+            let requirements_file = PathBuf::from(requirements);
+            let wheelhouse = Wheelhouse::new(&PathBuf::from(wheelhouse))?;
+            trace!(
+                "Scanning '{}' against wheelhouse '{}'",
+                requirements_file.display(),
+                wheelhouse.path.display()
+            );
+
+            let mut results: Vec<RequirementResult> = vec![];
+            for (line, requirement) in read_requirements(&requirements_file)? {
+                let outcome = match wheelhouse.resolve(&requirement) {
+                    Ok(artifact) => {
+                        debug!("Analysing artifact: '{}'", artifact.path.display());
+                        let analysed = artifact.unpack().and_then(|unpacked| {
+                            let mut result = self
+                                .get_package(unpacked.path().to_path_buf(), self.opt_show_all)?
+                                .analyse()?;
+                            result.relocate(unpacked.path(), &artifact.path);
+                            Ok(result)
+                        });
+                        match analysed {
+                            Ok(result) => ScanOutcome::Scanned {
+                                artifact: artifact.path.to_owned(),
+                                result,
+                            },
+                            Err(err) => ScanOutcome::Unscanned(Unscanned::Failed {
+                                artifact: artifact.path.to_owned(),
+                                error: err.to_string(),
+                            }),
+                        }
+                    }
+                    Err(unscanned) => ScanOutcome::Unscanned(unscanned),
+                };
+                results.push(RequirementResult {
+                    requirement: line,
+                    name: requirement.name,
+                    outcome,
+                });
+            }
+
+            Ok(RequirementsResult {
+                requirements_file,
+                wheelhouse: wheelhouse.path,
+                requirements: results,
+            })
+        }
+
+        /// Like `analyse_package`, but passes the results of each file to the listener as
+        /// soon as it is analysed, instead of keeping them all in memory.
+        pub fn analyse_package_streaming(
+            self,
+            path: &str,
+            listener: &mut dyn Listener,
+        ) -> Result<PackageSummary> {
+            trace!("Streaming package: '{}'", &path);
+            let pkg = Engine::locate_package(path)?;
+
+            self.get_package(pkg, self.opt_show_all)?
+                .analyse_streaming(listener)
+        }
+
+        pub fn analyse_file_streaming(
+            self,
+            path: &str,
+            listener: &mut dyn Listener,
+        ) -> Result<PackageSummary> {
+            let path = PathBuf::from(path);
+
+            self.get_package(path, self.opt_show_all)?
+                .analyse_streaming(listener)
+        }
+
         pub fn analyse_file(self, path: &str) -> Result<AnalysisResult> {
-            let path = PathBuf::from_str(path)?;
-
-            let rule_manager = match self.get_rule_manager() {
-                Ok(rm) => rm,
-                Err(err) => {
-                    return Err(format!(
-                        "Rule manager could not be initalized: {}",
-                        err.to_string()
-                    )
-                    .into())
-                }
-            };
+            let path = PathBuf::from(path);
 
-            let results = Package::new(
-                path,
-                rule_manager,
-                self.opt_threshold,
-                self.opt_show_all,
-                &self.config,
-            )?
-            .analyse_single()?;
+            let results = self
+                .get_package(path, self.opt_show_all)?
+                .analyse_single()?;
             Ok(results)
         }
     }
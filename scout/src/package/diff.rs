@@ -0,0 +1,404 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use super::requirement::{normalize_name, Requirement};
+use crate::evaluator::{AnalysisResult, Bulletin, SourceAnalysis};
+
+/// Peaks are only compared up to this, so rounding doesn't count as growth.
+const PEAK_EPSILON: f64 = 1e-6;
+
+/// Why a bulletin in the new version is reported.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum BulletinChange {
+    /// Nothing like it was in the same file of the old version
+    New,
+    /// It was there before, but the hotspot around it got denser
+    PeakGrew { old_peak: f64, new_peak: f64 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulletinDiff {
+    pub line: usize,
+    pub col: usize,
+    pub identifier: String,
+    pub reason: String,
+    /// The line of code the bulletin is on
+    pub code: String,
+    #[serde(flatten)]
+    pub change: BulletinChange,
+}
+
+/// The bulletins of a file that add risk compared to the old version.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    /// The path relative to the package, e.g. `pkg/module.py`
+    pub path: String,
+    /// The file is not in the old version
+    pub added: bool,
+    pub bulletins: Vec<BulletinDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum EntryPointChange {
+    Added {
+        entry_point: String,
+        target: String,
+    },
+    Changed {
+        entry_point: String,
+        old_target: String,
+        target: String,
+    },
+    Removed {
+        entry_point: String,
+        old_target: String,
+    },
+}
+
+impl fmt::Display for EntryPointChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryPointChange::Added {
+                entry_point,
+                target,
+            } => write!(f, "+ {} = {}", entry_point, target),
+            EntryPointChange::Changed {
+                entry_point,
+                old_target,
+                target,
+            } => write!(f, "~ {} = {} (was {})", entry_point, target, old_target),
+            EntryPointChange::Removed {
+                entry_point,
+                old_target,
+            } => write!(f, "- {} = {}", entry_point, old_target),
+        }
+    }
+}
+
+/// What a new version of a package adds in risk compared to an old one.
+#[derive(Debug, Default, Serialize)]
+pub struct DiffResult {
+    pub added_files: Vec<String>,
+    /// Requirements, as declared, whose package wasn't a dependency before
+    pub new_dependencies: Vec<String>,
+    pub entry_points: Vec<EntryPointChange>,
+    pub files: Vec<FileDiff>,
+}
+
+/// Sdists unpack to a single `name-version` directory, which is skipped so the files
+/// of both versions line up.
+pub fn diff_root(path: &Path) -> PathBuf {
+    let mut root = path.to_path_buf();
+    loop {
+        let entries: Vec<PathBuf> = match std::fs::read_dir(&root) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(_) => return root,
+        };
+        match entries.as_slice() {
+            [only] if only.is_dir() => root = only.to_owned(),
+            _ => return root,
+        }
+    }
+}
+
+/// The path of a file relative to the package, with the version dropped from
+/// `.dist-info` and `.egg-info` directories.
+fn relative_path(root: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(root).unwrap_or(path);
+    path.components()
+        .map(|component| {
+            let component = component.as_os_str().to_string_lossy();
+            for ext in [".dist-info", ".egg-info"] {
+                if let Some(stem) = component.strip_suffix(ext) {
+                    let name = stem.split('-').next().unwrap_or(stem);
+                    return format!("{}{}", name, ext);
+                }
+            }
+            component.into_owned()
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+fn list_files(root: &Path) -> BTreeSet<String> {
+    WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| relative_path(root, e.path()))
+        .collect()
+}
+
+/// Every entry point as `group: name` with its target, from any `entry_points.txt`.
+fn list_entry_points(root: &Path) -> BTreeMap<String, String> {
+    let mut entry_points: BTreeMap<String, String> = BTreeMap::new();
+
+    for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+        if entry.file_name() != "entry_points.txt" {
+            continue;
+        }
+        let content = match std::fs::read_to_string(entry.path()) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+
+        let mut group = String::from("");
+        for line in content.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                group = name.trim().to_owned();
+            } else if let Some((name, target)) = line.split_once('=') {
+                if !line.starts_with('#') {
+                    entry_points.insert(
+                        format!("{}: {}", group, name.trim()),
+                        target.trim().to_owned(),
+                    );
+                }
+            }
+        }
+    }
+
+    entry_points
+}
+
+fn requirements(results: &AnalysisResult) -> Vec<Requirement> {
+    match results.get_metadata() {
+        Some(metadata) => metadata
+            .requirements
+            .iter()
+            .filter_map(|r| Requirement::parse(r))
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// The highest peak of the hotspots the bulletin is in.
+fn peak(analysis: &SourceAnalysis, bulletin: &Bulletin) -> f64 {
+    analysis
+        .bulletins_by_hotspot()
+        .iter()
+        .filter(|(_, hotspot)| {
+            bulletin.line() >= hotspot.line_low() && bulletin.line() <= hotspot.line_high()
+        })
+        .map(|(_, hotspot)| hotspot.peak())
+        .fold(0f64, f64::max)
+}
+
+fn key(bulletin: &Bulletin) -> (String, String) {
+    (bulletin.identifier.to_owned(), bulletin.reason())
+}
+
+/// Compares the visible bulletins of a file to those of its old version. Lines move
+/// between versions, so bulletins are matched by what they found rather than where.
+fn diff_file(old: Option<&SourceAnalysis>, new: &SourceAnalysis) -> Vec<BulletinDiff> {
+    // how many times each finding was in the old file, and the highest peak around it
+    let mut seen: HashMap<(String, String), (usize, f64)> = HashMap::new();
+    if let Some(old) = old {
+        for bulletin in old.get_all_bulletins() {
+            let entry = seen.entry(key(bulletin)).or_insert((0, 0f64));
+            entry.0 += 1;
+            entry.1 = entry.1.max(peak(old, bulletin));
+        }
+    }
+
+    let lines: Vec<&str> = new.source.get_source().lines().collect();
+    let mut diffs: Vec<BulletinDiff> = vec![];
+    for bulletin in new.get_visible_bulletins() {
+        let new_peak = peak(new, bulletin);
+        let change = match seen.get_mut(&key(bulletin)) {
+            Some((count, old_peak)) if *count > 0 => {
+                *count -= 1;
+                if new_peak <= *old_peak + PEAK_EPSILON {
+                    continue;
+                }
+                BulletinChange::PeakGrew {
+                    old_peak: *old_peak,
+                    new_peak,
+                }
+            }
+            _ => BulletinChange::New,
+        };
+
+        diffs.push(BulletinDiff {
+            line: bulletin.line(),
+            col: bulletin.col(),
+            identifier: bulletin.identifier.to_owned(),
+            reason: bulletin.reason(),
+            code: lines
+                .get(bulletin.line().saturating_sub(1))
+                .map(|line| line.trim().to_owned())
+                .unwrap_or_default(),
+            change,
+        });
+    }
+
+    diffs.sort_by_key(|diff| (diff.line, diff.col));
+    diffs.dedup_by(|a, b| a.line == b.line && a.col == b.col && a.reason == b.reason);
+    diffs
+}
+
+impl DiffResult {
+    /// Compares the results for the new version against those for the old one. The old
+    /// version should be analysed with every bulletin kept, so nothing is reported as new
+    /// only because it was below the threshold before.
+    pub fn new(
+        old_root: &Path,
+        old: &AnalysisResult,
+        new_root: &Path,
+        new: &AnalysisResult,
+    ) -> Self {
+        let old_files = list_files(old_root);
+        let added_files: Vec<String> = list_files(new_root)
+            .into_iter()
+            .filter(|path| !old_files.contains(path))
+            .collect();
+
+        let old_deps: BTreeSet<String> = requirements(old)
+            .iter()
+            .map(|r| normalize_name(&r.name))
+            .collect();
+        let mut new_deps: BTreeSet<String> = BTreeSet::new();
+        let new_dependencies: Vec<String> = requirements(new)
+            .into_iter()
+            .filter(|r| !old_deps.contains(&normalize_name(&r.name)))
+            .filter(|r| new_deps.insert(normalize_name(&r.name)))
+            .map(|r| match &r.url {
+                Some(url) => format!("{} @ {}", r.name, url),
+                None => format!("{}{}", r.name, r.specifier),
+            })
+            .collect();
+
+        let old_entry_points = list_entry_points(old_root);
+        let new_entry_points = list_entry_points(new_root);
+        let mut entry_points: Vec<EntryPointChange> = vec![];
+        for (entry_point, target) in new_entry_points.iter() {
+            match old_entry_points.get(entry_point) {
+                None => entry_points.push(EntryPointChange::Added {
+                    entry_point: entry_point.to_owned(),
+                    target: target.to_owned(),
+                }),
+                Some(old_target) if old_target != target => {
+                    entry_points.push(EntryPointChange::Changed {
+                        entry_point: entry_point.to_owned(),
+                        old_target: old_target.to_owned(),
+                        target: target.to_owned(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for (entry_point, old_target) in old_entry_points.iter() {
+            if !new_entry_points.contains_key(entry_point) {
+                entry_points.push(EntryPointChange::Removed {
+                    entry_point: entry_point.to_owned(),
+                    old_target: old_target.to_owned(),
+                });
+            }
+        }
+
+        let old_analyses: HashMap<String, &SourceAnalysis> = old
+            .get_results()
+            .iter()
+            .map(|a| (relative_path(old_root, Path::new(a.source.get_path())), a))
+            .collect();
+        let mut files: Vec<FileDiff> = new
+            .get_results()
+            .iter()
+            .filter_map(|analysis| {
+                let path = relative_path(new_root, Path::new(analysis.source.get_path()));
+                let old = old_analyses.get(&path).copied();
+                // scores depend on the rest of the package, an unchanged file adds nothing
+                if old.is_some_and(|old| old.source.get_source() == analysis.source.get_source()) {
+                    return None;
+                }
+                // derived sources, e.g. `nb.ipynb#cell-2`, belong to their file
+                let file = path.split('#').next().unwrap_or_default();
+                Some(FileDiff {
+                    added: !old_files.contains(file),
+                    bulletins: diff_file(old, analysis),
+                    path,
+                })
+            })
+            .filter(|file| !file.bulletins.is_empty())
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        DiffResult {
+            added_files,
+            new_dependencies,
+            entry_points,
+            files,
+        }
+    }
+
+    /// Whether the new version adds anything at all.
+    pub fn is_empty(&self) -> bool {
+        self.added_files.is_empty()
+            && self.new_dependencies.is_empty()
+            && self.entry_points.is_empty()
+            && self.files.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self).unwrap()
+    }
+}
+
+impl fmt::Display for DiffResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.added_files.is_empty() {
+            writeln!(f, "{}", "Added files:".bold())?;
+            for path in self.added_files.iter() {
+                writeln!(f, "  + {}", path)?;
+            }
+        }
+        if !self.new_dependencies.is_empty() {
+            writeln!(f, "{}", "New dependencies:".bold())?;
+            for dependency in self.new_dependencies.iter() {
+                writeln!(f, "  + {}", dependency)?;
+            }
+        }
+        if !self.entry_points.is_empty() {
+            writeln!(f, "{}", "Entry points:".bold())?;
+            for change in self.entry_points.iter() {
+                writeln!(f, "  {}", change)?;
+            }
+        }
+
+        for file in self.files.iter() {
+            let added = if file.added { " (added)" } else { "" };
+            writeln!(f, "Location: {}{}", file.path, added)?;
+            let mut last_line = 0;
+            for bulletin in file.bulletins.iter() {
+                if bulletin.line != last_line {
+                    writeln!(f, "{:>3}| {}", bulletin.line, bulletin.code.dimmed())?;
+                    last_line = bulletin.line;
+                }
+                let change = match bulletin.change {
+                    BulletinChange::New => String::from("new"),
+                    BulletinChange::PeakGrew { old_peak, new_peak } => {
+                        format!("hotspot peak {:.2} -> {:.2}", old_peak, new_peak)
+                    }
+                };
+                writeln!(
+                    f,
+                    "      {} {}",
+                    format!("^{}", bulletin.reason).yellow(),
+                    format!("({})", change).dimmed()
+                )?;
+            }
+        }
+
+        if self.is_empty() {
+            writeln!(f, "No new risk was introduced.")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use colored::Colorize;
+use serde::Serialize;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use walkdir::WalkDir;
+
+use crate::evaluator::AnalysisResult;
+use crate::{utils, Result, ScoutError};
+
+/// Why a file of an installed distribution is not what was installed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum RecordIssue {
+    /// The hash of the file doesn't match the one in `RECORD`
+    Modified {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+    /// `RECORD` lists the file, but it is gone
+    Missing { path: String },
+    /// The path in `RECORD` is absolute or outside the environment, it isn't read
+    OutsideEnvironment { path: String },
+    /// The path in `RECORD` exists, but isn't a regular file, so it isn't read
+    NotAFile { path: String },
+    /// The file is inside a top-level package of the distribution, but no `RECORD` in the
+    /// environment lists it
+    Unrecorded { path: String },
+}
+
+impl fmt::Display for RecordIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordIssue::Modified { path, .. } => {
+                write!(f, "'{}' was modified after it was installed", path)
+            }
+            RecordIssue::Missing { path } => {
+                write!(f, "'{}' is in RECORD, but was removed", path)
+            }
+            RecordIssue::OutsideEnvironment { path } => {
+                write!(f, "'{}' in RECORD is outside the environment", path)
+            }
+            RecordIssue::NotAFile { path } => {
+                write!(f, "'{}' in RECORD is not a regular file", path)
+            }
+            RecordIssue::Unrecorded { path } => {
+                write!(
+                    f,
+                    "'{}' was added after the distribution was installed",
+                    path
+                )
+            }
+        }
+    }
+}
+
+/// A distribution installed in a site-packages directory, found by its `.dist-info`.
+#[derive(Debug)]
+pub struct Distribution {
+    pub name: String,
+    pub version: String,
+    pub dist_info: PathBuf,
+    /// Every file of the distribution that exists, including unrecorded ones
+    pub files: Vec<PathBuf>,
+    pub issues: Vec<RecordIssue>,
+    /// The paths in `RECORD`, whether they exist or not
+    recorded: HashSet<PathBuf>,
+}
+
+/// The results of one installed distribution.
+#[derive(Debug)]
+pub struct DistributionResult {
+    pub name: String,
+    pub version: String,
+    pub issues: Vec<RecordIssue>,
+    pub result: AnalysisResult,
+    /// Why the distribution could not be analysed, `result` is empty then
+    pub error: Option<String>,
+}
+
+/// The results of every distribution in a site-packages directory.
+#[derive(Debug)]
+pub struct InstalledResult {
+    pub site_packages: PathBuf,
+    pub distributions: Vec<DistributionResult>,
+}
+
+impl InstalledResult {
+    pub fn to_json(&self) -> String {
+        let distributions: Vec<serde_json::Value> = self
+            .distributions
+            .iter()
+            .map(|distribution| {
+                serde_json::json!({
+                    "name": distribution.name,
+                    "version": distribution.version,
+                    "record_issues": distribution.issues,
+                    "error": distribution.error,
+                    "results": distribution.result.to_json_value(),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "site_packages": self.site_packages,
+            "distributions": distributions,
+        })
+        .to_string()
+    }
+}
+
+impl fmt::Display for InstalledResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for distribution in self.distributions.iter() {
+            let report = distribution.result.to_string();
+            if distribution.issues.is_empty()
+                && distribution.error.is_none()
+                && report.trim().is_empty()
+            {
+                continue;
+            }
+            let header = format!(
+                "Distribution: {} {}",
+                distribution.name, distribution.version
+            );
+            writeln!(f, "{}", header.bold())?;
+            for issue in distribution.issues.iter() {
+                writeln!(f, "      {}", format!("^{}", issue).yellow())?;
+            }
+            if let Some(error) = &distribution.error {
+                writeln!(
+                    f,
+                    "      {}",
+                    format!("Could not be analysed: {}", error).red()
+                )?;
+            }
+            write!(f, "{}", report)?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits a CSV line, fields may be quoted to contain commas.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields: Vec<String> = vec![String::from("")];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::from("")),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Hashes the data the way `RECORD` does, e.g. `sha256=<urlsafe base64 without padding>`.
+/// Returns `None` for algorithms other than SHA-2.
+fn record_hash(algorithm: &str, data: &[u8]) -> Option<String> {
+    let digest = match algorithm {
+        "sha256" => Sha256::digest(data).to_vec(),
+        "sha384" => Sha384::digest(data).to_vec(),
+        "sha512" => Sha512::digest(data).to_vec(),
+        _ => return None,
+    };
+    Some(format!(
+        "{}={}",
+        algorithm,
+        base64::encode_config(digest, base64::URL_SAFE_NO_PAD)
+    ))
+}
+
+/// Finds the site-packages directory of a virtualenv, or returns the path as it is.
+pub fn site_packages_dir(path: &Path) -> PathBuf {
+    let has_dist_info = |dir: &Path| {
+        std::fs::read_dir(dir).is_ok_and(|mut entries| {
+            entries
+                .any(|e| e.is_ok_and(|e| e.file_name().to_string_lossy().ends_with(".dist-info")))
+        })
+    };
+    if has_dist_info(path) {
+        return path.to_path_buf();
+    }
+
+    // `lib/python3.X/site-packages` on POSIX, `Lib/site-packages` on Windows
+    let mut candidates: Vec<PathBuf> = vec![path.join("Lib").join("site-packages")];
+    for lib in ["lib", "lib64"] {
+        if let Ok(entries) = std::fs::read_dir(path.join(lib)) {
+            let mut pythons: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path().join("site-packages"))
+                .collect();
+            pythons.sort();
+            candidates.extend(pythons);
+        }
+    }
+    candidates
+        .into_iter()
+        .find(|dir| has_dist_info(dir))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// The root of the environment a site-packages directory is in, e.g. of a virtualenv for
+/// `lib/python3.11/site-packages` or `Lib/site-packages`. `RECORD` can point there for
+/// scripts, but never outside of it. Any other directory is its own root.
+fn environment_prefix(site: &Path) -> PathBuf {
+    let name = |path: Option<&Path>| {
+        path.and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().to_lowercase())
+    };
+    let parent = site.parent();
+    let grandparent = parent.and_then(Path::parent);
+    let prefix = match (name(parent), name(grandparent)) {
+        (Some(python), Some(lib)) if python.starts_with("python") && lib.starts_with("lib") => {
+            grandparent.and_then(Path::parent)
+        }
+        (Some(lib), _) if lib == "lib" => grandparent,
+        _ => None,
+    };
+    prefix.unwrap_or(site).to_path_buf()
+}
+
+/// Resolves `.` and `..` in the path without following links.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+impl Distribution {
+    /// Reads the distribution from its `.dist-info` directory, checking every file in
+    /// `RECORD` against its hash. Unrecorded files are only found by `find_all`, which
+    /// knows what the other distributions record.
+    pub fn load(dist_info: &Path) -> Result<Distribution> {
+        let site = dist_info
+            .parent()
+            .ok_or_else(|| ScoutError::PackageNotFound {
+                path: dist_info.to_path_buf(),
+            })?;
+        let stem = dist_info
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let stem = stem.trim_end_matches(".dist-info");
+        let (name, version) = stem.split_once('-').unwrap_or((stem, ""));
+
+        let record = utils::load_from_file(dist_info.join("RECORD"))?;
+        let root = std::fs::canonicalize(site).map_err(|err| ScoutError::from(err).at(site))?;
+        let prefix = environment_prefix(&root);
+
+        let mut files: Vec<PathBuf> = vec![];
+        let mut recorded: HashSet<PathBuf> = HashSet::new();
+        let mut issues: Vec<RecordIssue> = vec![];
+
+        for line in record.lines().filter(|line| !line.trim().is_empty()) {
+            let fields = split_csv(line);
+            let relative = fields[0].to_owned();
+            let entry = Path::new(&relative);
+            // a tampered RECORD could point anywhere, e.g. to `/dev/zero` or a home directory
+            let absolute = entry
+                .components()
+                .any(|component| matches!(component, Component::Prefix(_) | Component::RootDir));
+            if absolute || !normalize(&root.join(entry)).starts_with(&prefix) {
+                issues.push(RecordIssue::OutsideEnvironment { path: relative });
+                continue;
+            }
+            let path = site.join(entry);
+            recorded.insert(path.to_owned());
+
+            // links are followed when reading, so they have to stay inside as well
+            match std::fs::canonicalize(&path) {
+                Ok(resolved) if !resolved.starts_with(&prefix) => {
+                    issues.push(RecordIssue::OutsideEnvironment { path: relative });
+                    continue;
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    issues.push(RecordIssue::Missing { path: relative });
+                    continue;
+                }
+            }
+            match std::fs::metadata(&path) {
+                Ok(metadata) if metadata.is_file() => {}
+                Ok(_) => {
+                    issues.push(RecordIssue::NotAFile { path: relative });
+                    continue;
+                }
+                Err(_) => {
+                    issues.push(RecordIssue::Missing { path: relative });
+                    continue;
+                }
+            }
+            let data = match std::fs::read(&path) {
+                Ok(data) => data,
+                Err(_) => {
+                    issues.push(RecordIssue::Missing { path: relative });
+                    continue;
+                }
+            };
+            files.push(path);
+
+            let expected = match fields.get(1).filter(|hash| !hash.is_empty()) {
+                Some(expected) => expected,
+                None => continue,
+            };
+            let algorithm = expected.split('=').next().unwrap_or_default();
+            if let Some(actual) = record_hash(algorithm, &data) {
+                if &actual != expected {
+                    issues.push(RecordIssue::Modified {
+                        path: relative,
+                        expected: expected.to_owned(),
+                        actual,
+                    });
+                }
+            }
+        }
+
+        Ok(Distribution {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            dist_info: dist_info.to_path_buf(),
+            files,
+            issues,
+            recorded,
+        })
+    }
+
+    /// Reports the files in the top-level packages of the distribution that no `RECORD`
+    /// lists, and adds them to its files. Namespace packages share their top-level
+    /// directory with other distributions, so `recorded` holds the paths of every
+    /// distribution in the environment, and `reported` the files already reported for
+    /// another one.
+    fn add_unrecorded(
+        &mut self,
+        site: &Path,
+        recorded: &HashSet<PathBuf>,
+        reported: &mut HashSet<PathBuf>,
+    ) {
+        for top_level in Distribution::top_level(&self.dist_info, &self.recorded, site) {
+            let dir = site.join(&top_level);
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in WalkDir::new(&dir)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                let path = entry.path().to_path_buf();
+                // bytecode is written on import and isn't recorded
+                let in_pycache = path
+                    .parent()
+                    .and_then(|parent| parent.file_name())
+                    .is_some_and(|name| name == "__pycache__");
+                if recorded.contains(&path) || in_pycache || !reported.insert(path.to_owned()) {
+                    continue;
+                }
+                let relative = path.strip_prefix(site).unwrap_or(&path);
+                self.issues.push(RecordIssue::Unrecorded {
+                    path: relative.to_string_lossy().replace('\\', "/"),
+                });
+                self.files.push(path);
+            }
+        }
+    }
+
+    /// The top-level packages from `top_level.txt`, or from the directories in `RECORD`
+    /// when it is missing.
+    fn top_level(dist_info: &Path, recorded: &HashSet<PathBuf>, site: &Path) -> Vec<String> {
+        let mut names: Vec<String> = match std::fs::read_to_string(dist_info.join("top_level.txt"))
+        {
+            Ok(content) => content
+                .lines()
+                .map(|line| line.trim().to_owned())
+                .filter(|line| !line.is_empty())
+                .collect(),
+            Err(_) => recorded
+                .iter()
+                .filter_map(|path| path.strip_prefix(site).ok())
+                .filter(|path| path.components().count() > 1)
+                .filter_map(|path| path.components().next())
+                .map(|dir| dir.as_os_str().to_string_lossy().to_string())
+                .filter(|dir| !dir.ends_with(".dist-info") && dir != "..")
+                .collect(),
+        };
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Every distribution with a `.dist-info` directory in the site-packages directory.
+    pub fn find_all(site: &Path) -> Result<Vec<Distribution>> {
+        let mut dist_infos: Vec<PathBuf> = std::fs::read_dir(site)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| {
+                path.is_dir()
+                    && path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().ends_with(".dist-info"))
+            })
+            .collect();
+        dist_infos.sort();
+
+        let mut distributions: Vec<Distribution> = dist_infos
+            .iter()
+            .filter_map(|dist_info| match Distribution::load(dist_info) {
+                Ok(distribution) => Some(distribution),
+                Err(err) => {
+                    warn!("Skipping '{}': {}", dist_info.display(), err);
+                    None
+                }
+            })
+            .collect();
+
+        // files dropped into the packages of a distribution after it was installed
+        let recorded: HashSet<PathBuf> = distributions
+            .iter()
+            .flat_map(|distribution| distribution.recorded.iter().cloned())
+            .collect();
+        let mut reported: HashSet<PathBuf> = HashSet::new();
+        for distribution in distributions.iter_mut() {
+            distribution.add_unrecorded(site, &recorded, &mut reported);
+        }
+        Ok(distributions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{record_hash, split_csv};
+
+    #[test]
+    fn test_record_lines() {
+        assert_eq!(
+            split_csv("\"pkg/a,b.py\",sha256=abc,12"),
+            vec!["pkg/a,b.py", "sha256=abc", "12"]
+        );
+        assert_eq!(
+            split_csv("pkg-1.0.dist-info/RECORD,,"),
+            vec!["pkg-1.0.dist-info/RECORD", "", ""]
+        );
+
+        assert_eq!(
+            record_hash("sha256", b"").unwrap(),
+            "sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU"
+        );
+        assert_eq!(record_hash("md5", b""), None);
+    }
+}
@@ -1,34 +1,63 @@
 use crate::{
-    evaluator::{AnalysisResult, Evaluator, RuleManager, SourceAnalysis},
-    source::SourceFile,
+    evaluator::{
+        AnalysisError, AnalysisResult, Canaries, Evaluator, RuleManager, SourceAnalysis, Timings,
+    },
+    source::{is_native, parse_entry_points, AutoExec, SourceFile},
     utils::{self},
     visitors::VariableType,
-    Config, Result,
+    Config, Result, ScoutError,
 };
 use colored::Colorize;
 
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
-    str::FromStr,
+    time::Instant,
 };
 use walkdir::WalkDir;
 
 use rayon::prelude::*;
 use tokio::runtime::Runtime;
 
+mod diff;
+mod installed;
+mod requirement;
+mod stream;
+mod tfidf;
+mod typosquat;
+mod wheelhouse;
+
+pub use diff::{diff_root, BulletinChange, DiffResult, EntryPointChange, FileDiff};
+pub use installed::{
+    site_packages_dir, Distribution, DistributionResult, InstalledResult, RecordIssue,
+};
+use requirement::index_option;
+pub use stream::{JsonLines, Listener, PackageSummary};
+use tfidf::DocumentFrequencies;
+pub use typosquat::{NameFinding, PopularPackages};
+pub use wheelhouse::{
+    read_requirements, RequirementResult, RequirementsResult, ScanOutcome, Unscanned, Wheelhouse,
+};
+
 pub struct Package<'cfg> {
     pub path: PathBuf,
     checker: Evaluator,
     threshold: f64,
     show_all_override: bool,
+    explain: bool,
+    /// Only these files are analysed when set, instead of everything under `path`
+    files: Option<Vec<PathBuf>>,
     config: &'cfg Config,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Metadata {
     pub name: String,
     pub deps: Vec<String>,
+    /// The dependencies as declared, with versions and URLs, e.g. `foo @ https://...`
+    pub requirements: Vec<String>,
+    /// Indexes and download locations dependencies are fetched from
+    pub indexes: Vec<String>,
 }
 
 impl Metadata {
@@ -41,19 +70,34 @@ impl<'cfg> Package<'cfg> {
     pub fn new(
         path: PathBuf,
         rules: RuleManager,
+        canaries: Canaries,
         threshold: f64,
         show_all_override: bool,
         config: &'cfg Config,
     ) -> Result<Self> {
         Ok(Self {
             path: path.to_owned(),
-            checker: Evaluator::new(rules.get_rule_sets())?,
+            checker: Evaluator::new(rules.get_rule_sets(), canaries)?,
             threshold,
             show_all_override,
+            explain: false,
+            files: None,
             config,
         })
     }
 
+    /// Keeps files without any visible bulletins in the results, so they can be explained.
+    pub fn set_explain(mut self, explain: bool) -> Self {
+        self.explain = explain;
+        self
+    }
+
+    /// Analyses only the given files, e.g. those of one distribution in site-packages.
+    pub fn set_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.files = Some(files);
+        self
+    }
+
     // fn add_sourcefile(&self, path: &PathBuf, target: &mut Vec<SourceFile>) -> Result<()> {
     //     let source = block_on(tokio::fs::read_to_string(path))?;
     //     match SourceFile::load(path, source) {
@@ -66,74 +110,154 @@ impl<'cfg> Package<'cfg> {
     // }
 
     pub fn analyse(self) -> Result<AnalysisResult> {
-        let analyses: Vec<SourceAnalysis> = self.get_source_analyses()?;
+        let start = Instant::now();
+        let (analyses, errors) = self.get_source_analyses()?;
+        let loaded = Instant::now();
         let results = self.run_evaluation(analyses)?;
+        let timings = Package::timings(start, loaded);
 
-        // let metadata = match self.get_metadata(&self.path) {
-        //     Ok(metadata) => Some(metadata),
-        //     Err(err) => {
-        //         error!(
-        //             "Error getting metadate for package '{}' in package '{}'",
-        //             err.to_string(),
-        //             &self.path.as_path().to_str().unwrap()
-        //         );
-        //         None
-        //     }
-        // };
-        let metadata = None;
+        let metadata = self.read_metadata();
+        let findings = self.check_names(metadata.as_ref())?;
+
+        Ok(AnalysisResult::new(results, metadata)
+            .set_name_findings(findings)
+            .set_errors(errors)
+            .set_timings(timings))
+    }
+
+    fn read_metadata(&self) -> Option<Metadata> {
+        let metadata = match &self.files {
+            Some(files) => match files.iter().find(|f| f.ends_with("METADATA")) {
+                Some(path) => Package::parse_metadata_file(path),
+                None => Ok(Metadata::default()),
+            },
+            None => self.get_metadata(&self.path),
+        };
+        match metadata {
+            Ok(metadata) => Some(metadata),
+            Err(err) => {
+                error!(
+                    "Error getting metadata for package '{}': {}",
+                    self.path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
 
-        Ok(AnalysisResult::new(results, metadata))
+    /// Checks the package name and dependencies for typosquatting and dependency confusion.
+    fn check_names(&self, metadata: Option<&Metadata>) -> Result<Vec<NameFinding>> {
+        Ok(match metadata {
+            Some(metadata) => {
+                PopularPackages::new(&self.config.popular_packages_path)?.check(metadata)
+            }
+            None => vec![],
+        })
     }
 
     pub fn analyse_single(&mut self) -> Result<AnalysisResult> {
-        trace!(
-            "Analysing single file: '{}'",
-            &self.path.as_path().as_os_str().to_str().unwrap()
-        );
+        trace!("Analysing single file: '{}'", self.path.display());
 
-        let source = std::fs::read_to_string(&self.path)?;
-        let source = self.get_sourcefile(&self.path, source)?;
+        let start = Instant::now();
+        let sources = if Package::is_binary(&self.path) {
+            let data =
+                std::fs::read(&self.path).map_err(|err| ScoutError::from(err).at(&self.path))?;
+            vec![self.get_binary(&self.path, &data)?]
+        } else {
+            let source = utils::load_from_file(&self.path)?;
+            self.get_sourcefiles(&self.path, source)?
+        };
 
-        let analyses: Vec<SourceAnalysis> = self.run_precalc(vec![source])?;
+        let analyses: Vec<SourceAnalysis> = self.run_precalc(sources)?;
+        let loaded = Instant::now();
         let results = self.run_evaluation(analyses)?;
 
-        Ok(AnalysisResult::new(results, None))
+        Ok(AnalysisResult::new(results, None).set_timings(Package::timings(start, loaded)))
+    }
+
+    /// How long loading took, from `start` to `loaded`, and evaluating after that.
+    fn timings(start: Instant, loaded: Instant) -> Timings {
+        let ms = |from: Instant, to: Instant| to.duration_since(from).as_secs_f64() * 1000.0;
+        let end = Instant::now();
+        Timings {
+            load_ms: ms(start, loaded),
+            evaluate_ms: ms(loaded, end),
+            total_ms: ms(start, end),
+        }
     }
 
     fn get_from_cache(&self) -> Result<Vec<SourceAnalysis>> {
-        let json = std::fs::read_to_string("cache.json")?;
-        let obj: Vec<SourceAnalysis> = serde_json::from_str(json.as_str())?;
+        let cache_error = |source| ScoutError::Cache { source };
+        let json = std::fs::read_to_string("cache.json").map_err(|err| cache_error(err.into()))?;
+        let obj: Vec<SourceAnalysis> =
+            serde_json::from_str(json.as_str()).map_err(|err| cache_error(err.into()))?;
         Ok(obj)
     }
 
     fn save_to_cache(&self, analyses: &Vec<SourceAnalysis>) -> Result<()> {
-        let json = serde_json::to_string(analyses)?;
-        std::fs::write("cache.json", json)?;
+        let cache_error = |source| ScoutError::Cache { source };
+        let json = serde_json::to_string(analyses).map_err(|err| cache_error(err.into()))?;
+        std::fs::write("cache.json", json).map_err(|err| cache_error(err.into()))?;
         Ok(())
     }
 
-    fn get_source_analyses(&self) -> Result<Vec<SourceAnalysis>> {
+    fn get_source_analyses(&self) -> Result<(Vec<SourceAnalysis>, Vec<AnalysisError>)> {
         if self.config.use_cache {
-            return self.get_from_cache();
+            return Ok((self.get_from_cache()?, vec![]));
         }
 
-        let sources = self.load_sources()?;
+        let (sources, errors) = self.load_sources()?;
         let analyses = self.run_precalc(sources)?;
 
         if self.config.save_cache {
             self.save_to_cache(&analyses)?;
         }
 
-        Ok(analyses)
+        Ok((analyses, errors))
     }
 
-    fn get_sourcefile(&self, path: &PathBuf, source: String) -> Result<SourceFile> {
-        match SourceFile::load(path, source) {
-            Ok(source) => Ok(source),
-            Err(err) => Err(format!("Could not add source: {}", err.to_string()).into()),
+    fn get_sourcefile(&self, path: &Path, source: String) -> Result<SourceFile> {
+        SourceFile::load(path, source).map_err(|err| err.at(path))
+    }
+
+    /// Like `get_sourcefile`, but a Jupyter notebook gives one source per code cell, and
+    /// code objects passed to `marshal.loads` are added as sources of their own.
+    fn get_sourcefiles(&self, path: &Path, source: String) -> Result<Vec<SourceFile>> {
+        if path.extension().is_some_and(|ext| ext == "ipynb") {
+            SourceFile::load_notebook(path, &source).map_err(|err| err.at(path))
+        } else {
+            let source = self.get_sourcefile(path, source)?;
+            let mut sources = source.load_marshal_blobs();
+            sources.insert(0, source);
+            Ok(sources)
         }
     }
 
+    /// Loads a `.pyc` file or a native extension module.
+    fn get_binary(&self, path: &Path, data: &[u8]) -> Result<SourceFile> {
+        let source = if is_native(path) {
+            SourceFile::load_native(path, data)
+        } else {
+            SourceFile::load_compiled(path, data)
+        };
+        source.map_err(|err| err.at(path))
+    }
+
+    /// Files that are expected to hold code, failing to load one of them is an error.
+    /// Any other file is tried as Python source and skipped when it doesn't parse.
+    fn is_code(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| {
+            ["py", "pyw", "pth", "ipynb"]
+                .iter()
+                .any(|code| ext == *code)
+        }) || Package::is_binary(path)
+    }
+
+    fn is_binary(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "pyc") || is_native(path)
+    }
+
     async fn get_file(&self, path: &Path) -> Result<String> {
         Ok(tokio::fs::read_to_string(path).await?)
     }
@@ -143,168 +267,172 @@ impl<'cfg> Package<'cfg> {
     //     results
     // }
 
-    fn load_sources(&self) -> Result<Vec<SourceFile>> {
+    /// The files to analyse, in a stable order.
+    fn list_entries(&self) -> Vec<PathBuf> {
+        match &self.files {
+            Some(files) => files.to_owned(),
+            None => WalkDir::new(&self.path)
+                .follow_links(false)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .map(|e| e.into_path())
+                .collect(),
+        }
+    }
+
+    fn load_sources(&self) -> Result<(Vec<SourceFile>, Vec<AnalysisError>)> {
         trace!("Ackquiring sources...");
 
-        let entries = WalkDir::new(&self.path)
-            .follow_links(false)
-            .into_iter()
-            .filter(|e| e.is_ok())
-            .map(|e| e.unwrap());
+        let entries: Vec<PathBuf> = self.list_entries();
         // .collect::<Vec<walkdir::DirEntry>>();
 
         let results = async {
             let mut sources: Vec<(PathBuf, String)> = vec![];
+            let mut binaries: Vec<(PathBuf, Vec<u8>)> = vec![];
+            let mut errors: Vec<AnalysisError> = vec![];
             for entry in entries {
-                if let Ok(source) = self.get_file(entry.path()).await {
-                    sources.push((entry.path().to_owned(), source));
+                let read: Result<()> = if Package::is_binary(&entry) {
+                    tokio::fs::read(&entry)
+                        .await
+                        .map(|data| binaries.push((entry.to_owned(), data)))
+                        .map_err(|err| err.into())
+                } else {
+                    self.get_file(&entry)
+                        .await
+                        .map(|source| sources.push((entry.to_owned(), source)))
+                };
+                if let Err(err) = read {
+                    if entry.is_file() && Package::is_code(&entry) {
+                        errors.push(AnalysisError::new(&entry, err));
+                    }
                 }
             }
-            sources
+            (sources, binaries, errors)
         };
 
         let rt = Runtime::new()?;
-        let results = rt.block_on(results);
+        let (results, binaries, mut errors) = rt.block_on(results);
+
+        let entry_points = Package::collect_entry_points(&results);
 
-        let source: Vec<SourceFile> = results
+        let loaded: Vec<(PathBuf, Result<Vec<SourceFile>>)> = results
             .into_par_iter()
-            .filter_map(|(e, source)| self.get_sourcefile(&e, source).ok())
+            .map(|(e, source)| {
+                let sources = self.get_sourcefiles(&e, source);
+                (e, sources)
+            })
+            .chain(binaries.into_par_iter().map(|(e, data)| {
+                let source = self.get_binary(&e, &data).map(|source| vec![source]);
+                (e, source)
+            }))
             .collect();
 
+        let mut sources: Vec<SourceFile> = vec![];
+        for (path, loaded) in loaded {
+            match loaded {
+                Ok(loaded) => sources.extend(loaded.into_iter().map(|mut source| {
+                    if let Some(names) = entry_points.get(&source.source_path) {
+                        source.set_auto_exec(AutoExec::EntryPoint(names.to_owned()));
+                    }
+                    source
+                })),
+                Err(err) if Package::is_code(&path) => errors.push(AnalysisError::new(&path, err)),
+                Err(_) => (),
+            }
+        }
+
         // let val = Arc::try_unwrap(target).unwrap().into_inner().unwrap();
-        Ok(source)
+        Ok((sources, errors))
     }
 
-    fn run_evaluation(&self, mut analyses: Vec<SourceAnalysis>) -> Result<Vec<SourceAnalysis>> {
-        for analysis in analyses.iter_mut() {
-            self.checker.evaluate(analysis, self.config);
-
-            if let Some(report) = self.create_evaluation_report(&analysis) {
-                analysis.message = Some(report);
+    /// Maps the files targeted by `entry_points.txt` to the functions that are called,
+    /// modules are looked up relative to the directory of the `.dist-info` or `.egg-info`.
+    fn collect_entry_points(files: &[(PathBuf, String)]) -> HashMap<PathBuf, Vec<String>> {
+        let mut targets: HashMap<PathBuf, Vec<String>> = HashMap::new();
+
+        for (path, content) in files {
+            if path
+                .file_name()
+                .is_none_or(|name| name != "entry_points.txt")
+            {
+                continue;
             }
-        }
+            let root = match path.parent().and_then(|info| info.parent()) {
+                Some(root) => root,
+                None => continue,
+            };
 
-        analyses = analyses
-            .into_iter()
-            .filter_map(|a| {
-                if a.any_bulletins_over_threshold() {
-                    Some(a)
-                } else {
-                    None
+            for entry_point in parse_entry_points(content) {
+                for module_path in entry_point.module_paths(root) {
+                    let names = targets.entry(module_path).or_default();
+                    if let Some(object) = &entry_point.object {
+                        if !names.contains(object) {
+                            names.push(object.to_owned());
+                        }
+                    }
                 }
-            })
-            .collect();
+            }
+        }
 
-        Ok(analyses)
+        targets
     }
 
-    fn run_precalc(&self, sources: Vec<SourceFile>) -> Result<Vec<SourceAnalysis>> {
-        let mut analyses: Vec<SourceAnalysis> = sources
-            .into_iter()
-            .map(|source| {
-                SourceAnalysis::new(source, self.show_all_override, self.threshold, self.config)
-            })
-            .collect();
-
-        if self.config.feature_tfidf_imports {
-            self.calculate_import_tfidf(&mut analyses);
+    fn run_evaluation(&self, mut analyses: Vec<SourceAnalysis>) -> Result<Vec<SourceAnalysis>> {
+        for analysis in analyses.iter_mut() {
+            self.evaluate(analysis);
         }
 
-        if self.config.feature_tfidf_calls {
-            self.calculate_call_tfidf(&mut analyses);
-        }
+        analyses.retain(|a| self.is_kept(a));
 
         Ok(analyses)
     }
 
-    fn calculate_import_tfidf(&self, results: &mut Vec<SourceAnalysis>) {
-        let mut lookup: HashMap<String, HashMap<String, bool>> = HashMap::new();
-        for result in results.iter() {
-            let mut im_lookup: HashMap<String, bool> = HashMap::new();
-            for (im, count) in result.source.get_import_counts() {
-                let exists = match count {
-                    0 => false,
-                    _ => true,
-                };
-                im_lookup.insert(im.to_string(), exists);
-            }
-            lookup.insert(result.source.get_path().to_string(), im_lookup);
-        }
-
-        let count_sources = results.len() as f64;
-        debug!("count_sources: {}", count_sources);
-
-        for result in results.iter_mut() {
-            let term_freq: HashMap<String, f64> = result.source.import_term_frequency_table();
-            debug!("TFIDF table for result: {:?}", term_freq);
+    fn evaluate(&self, analysis: &mut SourceAnalysis) {
+        self.checker.evaluate(analysis, self.config);
 
-            for (im, freq) in term_freq {
-                let sources_with_im = lookup
-                    .iter()
-                    .filter(|&(_, im_lookup)| im_lookup.contains_key(&im))
-                    .count() as f64;
-                let tf = self.calc_idf(count_sources, sources_with_im);
-                let tfidf: f64 = freq * tf;
-
-                debug!(
-                    "sources with import {}: {} -> tf-idf {}",
-                    &im, sources_with_im, &tfidf
-                );
-
-                result.source.set_import_tfidf(im.as_str(), tfidf);
-            }
+        if let Some(report) = self.create_evaluation_report(analysis) {
+            analysis.message = Some(report);
         }
     }
 
-    // fn calc_df(&self, num_cases: f64, cases_with_term: f64) -> f64 {
-    //     cases_with_term / num_cases
-    // }
-
-    fn calc_idf(&self, num_cases: f64, cases_with_term: f64) -> f64 {
-        (num_cases / cases_with_term).ln()
+    /// Whether the analysis is kept in the results after it was evaluated.
+    fn is_kept(&self, analysis: &SourceAnalysis) -> bool {
+        self.explain || analysis.any_bulletins_over_threshold()
     }
 
-    // fn calc_idf_smooth(&self, num_cases: f64, cases_with_term: f64) -> f64 {
-    //     (num_cases / (1.0f64 + cases_with_term)).ln() + 1.0f64
-    // }
+    fn run_precalc(&self, sources: Vec<SourceFile>) -> Result<Vec<SourceAnalysis>> {
+        let mut analyses: Vec<SourceAnalysis> = sources
+            .into_iter()
+            .map(|source| {
+                SourceAnalysis::new(source, self.show_all_override, self.threshold, self.config)
+            })
+            .collect();
 
-    fn calculate_call_tfidf(&self, results: &mut Vec<SourceAnalysis>) {
-        let mut lookup: HashMap<String, HashMap<String, bool>> = HashMap::new();
-        for result in results.iter() {
-            let mut call_lookup: HashMap<String, bool> = HashMap::new();
-            for (call, count) in result.source.get_call_counts() {
-                let exists = match count {
-                    0 => false,
-                    _ => true,
-                };
-                call_lookup.insert(call.to_string(), exists);
+        if self.uses_tfidf() {
+            let mut frequencies = DocumentFrequencies::default();
+            for analysis in analyses.iter() {
+                frequencies.add(&analysis.source);
+            }
+            debug!("count_sources: {}", analyses.len());
+            for analysis in analyses.iter_mut() {
+                self.set_tfidf(&frequencies, analysis);
             }
-            lookup.insert(result.source.get_path().to_string(), call_lookup);
         }
 
-        let count_sources = results.len() as f64;
-        debug!("count_sources: {}", count_sources);
-
-        for result in results.iter_mut() {
-            let term_freq: HashMap<String, f64> = result.source.calc_term_frequency_table();
-            debug!("TFIDF table for result: {:?}", term_freq);
+        Ok(analyses)
+    }
 
-            for (call, freq) in term_freq {
-                let sources_with_call = lookup
-                    .iter()
-                    .filter(|&(_, call_lookup)| call_lookup.contains_key(&call))
-                    .count() as f64;
-                // let idf: f64 = self.calc_idf(count_sources, sources_with_call);
-                let idf: f64 = self.calc_idf(count_sources, sources_with_call);
-                let tfidf: f64 = freq * idf;
-
-                debug!(
-                    "sources with import {}: {} -> tf-idf {}",
-                    &call, sources_with_call, &tfidf
-                );
+    fn uses_tfidf(&self) -> bool {
+        self.config.feature_tfidf_imports || self.config.feature_tfidf_calls
+    }
 
-                result.source.set_call_tfidf(call.as_str(), tfidf);
-            }
+    fn set_tfidf(&self, frequencies: &DocumentFrequencies, analysis: &mut SourceAnalysis) {
+        if self.config.feature_tfidf_imports {
+            frequencies.set_import_tfidf(&mut analysis.source);
+        }
+        if self.config.feature_tfidf_calls {
+            frequencies.set_call_tfidf(&mut analysis.source);
         }
     }
 
@@ -344,9 +472,6 @@ impl<'cfg> Package<'cfg> {
         message += format!("Location: {}\n", eval_result.source.get_path()).as_str();
         let mut first = true;
         for (group, hotspot) in eval_result.bulletins_by_hotspot() {
-            if first {
-                first = false;
-            }
             debug!("Current hotspot: {:?}", hotspot);
             let f = eval_result.get_uniq_functionality(&group);
             debug!("Functionality for group: {:?}", f);
@@ -402,7 +527,19 @@ impl<'cfg> Package<'cfg> {
 
             if display {
                 if !first {
-                    message += "...\n";
+                    message += "\n...\n";
+                }
+                first = false;
+                if let Some(definition) = &hotspot.definition {
+                    let header = format!(
+                        "In {} '{}' (lines {}-{}, score {:.2}):",
+                        definition.kind.as_str(),
+                        definition.name,
+                        definition.start,
+                        definition.end,
+                        hotspot.peak()
+                    );
+                    output.insert(0, header.bold().to_string());
                 }
                 message += format!("{}", output.join("\n")).as_str();
             }
@@ -411,65 +548,113 @@ impl<'cfg> Package<'cfg> {
     }
 
     pub fn get_metadata(&self, path: &PathBuf) -> Result<Metadata> {
-        let metadata_files =
-            utils::collect_files_matching(path, vec!["METADATA", "PKG-INFO", "setup.py"]);
+        let metadata_files = utils::collect_files_matching(
+            path,
+            vec!["METADATA", "PKG-INFO", "setup.py", "requirements.txt"],
+        );
 
-        if let Some(path) = metadata_files.get("METADATA") {
-            return Ok(Package::parse_metadata_file(path)?);
+        let mut metadata = if let Some(path) = metadata_files.get("METADATA") {
+            Package::parse_metadata_file(path)?
         } else {
-            let mut metadata = Metadata {
-                name: String::from(""),
-                deps: vec![],
-            };
+            let mut metadata = Metadata::default();
 
             if let Some(pkg_info_path) = metadata_files.get("PKG-INFO") {
                 metadata.name = Package::parse_name_from_pkg(pkg_info_path)?;
             }
             if let Some(setup_path) = metadata_files.get("setup.py") {
-                metadata.deps = self.parse_deps_from_setup(setup_path)?;
+                self.parse_setup(setup_path, &mut metadata)?;
             }
 
-            return Ok(metadata);
+            metadata
+        };
+
+        if let Some(requirements_path) = metadata_files.get("requirements.txt") {
+            Package::parse_requirements_file(requirements_path, &mut metadata)?;
         }
+
+        Ok(metadata)
     }
 
-    fn parse_deps_from_setup(&self, path: &PathBuf) -> Result<Vec<String>> {
-        let mut deps: Vec<String> = vec![];
+    fn string_items(word: &Option<VariableType>) -> Vec<String> {
+        match word {
+            Some(VariableType::List(items)) | Some(VariableType::Tuple(items)) => items
+                .iter()
+                .flatten()
+                .filter_map(|var| var.get_string())
+                .map(|str| str.to_string())
+                .collect(),
+            _ => vec![],
+        }
+    }
 
-        // let mut sources: Vec<SourceFile> = vec![];
-        // self.add_sourcefile(path, &mut sources)?;
-        // let source = sources.pop().unwrap();
+    /// Reads the name, `install_requires` and `dependency_links` passed to `setup()`.
+    fn parse_setup(&self, path: &PathBuf, metadata: &mut Metadata) -> Result<()> {
         let source = std::fs::read_to_string(path)?;
         let source = self.get_sourcefile(path, source)?;
 
         let entries = source.get_entries();
         for entry in entries {
-            if entry.get_identifier() == "setup" {
-                for (key, word) in &entry.keywords {
-                    if let Some(key) = key.as_ref() {
-                        if key == &String::from("install_requires") {
-                            if let Some(list) = word {
-                                match list {
-                                    VariableType::List(items) => {
-                                        for item in items.iter() {
-                                            if let Some(var) = item {
-                                                if var.is_string() {
-                                                    let str = var.get_string().unwrap();
-                                                    deps.push(str.to_string());
-                                                }
-                                            }
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
+            if entry.get_identifier() != "setup" {
+                continue;
+            }
+            for (key, word) in &entry.keywords {
+                match key.as_deref() {
+                    Some("name") if metadata.name.is_empty() => {
+                        if let Some(name) = word.as_ref().and_then(|word| word.get_string()) {
+                            metadata.name = name.to_string();
                         }
                     }
+                    Some("install_requires") => {
+                        let deps = Package::string_items(word);
+                        metadata.requirements.extend(deps.iter().cloned());
+                        metadata.deps.extend(deps);
+                    }
+                    Some("dependency_links") => {
+                        metadata.indexes.extend(Package::string_items(word));
+                    }
+                    _ => {}
                 }
             }
         }
 
-        Ok(deps)
+        Ok(())
+    }
+
+    /// Reads the requirements and index options in a requirements file. Lines continued
+    /// with `\` are joined and options of a requirement, e.g. `--hash`, are dropped, as
+    /// in files written by `pip-compile`.
+    fn parse_requirements_file(path: &PathBuf, metadata: &mut Metadata) -> Result<()> {
+        let mut lines: Vec<String> = vec![];
+        let mut continued = false;
+        for line in utils::read_lines(path)?.map_while(|line| line.ok()) {
+            let joined = line.trim_end().strip_suffix('\\');
+            let line = joined.unwrap_or(&line).to_owned();
+            match lines.last_mut() {
+                Some(last) if continued => last.push_str(&line),
+                _ => lines.push(line),
+            }
+            continued = joined.is_some();
+        }
+
+        for line in lines.iter() {
+            let line = line.trim();
+            let line = match line.find(" --") {
+                Some(idx) if !line.starts_with('-') => line[..idx].trim_end(),
+                _ => line,
+            };
+            if let Some(url) = index_option(line) {
+                metadata.indexes.push(url);
+            } else if let Some(editable) = line
+                .strip_prefix("-e ")
+                .or_else(|| line.strip_prefix("--editable "))
+            {
+                metadata.requirements.push(editable.trim().to_owned());
+            } else if !line.is_empty() && !line.starts_with('#') && !line.starts_with('-') {
+                metadata.requirements.push(line.to_owned());
+            }
+        }
+
+        Ok(())
     }
 
     fn parse_name_from_pkg(path: &PathBuf) -> Result<String> {
@@ -491,6 +676,7 @@ impl<'cfg> Package<'cfg> {
     fn parse_metadata_file(path: &PathBuf) -> Result<Metadata> {
         let mut name: String = String::from("");
         let mut deps: Vec<String> = vec![];
+        let mut requirements: Vec<String> = vec![];
 
         for line in utils::read_lines(path)? {
             if let Ok(line) = line {
@@ -503,12 +689,18 @@ impl<'cfg> Package<'cfg> {
                         if let Some(dep) = right.split_ascii_whitespace().into_iter().next() {
                             deps.push(dep.to_string());
                         }
+                        requirements.push(right.trim().to_string());
                     }
                 }
             }
         }
 
-        Ok(Metadata { name, deps })
+        Ok(Metadata {
+            name,
+            deps,
+            requirements,
+            indexes: vec![],
+        })
     }
 
     fn get_package_dir(path: &Path) -> Option<PathBuf> {
@@ -524,7 +716,7 @@ impl<'cfg> Package<'cfg> {
     }
 
     pub fn locate_package(path: &str) -> Option<PathBuf> {
-        let p = PathBuf::from_str(path).unwrap();
+        let p = PathBuf::from(path);
         debug!("Locating package: {:?}", &p);
 
         let pkg_path = Package::get_package_dir(&p)?;
@@ -536,7 +728,39 @@ impl<'cfg> Package<'cfg> {
 mod tests {
     use std::{path::PathBuf, str::FromStr};
 
-    use crate::{Metadata, Package};
+    use crate::evaluator::{Bulletin, Functionality};
+    use crate::{
+        BulletinChange, Engine, EntryPointChange, Metadata, Package, RecordIssue, ScanOutcome,
+        Unscanned,
+    };
+
+    /// Analyses a file or package with every bulletin shown, and maps each bulletin along
+    /// with the file name of its source, e.g. `test-notebook.ipynb#cell-2`.
+    fn collect<T>(path: &str, map: impl Fn(&str, &Bulletin) -> T) -> Vec<T> {
+        let engine = Engine::new().set_show_all(true);
+        let results = if PathBuf::from(path).is_file() {
+            engine.analyse_file(path)
+        } else {
+            engine.analyse_package(path)
+        }
+        .unwrap();
+
+        let mut found = vec![];
+        for result in results.get_results() {
+            let name = result.source.get_path().rsplit('/').next().unwrap();
+            for bulletin in result.get_all_bulletins() {
+                found.push(map(name, bulletin));
+            }
+        }
+        found
+    }
+
+    /// Whether a bulletin in the file has a reason that contains the text.
+    fn reported(found: &[(String, String)], file: &str, text: &str) -> bool {
+        found
+            .iter()
+            .any(|(name, reason)| name == file && reason.contains(text))
+    }
 
     #[test]
     fn test_parse_metadata_file() {
@@ -551,4 +775,424 @@ mod tests {
             .contains(&String::from("google-cloud-bigquery")));
         assert!(metadata.deps.contains(&String::from("pytest")));
     }
+
+    #[test]
+    fn test_analysis_is_deterministic() {
+        let path = "../examples/files/test-obfuscated-example.py";
+
+        let analyse = || {
+            let results = Engine::new().set_show_all(true).analyse_file(path).unwrap();
+            let hotspots = results
+                .get_results()
+                .iter()
+                .map(|r| format!("{:?}", r.get_hotspots()))
+                .collect::<Vec<String>>();
+            (hotspots, results.to_json_with_fields())
+        };
+
+        let (first_hotspots, first_json) = analyse();
+        assert!(!first_hotspots.is_empty());
+
+        for _ in 0..25 {
+            let (hotspots, json) = analyse();
+            assert_eq!(hotspots, first_hotspots);
+            assert_eq!(json, first_json);
+        }
+    }
+
+    #[test]
+    fn test_explain_matches_visible_bulletins() {
+        let results = Engine::new()
+            .set_explain(true)
+            .analyse_file("../examples/files/test-obfuscated-example.py")
+            .unwrap();
+
+        for result in results.get_results() {
+            let visible = result.get_visible_bulletins().len();
+            let explanations = result.explain();
+
+            assert_eq!(explanations.len(), result.get_all_bulletins().len());
+            assert_eq!(explanations.iter().filter(|e| e.shown).count(), visible);
+        }
+    }
+
+    #[test]
+    fn test_credential_access_then_send() {
+        let credential_lines: Vec<(usize, String)> =
+            collect("../examples/files/test-credential-stealer.py", |_, b| {
+                (b.line(), b.reason(), b.functionality)
+            })
+            .into_iter()
+            .filter(|(_, _, f)| *f == Some(Functionality::CredentialAccess))
+            .map(|(line, reason, _)| (line, reason))
+            .collect();
+
+        // the path join, the folded chrome path and the environment dump
+        assert!(credential_lines.iter().any(|(line, _)| *line == 10));
+        assert!(credential_lines.iter().any(|(line, _)| *line == 14));
+        assert!(credential_lines.iter().any(|(line, _)| *line == 18));
+        assert!(credential_lines
+            .iter()
+            .any(|(line, reason)| *line == 24 && reason.contains("accessed at line 10")));
+    }
+
+    #[test]
+    fn test_persistence_writes_and_calls() {
+        let mut lines: Vec<usize> = collect("../examples/files/test-persistence.py", |_, b| {
+            (b.line(), b.functionality)
+        })
+        .into_iter()
+        .filter(|(_, f)| *f == Some(Functionality::Persistence))
+        .map(|(line, _)| line)
+        .collect();
+        lines.dedup();
+
+        // the rc file, the .pth drop, the startup folder, crontab and the run key, but not
+        // the write to a plain file or to a model checkpoint ending in .pth, and not
+        // `crontab -l` on its own
+        assert_eq!(lines, vec![9, 12, 15, 17, 19, 30]);
+    }
+
+    #[test]
+    fn test_notebook_cells_and_shell_commands() {
+        let found = collect("../examples/files/test-notebook.ipynb", |name, b| {
+            (name.to_owned(), b.line(), b.functionality)
+        });
+        let shell: Vec<(&str, usize)> = found
+            .iter()
+            .filter(|(_, _, f)| *f == Some(Functionality::Process))
+            .map(|(path, line, _)| (path.rsplit('#').next().unwrap(), *line))
+            .collect();
+
+        // line numbers are relative to the cell, and the markdown cell still counts
+        assert!(shell.contains(&("cell-2", 3)));
+        assert!(shell.contains(&("cell-2", 4)));
+        assert!(shell.contains(&("cell-3", 4)));
+        assert!(shell.contains(&("cell-4", 2)));
+        assert!(shell.contains(&("cell-4", 3)));
+        assert!(found.iter().any(|(path, line, f)| path.ends_with("#cell-3")
+            && *line == 4
+            && *f == Some(Functionality::Network)));
+    }
+
+    #[test]
+    fn test_compiled_code_is_inspected() {
+        let identifiers =
+            |path: &str| collect(path, |name, b| (name.to_owned(), b.identifier.to_owned()));
+
+        let pyc = identifiers("../examples/files/test-compiled.cpython-311.pyc");
+        assert!(pyc.iter().any(|(_, i)| i == "os.system"));
+        assert!(pyc.iter().any(|(_, i)| i == "subprocess.Popen"));
+
+        let blob = identifiers("../examples/files/test-marshal-blob.py");
+        assert!(blob.contains(&(
+            String::from("test-marshal-blob.py#marshal-5"),
+            String::from("os.system")
+        )));
+    }
+
+    #[test]
+    fn test_dotted_function_rules_match_the_full_call() {
+        let rules = collect("../examples/files/test-marshal-blob.py", |_, b| {
+            (b.rule_id(), b.line())
+        });
+
+        // `marshal.loads` only matches calls ending in `marshal.loads`, not any `loads`
+        assert!(rules.contains(&(String::from("function:marshal.loads"), 5)));
+        assert!(!rules.contains(&(String::from("function:marshal.load"), 5)));
+    }
+
+    #[test]
+    fn test_native_modules_are_inspected() {
+        let found = collect("../examples/packages/native", |name, b| {
+            (name.to_owned(), b.reason())
+        });
+        let reported = |file: &str, text: &str| reported(&found, file, text);
+
+        let so = "helpers.cpython-311-x86_64-linux-gnu.so";
+        let pyd = "loader.cp311-win_amd64.pyd";
+        assert!(reported(so, "'system' is often used"));
+        assert!(reported(so, "(ELF) contains 'http://45.13.227.10/stage2'"));
+        assert!(reported(so, "is imported instead of"));
+        assert!(reported(pyd, "'VirtualAlloc' is often used"));
+        assert!(reported(
+            pyd,
+            "(PE) contains 'http://45.13.227.10/payload.exe'"
+        ));
+        assert!(!reported(pyd, "is imported instead of"));
+    }
+
+    #[test]
+    fn test_name_findings() {
+        let results = Engine::new()
+            .analyse_package("../examples/packages/typosquat")
+            .unwrap();
+        let findings: Vec<String> = results
+            .get_name_findings()
+            .iter()
+            .map(|finding| finding.to_string())
+            .collect();
+
+        assert_eq!(
+            findings,
+            vec![
+                "Package 'colourama' looks like the popular package 'colorama' (typo)",
+                "Dependency 'reqeusts' looks like the popular package 'requests' (typo)",
+                "Dependency 'pythondateutil' looks like the popular package 'python-dateutil' (separators)",
+                "Dependency 'python-urllib3' looks like the popular package 'urllib3' (added prefix or suffix)",
+                "Dependency 'internal-tools' is installed from 'https://files.example.net/internal_tools-1.0.tar.gz'",
+                "Dependencies are looked up in the index 'https://pypi.example.net/simple'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_reports_new_risk_only() {
+        let diff = Engine::new()
+            .analyse_diff(
+                "../examples/packages/diff/old",
+                "../examples/packages/diff/new",
+            )
+            .unwrap();
+
+        assert_eq!(diff.added_files, vec!["demo_pkg/_update.py"]);
+        assert_eq!(diff.new_dependencies, vec!["pycryptodome>=3.19"]);
+        assert_eq!(
+            diff.entry_points,
+            vec![EntryPointChange::Added {
+                entry_point: String::from("console_scripts: demo-update"),
+                target: String::from("demo_pkg._update:run"),
+            }]
+        );
+
+        // util.py is the same in both versions
+        let files: Vec<(&str, bool)> = diff
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.added))
+            .collect();
+        assert_eq!(
+            files,
+            vec![("demo_pkg/_update.py", true), ("demo_pkg/cli.py", false)]
+        );
+        assert!(diff.files[1]
+            .bulletins
+            .iter()
+            .any(|b| b.identifier == "os.system" && b.change == BulletinChange::New));
+    }
+
+    #[test]
+    fn test_installed_distributions() {
+        let installed = Engine::new()
+            .set_show_all(true)
+            .analyse_installed("../examples/packages/site_packages")
+            .unwrap();
+        let names: Vec<&str> = installed
+            .distributions
+            .iter()
+            .map(|distribution| distribution.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["fastjsonx", "goodlib"]);
+
+        let fastjsonx = &installed.distributions[0];
+        assert!(matches!(
+            &fastjsonx.issues[..],
+            [
+                RecordIssue::Modified { path: modified, .. },
+                RecordIssue::Missing { path: missing },
+                RecordIssue::Unrecorded { path: unrecorded },
+            ] if modified == "fastjsonx/codec.py"
+                && missing == "fastjsonx/schema.json"
+                && unrecorded == "fastjsonx/_hook.py"
+        ));
+        assert!(fastjsonx.result.get_results().iter().any(|result| result
+            .get_all_bulletins()
+            .iter()
+            .any(|bulletin| bulletin.identifier == "os.system")));
+
+        assert!(installed.distributions[1].issues.is_empty());
+    }
+
+    #[test]
+    fn test_installed_distribution_errors_are_recorded() {
+        // reading a cache that doesn't exist fails the analysis of every distribution
+        let mut engine = Engine::new();
+        engine
+            .set_config(String::from("{ \"use_cache\": true }"))
+            .unwrap();
+        let installed = engine
+            .analyse_installed("../examples/packages/site_packages")
+            .unwrap();
+
+        assert_eq!(installed.distributions.len(), 2);
+        for distribution in installed.distributions.iter() {
+            assert!(distribution.result.get_results().is_empty());
+            assert!(distribution
+                .error
+                .as_ref()
+                .is_some_and(|error| error.contains("cache")));
+        }
+        assert!(!installed.distributions[0].issues.is_empty());
+    }
+
+    #[test]
+    fn test_installed_namespace_packages() {
+        let installed = Engine::new()
+            .analyse_installed("../examples/packages/venv")
+            .unwrap();
+        let issues: Vec<(&str, &RecordIssue)> = installed
+            .distributions
+            .iter()
+            .filter(|distribution| distribution.name.starts_with("nsvendor"))
+            .flat_map(|distribution| {
+                let name = distribution.name.as_str();
+                distribution.issues.iter().map(move |issue| (name, issue))
+            })
+            .collect();
+
+        // both record only their own part of `nsvendor`, a file neither records is reported once
+        assert!(
+            matches!(
+                &issues[..],
+                [("nsvendor_auth", RecordIssue::Unrecorded { path })] if path == "nsvendor/_patch.py"
+            ),
+            "{:?}",
+            issues
+        );
+    }
+
+    #[test]
+    fn test_installed_record_paths() {
+        let installed = Engine::new()
+            .analyse_installed("../examples/packages/venv")
+            .unwrap();
+        let distribution = |name: &str| {
+            installed
+                .distributions
+                .iter()
+                .find(|distribution| distribution.name == name)
+                .unwrap()
+        };
+
+        // the script is recorded relative to site-packages, but stays inside the virtualenv
+        let core = distribution("nsvendor_core");
+        assert!(core.issues.is_empty(), "{:?}", core.issues);
+
+        // a tampered RECORD can't make the scan read devices or files outside of it, not
+        // even through a link
+        let evil = distribution("evilrecord");
+        assert_eq!(
+            evil.issues,
+            vec![
+                RecordIssue::OutsideEnvironment {
+                    path: String::from("/dev/zero")
+                },
+                RecordIssue::OutsideEnvironment {
+                    path: String::from("../../../../outside.py")
+                },
+                RecordIssue::NotAFile {
+                    path: String::from("evilrecord")
+                },
+                RecordIssue::OutsideEnvironment {
+                    path: String::from("evilrecord_link/secret.py")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_requirements_against_wheelhouse() {
+        let scanned = Engine::new()
+            .analyse_requirements(
+                "../examples/packages/wheelhouse/requirements.txt",
+                "../examples/packages/wheelhouse/wheels",
+            )
+            .unwrap();
+
+        let requirements: Vec<&str> = scanned
+            .requirements
+            .iter()
+            .map(|requirement| requirement.requirement.as_str())
+            .collect();
+        assert_eq!(
+            requirements,
+            vec![
+                "tidy-json==2.1",
+                "netpipe==0.4.2",
+                "colorama>=0.4",
+                "left-pad==1.3.0",
+                "private-tools @ https://files.example.net/private_tools-1.0.tar.gz",
+            ]
+        );
+
+        // the sdist is reported under the archive, not where it was unpacked to
+        match &scanned.requirements[1].outcome {
+            ScanOutcome::Scanned { result, .. } => {
+                let setup = result
+                    .get_results()
+                    .iter()
+                    .find(|result| result.source.get_path().ends_with("setup.py"))
+                    .unwrap();
+                assert!(setup
+                    .source
+                    .source_path
+                    .starts_with("../examples/packages/wheelhouse/wheels/netpipe-0.4.2.tar.gz"));
+                assert!(setup
+                    .get_all_bulletins()
+                    .iter()
+                    .any(|bulletin| bulletin.identifier == "os.system"));
+            }
+            ScanOutcome::Unscanned(unscanned) => panic!("netpipe was not scanned: {}", unscanned),
+        }
+
+        let unscanned: Vec<&Unscanned> = scanned
+            .unscanned()
+            .into_iter()
+            .map(|(_, unscanned)| unscanned)
+            .collect();
+        assert_eq!(
+            unscanned,
+            vec![
+                &Unscanned::NotPinned,
+                &Unscanned::NoArtifact,
+                &Unscanned::DirectUrl {
+                    url: String::from("https://files.example.net/private_tools-1.0.tar.gz")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_auto_executed_code_is_labelled() {
+        let mut labelled: Vec<(String, usize, bool)> =
+            collect("../examples/packages/auto_exec", |name, b| {
+                (
+                    b.identifier.to_owned(),
+                    name.to_owned(),
+                    b.line(),
+                    b.auto_exec().is_some(),
+                )
+            })
+            .into_iter()
+            .filter(|(identifier, ..)| {
+                identifier.ends_with("system") || identifier.ends_with("Popen")
+            })
+            .map(|(_, name, line, auto_exec)| (name, line, auto_exec))
+            .collect();
+        labelled.sort();
+        labelled.dedup();
+
+        assert_eq!(
+            labelled,
+            vec![
+                (String::from("__init__.py"), 4, true),
+                (String::from("__init__.py"), 8, false),
+                (String::from("cli.py"), 5, true),
+                (String::from("cli.py"), 9, false),
+                (String::from("demo.pth"), 2, true),
+                (String::from("setup.py"), 10, true),
+                (String::from("setup.py"), 13, false),
+            ]
+        );
+    }
 }
@@ -0,0 +1,182 @@
+/// Hosts that serve the default package index.
+const DEFAULT_INDEX_HOSTS: [&str; 3] = ["pypi.org", "pypi.python.org", "files.pythonhosted.org"];
+
+/// Options in requirement files that point pip at another index or download location.
+const INDEX_OPTIONS: [&str; 5] = [
+    "-i",
+    "--index-url",
+    "--extra-index-url",
+    "-f",
+    "--find-links",
+];
+
+/// A declared dependency, e.g. `requests[socks]>=2.0; python_version > "3.6"` or
+/// `internal @ https://example.com/internal-1.0.tar.gz`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    pub name: String,
+    /// The version constraints, without extras or markers
+    pub specifier: String,
+    /// Set when the requirement is installed from a URL instead of an index
+    pub url: Option<String>,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || c == '.'
+}
+
+fn is_url(value: &str) -> bool {
+    let value = value.to_lowercase();
+    [
+        "http://", "https://", "file:", "git+", "hg+", "svn+", "bzr+",
+    ]
+    .iter()
+    .any(|scheme| value.starts_with(scheme))
+}
+
+impl Requirement {
+    /// Parses a PEP 508 requirement or a requirement file line. Returns `None` for
+    /// comments, options and lines without a package name.
+    pub fn parse(line: &str) -> Option<Requirement> {
+        let line = match line.find(" #") {
+            Some(idx) => &line[..idx],
+            None => line,
+        };
+        let line = line.split(';').next().unwrap_or_default().trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('-') {
+            return None;
+        }
+
+        if is_url(line) {
+            // `git+https://host/repo.git#egg=name`, or an archive named after the package
+            let name = match line.split_once("#egg=") {
+                Some((_, egg)) => egg.split('&').next().unwrap_or_default(),
+                None => line
+                    .trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default()
+                    .split(['-', '#', '?'])
+                    .next()
+                    .unwrap_or_default(),
+            };
+            return Some(Requirement {
+                name: name.to_owned(),
+                specifier: String::from(""),
+                url: Some(line.to_owned()),
+            })
+            .filter(|requirement| !requirement.name.is_empty());
+        }
+
+        let (spec, url) = match line.split_once('@') {
+            Some((spec, url)) => (spec.trim(), Some(url.trim().to_owned())),
+            None => (line, None),
+        };
+        let end = spec.find(|c| !is_name_char(c)).unwrap_or(spec.len());
+        let name = &spec[..end];
+        if name.is_empty() {
+            return None;
+        }
+        let rest = spec[end..].trim_start();
+        let rest = match rest.strip_prefix('[') {
+            Some(extras) => extras.split_once(']').map(|(_, rest)| rest).unwrap_or(""),
+            None => rest,
+        };
+        let specifier = rest
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .replace(' ', "");
+
+        Some(Requirement {
+            name: name.to_owned(),
+            specifier,
+            url,
+        })
+    }
+}
+
+/// Normalises a name the way PyPI does, so `Foo_Bar` and `foo-bar` are the same package.
+pub fn normalize_name(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c == '-' || c == '_' || c == '.' {
+            if !out.ends_with('-') {
+                out.push('-');
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// The index or find-links URL set by a requirement file line, e.g.
+/// `--extra-index-url https://example.com/simple`.
+pub fn index_option(line: &str) -> Option<String> {
+    let line = line.trim();
+    let (option, value) = match line.split_once(['=', ' ']) {
+        Some((option, value)) => (option, value.trim()),
+        None => return None,
+    };
+    if INDEX_OPTIONS.contains(&option) && !value.is_empty() {
+        Some(value.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Whether the URL points to PyPI, or to where PyPI serves its files from.
+pub fn is_default_index(url: &str) -> bool {
+    let host = url
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(url)
+        .split(['/', ':', '?'])
+        .next()
+        .unwrap_or_default()
+        .rsplit('@')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    DEFAULT_INDEX_HOSTS.contains(&host.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{index_option, is_default_index, Requirement};
+
+    #[test]
+    fn test_parse_requirements() {
+        let parse = |line: &str| {
+            let requirement = Requirement::parse(line).unwrap();
+            (requirement.name, requirement.specifier, requirement.url)
+        };
+
+        assert_eq!(
+            parse("requests[socks] >= 2.0, <3 ; python_version > '3.6'"),
+            (String::from("requests"), String::from(">=2.0,<3"), None)
+        );
+        assert_eq!(
+            parse("crcmod (<2.0,>=1.7)"),
+            (String::from("crcmod"), String::from("<2.0,>=1.7"), None)
+        );
+        assert_eq!(
+            parse("internal @ https://example.com/internal-1.0.tar.gz").2,
+            Some(String::from("https://example.com/internal-1.0.tar.gz"))
+        );
+        assert_eq!(
+            parse("git+https://example.com/tools.git#egg=tools").0,
+            String::from("tools")
+        );
+        assert_eq!(Requirement::parse("# a comment"), None);
+        assert_eq!(Requirement::parse("-r other.txt"), None);
+
+        assert_eq!(
+            index_option("--extra-index-url=https://example.com/simple"),
+            Some(String::from("https://example.com/simple"))
+        );
+        assert!(is_default_index("https://pypi.org/simple"));
+        assert!(!is_default_index("https://user@pypi.example.com/simple"));
+    }
+}
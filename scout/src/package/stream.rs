@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::tfidf::DocumentFrequencies;
+use super::{Metadata, NameFinding, Package};
+use crate::evaluator::{
+    AnalysisError, Bulletin, BulletinReport, FileReport, PackageReport, ParseStatus, ReportOptions,
+    SourceAnalysis, Timings, SCHEMA_VERSION,
+};
+use crate::source::{AutoExec, SourceFile};
+use crate::{utils, Result, ScoutError};
+
+/// Receives the results of a package while it is analysed, instead of all at once when it
+/// is done. A file can hold several sources, e.g. the cells of a notebook, each of them is
+/// parsed and finished on its own. Returning an error stops the analysis.
+pub trait Listener {
+    /// A file is about to be read, followed by its sources or by `on_file_failed`.
+    fn on_file_started(&mut self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// The file could not be read or parsed. Like with `analyse`, only code files are
+    /// listed in the errors of the summary as well.
+    fn on_file_failed(&mut self, _error: &AnalysisError) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_file_parsed(&mut self, _source: &SourceFile) -> Result<()> {
+        Ok(())
+    }
+
+    /// A bulletin shown in the report, called after the source was evaluated.
+    fn on_bulletin(&mut self, _analysis: &SourceAnalysis, _bulletin: &Bulletin) -> Result<()> {
+        Ok(())
+    }
+
+    /// The source was evaluated, it is dropped after this returns.
+    fn on_file_finished(&mut self, _analysis: &SourceAnalysis) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_package_finished(&mut self, _summary: &PackageSummary) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// What is left of a package once every source was streamed.
+#[derive(Debug)]
+pub struct PackageSummary {
+    /// Not set for single files
+    pub metadata: Option<Metadata>,
+    pub name_findings: Vec<NameFinding>,
+    pub errors: Vec<AnalysisError>,
+    /// How many sources were analysed
+    pub sources: usize,
+    /// How many of them are in the textual report
+    pub reported: usize,
+    pub timings: Timings,
+}
+
+impl<'cfg> Package<'cfg> {
+    /// Reads the `entry_points.txt` files among the entries.
+    fn read_entry_points(entries: &[PathBuf]) -> HashMap<PathBuf, Vec<String>> {
+        let files: Vec<(PathBuf, String)> = entries
+            .iter()
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name == "entry_points.txt")
+            })
+            .filter_map(|path| Some((path.to_owned(), std::fs::read_to_string(path).ok()?)))
+            .collect();
+        Package::collect_entry_points(&files)
+    }
+
+    fn load_entry(
+        &self,
+        path: &Path,
+        entry_points: &HashMap<PathBuf, Vec<String>>,
+    ) -> Result<Vec<SourceFile>> {
+        let mut sources = if Package::is_binary(path) {
+            let data = std::fs::read(path).map_err(|err| ScoutError::from(err).at(path))?;
+            vec![self.get_binary(path, &data)?]
+        } else {
+            self.get_sourcefiles(path, utils::load_from_file(path)?)?
+        };
+        for source in sources.iter_mut() {
+            if let Some(names) = entry_points.get(&source.source_path) {
+                source.set_auto_exec(AutoExec::EntryPoint(names.to_owned()));
+            }
+        }
+        Ok(sources)
+    }
+
+    /// Analyses the package one file at a time and passes the results to the listener as
+    /// they come, so only a single file is held in memory. With TF-IDF enabled, every file
+    /// is parsed once beforehand to count the modules and functions it uses. The results
+    /// are the same as those of `analyse`.
+    pub fn analyse_streaming(self, listener: &mut dyn Listener) -> Result<PackageSummary> {
+        let start = Instant::now();
+        let entries: Vec<PathBuf> = self
+            .list_entries()
+            .into_iter()
+            .filter(|path| !path.is_dir())
+            .collect();
+        let entry_points = Package::read_entry_points(&entries);
+
+        let frequencies = if self.uses_tfidf() {
+            let mut frequencies = DocumentFrequencies::default();
+            for entry in entries.iter() {
+                for source in self.load_entry(entry, &entry_points).unwrap_or_default() {
+                    frequencies.add(&source);
+                }
+            }
+            Some(frequencies)
+        } else {
+            None
+        };
+
+        let mut load = start.elapsed();
+        let mut evaluate = Duration::ZERO;
+        let mut errors: Vec<AnalysisError> = vec![];
+        let (mut sources, mut reported) = (0, 0);
+        for entry in entries.iter() {
+            listener.on_file_started(entry)?;
+
+            let loading = Instant::now();
+            let loaded = self.load_entry(entry, &entry_points);
+            load += loading.elapsed();
+            let loaded = match loaded {
+                Ok(loaded) => loaded,
+                Err(err) => {
+                    let error = AnalysisError::new(entry, err);
+                    listener.on_file_failed(&error)?;
+                    if Package::is_code(entry) {
+                        errors.push(error);
+                    }
+                    continue;
+                }
+            };
+            for source in loaded {
+                listener.on_file_parsed(&source)?;
+
+                let evaluating = Instant::now();
+                let mut analysis = SourceAnalysis::new(
+                    source,
+                    self.show_all_override,
+                    self.threshold,
+                    self.config,
+                );
+                if let Some(frequencies) = &frequencies {
+                    self.set_tfidf(frequencies, &mut analysis);
+                }
+                self.evaluate(&mut analysis);
+                evaluate += evaluating.elapsed();
+
+                for bulletin in analysis.get_visible_bulletins() {
+                    listener.on_bulletin(&analysis, bulletin)?;
+                }
+                sources += 1;
+                if analysis.message.is_some() {
+                    reported += 1;
+                }
+                listener.on_file_finished(&analysis)?;
+            }
+        }
+
+        let metadata = if self.path.is_file() {
+            None
+        } else {
+            self.read_metadata()
+        };
+        let summary = PackageSummary {
+            name_findings: self.check_names(metadata.as_ref())?,
+            metadata,
+            errors,
+            sources,
+            reported,
+            timings: Timings {
+                load_ms: load.as_secs_f64() * 1000.0,
+                evaluate_ms: evaluate.as_secs_f64() * 1000.0,
+                total_ms: start.elapsed().as_secs_f64() * 1000.0,
+            },
+        };
+        listener.on_package_finished(&summary)?;
+        Ok(summary)
+    }
+}
+
+/// A line of the JSON Lines output.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    FileStarted {
+        path: &'a Path,
+    },
+    FileFailed {
+        error: &'a AnalysisError,
+    },
+    FileParsed {
+        path: &'a str,
+        loc: usize,
+        status: ParseStatus,
+    },
+    Bulletin {
+        path: &'a str,
+        bulletin: BulletinReport,
+    },
+    FileFinished {
+        file: FileReport,
+    },
+    PackageFinished {
+        schema_version: u32,
+        scout_version: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        package: Option<PackageReport>,
+        name_findings: &'a Vec<NameFinding>,
+        errors: &'a Vec<AnalysisError>,
+        sources: usize,
+        reported: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timings: Option<&'a Timings>,
+    },
+}
+
+/// Writes every event as a line of JSON, flushed right away. Files and bulletins are the
+/// objects of the JSON output, see `Report`.
+pub struct JsonLines<W: Write> {
+    out: W,
+    options: ReportOptions,
+}
+
+impl<W: Write> JsonLines<W> {
+    pub fn new(out: W) -> Self {
+        JsonLines {
+            out,
+            options: ReportOptions::default(),
+        }
+    }
+
+    pub fn set_options(mut self, options: ReportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn write(&mut self, event: &Event) -> Result<()> {
+        serde_json::to_writer(&mut self.out, event)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Listener for JsonLines<W> {
+    fn on_file_started(&mut self, path: &Path) -> Result<()> {
+        self.write(&Event::FileStarted { path })
+    }
+
+    fn on_file_failed(&mut self, error: &AnalysisError) -> Result<()> {
+        self.write(&Event::FileFailed { error })
+    }
+
+    fn on_file_parsed(&mut self, source: &SourceFile) -> Result<()> {
+        self.write(&Event::FileParsed {
+            path: source.get_path(),
+            loc: source.get_loc(),
+            status: ParseStatus::of(source),
+        })
+    }
+
+    fn on_bulletin(&mut self, analysis: &SourceAnalysis, bulletin: &Bulletin) -> Result<()> {
+        self.write(&Event::Bulletin {
+            path: analysis.source.get_path(),
+            bulletin: BulletinReport::new(analysis, bulletin),
+        })
+    }
+
+    fn on_file_finished(&mut self, analysis: &SourceAnalysis) -> Result<()> {
+        let file = FileReport::from_analysis(analysis, &self.options);
+        self.write(&Event::FileFinished { file })
+    }
+
+    fn on_package_finished(&mut self, summary: &PackageSummary) -> Result<()> {
+        self.write(&Event::PackageFinished {
+            schema_version: SCHEMA_VERSION,
+            scout_version: env!("CARGO_PKG_VERSION"),
+            package: summary.metadata.as_ref().map(PackageReport::from_metadata),
+            name_findings: &summary.name_findings,
+            errors: &summary.errors,
+            sources: summary.sources,
+            reported: summary.reported,
+            timings: Some(&summary.timings).filter(|_| self.options.get_timings()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{Listener, PackageSummary};
+    use crate::evaluator::{AnalysisError, Bulletin, ReportOptions, SourceAnalysis};
+    use crate::{Engine, FileReport, Report, Result};
+
+    #[derive(Default)]
+    struct Collect {
+        started: usize,
+        failed: Vec<String>,
+        bulletins: usize,
+        files: Vec<FileReport>,
+        finished: bool,
+    }
+
+    impl Listener for Collect {
+        fn on_file_started(&mut self, _path: &Path) -> Result<()> {
+            self.started += 1;
+            Ok(())
+        }
+
+        fn on_file_failed(&mut self, error: &AnalysisError) -> Result<()> {
+            // a file is always started before it fails
+            assert!(self.started > self.failed.len());
+            self.failed.push(error.path.to_owned());
+            Ok(())
+        }
+
+        fn on_bulletin(&mut self, _analysis: &SourceAnalysis, _bulletin: &Bulletin) -> Result<()> {
+            self.bulletins += 1;
+            Ok(())
+        }
+
+        fn on_file_finished(&mut self, analysis: &SourceAnalysis) -> Result<()> {
+            if analysis.message.is_some() {
+                self.files.push(FileReport::from_analysis(
+                    analysis,
+                    &ReportOptions::default(),
+                ));
+            }
+            Ok(())
+        }
+
+        fn on_package_finished(&mut self, _summary: &PackageSummary) -> Result<()> {
+            self.finished = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_streaming_matches_analyse() {
+        let path = "../examples/packages/auto_exec";
+        let mut collect = Collect::default();
+        let summary = Engine::new()
+            .analyse_package_streaming(path, &mut collect)
+            .unwrap();
+        let results = Engine::new().analyse_package(path).unwrap();
+        let report = Report::new(&results, &ReportOptions::default());
+
+        assert!(collect.finished);
+        assert!(collect.started > 0 && collect.bulletins > 0);
+        assert_eq!(summary.reported, collect.files.len());
+        // the same files, bulletins and densities, TF-IDF included
+        let reported: Vec<&FileReport> = report.files.iter().filter(|f| f.reported).collect();
+        assert_eq!(
+            serde_json::to_value(&collect.files).unwrap(),
+            serde_json::to_value(&reported).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_streaming_reports_failed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.py");
+        std::fs::write(&path, b"print('\xff\xfe')\n\xff\xfe\n").unwrap();
+
+        let mut collect = Collect::default();
+        let summary = Engine::new()
+            .analyse_file_streaming(path.to_str().unwrap(), &mut collect)
+            .unwrap();
+
+        assert_eq!(collect.started, 1);
+        assert_eq!(collect.failed, vec![path.display().to_string()]);
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(summary.sources, 0);
+    }
+}
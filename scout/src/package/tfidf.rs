@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use crate::SourceFile;
+
+/// How many sources of a package import each module and call each function, to weigh
+/// them by TF-IDF. Only the names are kept, so it can be collected one source at a time.
+#[derive(Debug, Default)]
+pub struct DocumentFrequencies {
+    sources: usize,
+    imports: HashMap<String, usize>,
+    calls: HashMap<String, usize>,
+}
+
+impl DocumentFrequencies {
+    pub fn add(&mut self, source: &SourceFile) {
+        self.sources += 1;
+        for import in source.get_import_counts().keys() {
+            *self.imports.entry(import.to_owned()).or_default() += 1;
+        }
+        for call in source.get_call_counts().keys() {
+            *self.calls.entry(call.to_owned()).or_default() += 1;
+        }
+    }
+
+    fn idf(&self, sources_with_term: usize) -> f64 {
+        (self.sources as f64 / sources_with_term as f64).ln()
+    }
+
+    pub fn set_import_tfidf(&self, source: &mut SourceFile) {
+        for (import, freq) in source.import_term_frequency_table() {
+            let sources_with_import = self.imports.get(&import).copied().unwrap_or(0);
+            let tfidf = freq * self.idf(sources_with_import);
+            debug!(
+                "sources with import {}: {} -> tf-idf {}",
+                &import, sources_with_import, &tfidf
+            );
+            source.set_import_tfidf(&import, tfidf);
+        }
+    }
+
+    pub fn set_call_tfidf(&self, source: &mut SourceFile) {
+        for (call, freq) in source.calc_term_frequency_table() {
+            let sources_with_call = self.calls.get(&call).copied().unwrap_or(0);
+            let tfidf = freq * self.idf(sources_with_call);
+            debug!(
+                "sources with call {}: {} -> tf-idf {}",
+                &call, sources_with_call, &tfidf
+            );
+            source.set_call_tfidf(&call, tfidf);
+        }
+    }
+}
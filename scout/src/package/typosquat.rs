@@ -0,0 +1,339 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::requirement::{is_default_index, normalize_name, Requirement};
+use super::Metadata;
+use crate::utils;
+use crate::Result;
+
+/// Prefixes and suffixes that are added to popular names, e.g. `python-requests`.
+const PREFIXES: [&str; 3] = ["python-", "python3-", "py-"];
+const SUFFIXES: [&str; 4] = ["-py", "-python", "-python3", "-lib"];
+
+/// Names shorter than this are too close to too many others to check for typos.
+const MIN_TYPO_LEN: usize = 5;
+/// From this length on, names two edits away are reported as well.
+const TWO_EDITS_LEN: usize = 9;
+
+/// How a name is made to look like a popular one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Technique {
+    /// Letters are added, removed, replaced or swapped, e.g. `reqeusts`
+    Typo,
+    /// Characters that look alike, e.g. `0` for `o` or Cyrillic letters
+    Homoglyph,
+    /// Separators are left out or added, e.g. `pythondateutil`
+    Separator,
+    /// A prefix or suffix is added, e.g. `python-requests` or `requests-py`
+    Affix,
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let technique = match self {
+            Technique::Typo => "typo",
+            Technique::Homoglyph => "look-alike characters",
+            Technique::Separator => "separators",
+            Technique::Affix => "added prefix or suffix",
+        };
+        write!(f, "{}", technique)
+    }
+}
+
+/// Something about the package name or its dependencies that is often seen in
+/// typosquatting and dependency confusion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NameFinding {
+    /// The package, or one of its dependencies, is named like a popular package
+    Lookalike {
+        name: String,
+        dependency: bool,
+        popular: String,
+        technique: Technique,
+    },
+    /// A dependency is installed from a URL instead of an index
+    DirectUrl { name: String, url: String },
+    /// Dependencies are looked up in an index other than PyPI
+    ExtraIndex { url: String },
+}
+
+impl fmt::Display for NameFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameFinding::Lookalike {
+                name,
+                dependency,
+                popular,
+                technique,
+            } => write!(
+                f,
+                "{} '{}' looks like the popular package '{}' ({})",
+                if *dependency { "Dependency" } else { "Package" },
+                name,
+                popular,
+                technique
+            ),
+            NameFinding::DirectUrl { name, url } => {
+                write!(f, "Dependency '{}' is installed from '{}'", name, url)
+            }
+            NameFinding::ExtraIndex { url } => {
+                write!(f, "Dependencies are looked up in the index '{}'", url)
+            }
+        }
+    }
+}
+
+fn strip_separators(name: &str) -> String {
+    name.chars().filter(|c| *c != '-').collect()
+}
+
+/// Replaces characters that look like ASCII letters with those letters.
+fn fold_homoglyphs(name: &str) -> String {
+    let folded: String = name
+        .chars()
+        .map(|c| match c {
+            '0' | 'о' | 'ο' => 'o',
+            '1' | 'і' | 'ı' | 'ӏ' => 'l',
+            'а' | 'α' => 'a',
+            'е' | 'ε' => 'e',
+            'р' | 'ρ' => 'p',
+            'с' => 'c',
+            'у' => 'y',
+            'х' => 'x',
+            'ѕ' => 's',
+            'ј' => 'j',
+            'ν' => 'v',
+            c => c,
+        })
+        .collect();
+    folded.replace("rn", "m").replace("vv", "w")
+}
+
+/// Optimal string alignment distance, where swapping two neighbours counts as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows: Vec<Vec<usize>> = (0..=a.len())
+        .map(|i| {
+            let mut row = vec![0; b.len() + 1];
+            row[0] = i;
+            row
+        })
+        .collect();
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// A list of popular package names, one per line, that other names are compared to.
+#[derive(Debug)]
+pub struct PopularPackages {
+    /// Normalised names, in the order of the list
+    names: Vec<String>,
+    known: HashSet<String>,
+    /// Names without separators, and with homoglyphs folded as well
+    stripped: HashMap<String, usize>,
+    folded: HashMap<String, usize>,
+}
+
+impl PopularPackages {
+    pub const DEFAULT_POPULAR_FILE: &'static str = "popular_packages.txt";
+    const DEFAULT_POPULAR: &'static str = include_str!("popular_packages.txt");
+
+    pub fn new(popular_path: &Option<String>) -> Result<Self> {
+        let list = match popular_path {
+            Some(popular_path) => {
+                trace!("Loading popular packages from: '{}'", popular_path);
+                utils::load_from_file(popular_path)?
+            }
+            None => {
+                trace!(
+                    "Using default popular packages: '{}'",
+                    PopularPackages::DEFAULT_POPULAR_FILE
+                );
+                PopularPackages::DEFAULT_POPULAR.to_owned()
+            }
+        };
+        Ok(PopularPackages::from_list(&list))
+    }
+
+    fn from_list(list: &str) -> Self {
+        let mut popular = PopularPackages {
+            names: vec![],
+            known: HashSet::new(),
+            stripped: HashMap::new(),
+            folded: HashMap::new(),
+        };
+        for line in list.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let name = normalize_name(line);
+            if !popular.known.insert(name.to_owned()) {
+                continue;
+            }
+            let idx = popular.names.len();
+            let stripped = strip_separators(&name);
+            popular
+                .folded
+                .entry(fold_homoglyphs(&stripped))
+                .or_insert(idx);
+            popular.stripped.entry(stripped).or_insert(idx);
+            popular.names.push(name);
+        }
+        popular
+    }
+
+    /// The popular package the name imitates, if any. Popular names themselves are fine.
+    pub fn lookalike(&self, name: &str) -> Option<(&str, Technique)> {
+        let name = normalize_name(name);
+        if name.is_empty() || self.known.contains(&name) {
+            return None;
+        }
+        let stripped = strip_separators(&name);
+
+        if let Some(&idx) = self.stripped.get(&stripped) {
+            return Some((&self.names[idx], Technique::Separator));
+        }
+        if let Some(&idx) = self.folded.get(&fold_homoglyphs(&stripped)) {
+            return Some((&self.names[idx], Technique::Homoglyph));
+        }
+
+        let affixed = PREFIXES
+            .iter()
+            .filter_map(|prefix| name.strip_prefix(prefix))
+            .chain(
+                SUFFIXES
+                    .iter()
+                    .filter_map(|suffix| name.strip_suffix(suffix)),
+            )
+            .find(|bare| self.known.contains(*bare));
+        if let Some(bare) = affixed {
+            let idx = self.names.iter().position(|known| known == bare)?;
+            return Some((&self.names[idx], Technique::Affix));
+        }
+
+        if name.chars().count() < MIN_TYPO_LEN {
+            return None;
+        }
+        let max_distance = if name.chars().count() >= TWO_EDITS_LEN {
+            2
+        } else {
+            1
+        };
+        self.names
+            .iter()
+            .filter(|known| known.chars().count() >= MIN_TYPO_LEN)
+            .filter(|known| known.len().abs_diff(name.len()) <= max_distance)
+            .map(|known| (known, edit_distance(&name, known)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(known, _)| (known.as_str(), Technique::Typo))
+    }
+
+    /// Checks the package name, its dependencies and where they are installed from.
+    pub fn check(&self, metadata: &Metadata) -> Vec<NameFinding> {
+        let mut findings: Vec<NameFinding> = vec![];
+
+        if let Some((popular, technique)) = self.lookalike(&metadata.name) {
+            findings.push(NameFinding::Lookalike {
+                name: metadata.name.to_owned(),
+                dependency: false,
+                popular: popular.to_owned(),
+                technique,
+            });
+        }
+
+        let mut seen: HashSet<String> = HashSet::new();
+        for requirement in metadata
+            .requirements
+            .iter()
+            .filter_map(|r| Requirement::parse(r))
+        {
+            if !seen.insert(normalize_name(&requirement.name)) {
+                continue;
+            }
+            if let Some((popular, technique)) = self.lookalike(&requirement.name) {
+                findings.push(NameFinding::Lookalike {
+                    name: requirement.name.to_owned(),
+                    dependency: true,
+                    popular: popular.to_owned(),
+                    technique,
+                });
+            }
+            if let Some(url) = requirement.url {
+                findings.push(NameFinding::DirectUrl {
+                    name: requirement.name,
+                    url,
+                });
+            }
+        }
+
+        for url in metadata.indexes.iter() {
+            if !is_default_index(url) {
+                findings.push(NameFinding::ExtraIndex {
+                    url: url.to_owned(),
+                });
+            }
+        }
+
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PopularPackages, Technique};
+
+    #[test]
+    fn test_lookalikes() {
+        let popular = PopularPackages::from_list("requests\npython-dateutil\nurllib3\ncolorama\n");
+        let lookalike = |name: &str| popular.lookalike(name).map(|(p, t)| (p.to_owned(), t));
+
+        assert_eq!(lookalike("Requests"), None);
+        assert_eq!(lookalike("python_dateutil"), None);
+        assert_eq!(
+            lookalike("reqeusts"),
+            Some((String::from("requests"), Technique::Typo))
+        );
+        assert_eq!(
+            lookalike("colourama"),
+            Some((String::from("colorama"), Technique::Typo))
+        );
+        assert_eq!(
+            lookalike("pythondateutil"),
+            Some((String::from("python-dateutil"), Technique::Separator))
+        );
+        assert_eq!(
+            lookalike("urlli63"),
+            Some((String::from("urllib3"), Technique::Typo))
+        );
+        assert_eq!(
+            lookalike("co1orama"),
+            Some((String::from("colorama"), Technique::Homoglyph))
+        );
+        assert_eq!(
+            lookalike("python-requests"),
+            Some((String::from("requests"), Technique::Affix))
+        );
+        assert_eq!(lookalike("flask"), None);
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use tempfile::TempDir;
+
+use super::requirement::{normalize_name, Requirement};
+use super::{Metadata, Package};
+use crate::evaluator::AnalysisResult;
+use crate::{Result, ScoutError};
+
+/// Source distribution archives that can be unpacked.
+const SDIST_EXTENSIONS: [&str; 3] = [".tar.gz", ".tgz", ".zip"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArtifactKind {
+    Wheel,
+    Sdist,
+    Directory,
+}
+
+/// A wheel or source distribution in the wheelhouse, or a directory named
+/// `name-version` one was unpacked to.
+#[derive(Debug)]
+pub struct Artifact {
+    /// The normalised name of the distribution
+    pub name: String,
+    pub version: String,
+    pub path: PathBuf,
+    kind: ArtifactKind,
+}
+
+/// Where an artifact was unpacked to, the temporary directory is removed when dropped.
+pub struct Unpacked {
+    path: PathBuf,
+    _temp: Option<TempDir>,
+}
+
+impl Unpacked {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Artifact {
+    /// Reads the name and version from the file name, e.g.
+    /// `requests-2.31.0-py3-none-any.whl`, `requests-2.31.0.tar.gz` or a `requests-2.31.0`
+    /// directory.
+    fn from_path(path: &Path) -> Option<Artifact> {
+        let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+
+        let (name, version, kind) = if path.is_dir() {
+            // the version has to start with a digit, so `my-tools` isn't `my` version `tools`
+            let (name, version) = file_name.rsplit_once('-')?;
+            if !version.starts_with(|c: char| c.is_ascii_digit()) {
+                return None;
+            }
+            (name, version, ArtifactKind::Directory)
+        } else if let Some(stem) = file_name.strip_suffix(".whl") {
+            // name-version(-build)?-python-abi-platform
+            let parts: Vec<&str> = stem.split('-').collect();
+            if parts.len() < 5 {
+                return None;
+            }
+            (parts[0], parts[1], ArtifactKind::Wheel)
+        } else {
+            let stem = SDIST_EXTENSIONS
+                .iter()
+                .find_map(|extension| file_name.strip_suffix(extension))?;
+            let (name, version) = stem.rsplit_once('-')?;
+            (name, version, ArtifactKind::Sdist)
+        };
+
+        Some(Artifact {
+            name: normalize_name(name),
+            version: version.to_owned(),
+            path: path.to_path_buf(),
+            kind,
+        })
+    }
+
+    fn archive_error(&self, err: impl std::error::Error + Send + Sync + 'static) -> ScoutError {
+        ScoutError::Archive {
+            path: self.path.to_owned(),
+            source: Box::new(err),
+        }
+    }
+
+    /// Unpacks the archive into a temporary directory. Directories are used as they are.
+    pub fn unpack(&self) -> Result<Unpacked> {
+        if self.kind == ArtifactKind::Directory {
+            return Ok(Unpacked {
+                path: self.path.to_owned(),
+                _temp: None,
+            });
+        }
+
+        let temp = tempfile::tempdir()?;
+        let file = File::open(&self.path).map_err(|err| ScoutError::from(err).at(&self.path))?;
+        let file_name = self.path.to_string_lossy().to_lowercase();
+        if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            // entries that would be written outside the directory are skipped
+            tar::Archive::new(GzDecoder::new(file))
+                .unpack(temp.path())
+                .map_err(|err| self.archive_error(err))?;
+        } else {
+            // wheels are zip files as well, entries outside the directory are skipped
+            zip::ZipArchive::new(file)
+                .and_then(|mut archive| archive.extract(temp.path()))
+                .map_err(|err| self.archive_error(err))?;
+        }
+
+        Ok(Unpacked {
+            path: temp.path().to_path_buf(),
+            _temp: Some(temp),
+        })
+    }
+}
+
+/// The version a requirement is pinned to with `==` or `===`.
+fn pinned_version(specifier: &str) -> Option<&str> {
+    let version = specifier
+        .strip_prefix("===")
+        .or_else(|| specifier.strip_prefix("=="))?;
+    if version.is_empty() || version.contains([',', '*']) {
+        None
+    } else {
+        Some(version)
+    }
+}
+
+/// Compares versions, ignoring case, a leading `v` and trailing zeros, so `1.2` is `1.2.0`.
+fn same_version(a: &str, b: &str) -> bool {
+    let trim = |version: &str| {
+        let version = version.trim().to_lowercase();
+        let mut version = version.strip_prefix('v').unwrap_or(&version).to_owned();
+        while version.ends_with(".0") {
+            version.truncate(version.len() - 2);
+        }
+        version
+    };
+    trim(a) == trim(b)
+}
+
+/// Why a requirement wasn't scanned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Unscanned {
+    /// The requirement doesn't pin an exact version with `==`
+    NotPinned,
+    /// The requirement is installed from a URL instead of an index
+    DirectUrl { url: String },
+    /// The wheelhouse has no wheel or source distribution of the pinned version
+    NoArtifact,
+    /// The artifact couldn't be unpacked or analysed
+    Failed { artifact: PathBuf, error: String },
+}
+
+impl fmt::Display for Unscanned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unscanned::NotPinned => write!(f, "Not scanned, the version isn't pinned with '=='"),
+            Unscanned::DirectUrl { url } => {
+                write!(f, "Not scanned, it is installed from '{}'", url)
+            }
+            Unscanned::NoArtifact => {
+                write!(f, "Not scanned, no matching artifact is in the wheelhouse")
+            }
+            Unscanned::Failed { artifact, error } => write!(
+                f,
+                "Not scanned, '{}' could not be analysed: {}",
+                artifact.display(),
+                error
+            ),
+        }
+    }
+}
+
+/// A directory of wheels and source distributions, e.g. an offline mirror filled by
+/// `pip download`.
+#[derive(Debug)]
+pub struct Wheelhouse {
+    pub path: PathBuf,
+    artifacts: Vec<Artifact>,
+}
+
+impl Wheelhouse {
+    pub fn new(path: &Path) -> Result<Wheelhouse> {
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(path) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(err) => return Err(ScoutError::from(err).at(path)),
+        };
+        paths.sort();
+
+        Ok(Wheelhouse {
+            path: path.to_path_buf(),
+            artifacts: paths
+                .iter()
+                .filter_map(|path| Artifact::from_path(path))
+                .collect(),
+        })
+    }
+
+    /// The artifact a requirement resolves to, wheels are preferred over source
+    /// distributions.
+    pub fn resolve(&self, requirement: &Requirement) -> std::result::Result<&Artifact, Unscanned> {
+        if let Some(url) = &requirement.url {
+            return Err(Unscanned::DirectUrl {
+                url: url.to_owned(),
+            });
+        }
+        let version = pinned_version(&requirement.specifier).ok_or(Unscanned::NotPinned)?;
+        let name = normalize_name(&requirement.name);
+
+        let mut matching = self
+            .artifacts
+            .iter()
+            .filter(|artifact| artifact.name == name && same_version(&artifact.version, version));
+        let first = matching.next().ok_or(Unscanned::NoArtifact)?;
+        Ok(std::iter::once(first)
+            .chain(matching)
+            .find(|artifact| artifact.kind == ArtifactKind::Wheel)
+            .unwrap_or(first))
+    }
+}
+
+/// Reads the requirements in a requirements or lock file.
+pub fn read_requirements(path: &Path) -> Result<Vec<(String, Requirement)>> {
+    let mut metadata = Metadata::default();
+    Package::parse_requirements_file(&path.to_path_buf(), &mut metadata)?;
+    Ok(metadata
+        .requirements
+        .into_iter()
+        .filter_map(|line| Requirement::parse(&line).map(|requirement| (line, requirement)))
+        .collect())
+}
+
+/// What was found for a single requirement.
+#[derive(Debug)]
+pub enum ScanOutcome {
+    Scanned {
+        artifact: PathBuf,
+        result: AnalysisResult,
+    },
+    Unscanned(Unscanned),
+}
+
+#[derive(Debug)]
+pub struct RequirementResult {
+    /// The requirement as it is written in the file
+    pub requirement: String,
+    pub name: String,
+    pub outcome: ScanOutcome,
+}
+
+/// The results of every requirement in a requirements file.
+#[derive(Debug)]
+pub struct RequirementsResult {
+    pub requirements_file: PathBuf,
+    pub wheelhouse: PathBuf,
+    pub requirements: Vec<RequirementResult>,
+}
+
+impl RequirementsResult {
+    /// The requirements that weren't scanned, and why.
+    pub fn unscanned(&self) -> Vec<(&str, &Unscanned)> {
+        self.requirements
+            .iter()
+            .filter_map(|requirement| match &requirement.outcome {
+                ScanOutcome::Unscanned(unscanned) => {
+                    Some((requirement.requirement.as_str(), unscanned))
+                }
+                ScanOutcome::Scanned { .. } => None,
+            })
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        let requirements: Vec<serde_json::Value> = self
+            .requirements
+            .iter()
+            .map(|requirement| match &requirement.outcome {
+                ScanOutcome::Scanned { artifact, result } => serde_json::json!({
+                    "requirement": requirement.requirement,
+                    "name": requirement.name,
+                    "status": "scanned",
+                    "artifact": artifact,
+                    "results": result.to_json_value(),
+                }),
+                ScanOutcome::Unscanned(unscanned) => serde_json::json!({
+                    "requirement": requirement.requirement,
+                    "name": requirement.name,
+                    "status": "unscanned",
+                    "unscanned": unscanned,
+                }),
+            })
+            .collect();
+        serde_json::json!({
+            "requirements_file": self.requirements_file,
+            "wheelhouse": self.wheelhouse,
+            "requirements": requirements,
+        })
+        .to_string()
+    }
+}
+
+impl fmt::Display for RequirementsResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for requirement in self.requirements.iter() {
+            match &requirement.outcome {
+                ScanOutcome::Scanned { artifact, result } => {
+                    let header = format!(
+                        "Requirement: {} ({})",
+                        requirement.requirement,
+                        artifact.display()
+                    );
+                    writeln!(f, "{}", header.bold())?;
+                    let report = result.to_string();
+                    if report.trim().is_empty() {
+                        writeln!(f, "      no findings")?;
+                    } else {
+                        writeln!(f, "{}", report.trim_end())?;
+                    }
+                }
+                ScanOutcome::Unscanned(unscanned) => {
+                    let header = format!("Requirement: {}", requirement.requirement);
+                    writeln!(f, "{}", header.bold())?;
+                    writeln!(f, "      {}", format!("^{}", unscanned).yellow())?;
+                }
+            }
+        }
+
+        let unscanned = self.unscanned().len();
+        if unscanned > 0 {
+            writeln!(
+                f,
+                "\n{} of {} requirements were not scanned",
+                unscanned,
+                self.requirements.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{pinned_version, same_version, Artifact, ArtifactKind};
+
+    #[test]
+    fn test_artifact_names() {
+        let artifact = Artifact::from_path(Path::new("Tidy_JSON-2.1.0-py3-none-any.whl")).unwrap();
+        assert_eq!(
+            (
+                artifact.name.as_str(),
+                artifact.version.as_str(),
+                artifact.kind
+            ),
+            ("tidy-json", "2.1.0", ArtifactKind::Wheel)
+        );
+        let artifact = Artifact::from_path(Path::new("net-pipe-0.4.2.tar.gz")).unwrap();
+        assert_eq!(
+            (
+                artifact.name.as_str(),
+                artifact.version.as_str(),
+                artifact.kind
+            ),
+            ("net-pipe", "0.4.2", ArtifactKind::Sdist)
+        );
+        assert!(Artifact::from_path(Path::new("README.md")).is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let unpacked = dir.path().join("Net_Pipe-0.4.2");
+        std::fs::create_dir(&unpacked).unwrap();
+        std::fs::create_dir(dir.path().join("build-tools")).unwrap();
+        let artifact = Artifact::from_path(&unpacked).unwrap();
+        assert_eq!(
+            (
+                artifact.name.as_str(),
+                artifact.version.as_str(),
+                artifact.kind
+            ),
+            ("net-pipe", "0.4.2", ArtifactKind::Directory)
+        );
+        assert_eq!(artifact.unpack().unwrap().path(), unpacked);
+        assert!(Artifact::from_path(&dir.path().join("build-tools")).is_none());
+
+        assert_eq!(pinned_version("==2.1"), Some("2.1"));
+        assert_eq!(pinned_version(">=2.1"), None);
+        assert_eq!(pinned_version("==2.*"), None);
+        assert!(same_version("2.1", "2.1.0"));
+        assert!(!same_version("2.1", "2.10"));
+    }
+}
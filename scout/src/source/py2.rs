@@ -0,0 +1,679 @@
+//! Rewrites Python 2 syntax into something the Python 3 parser accepts.
+//!
+//! Rewrites never add or remove lines, so every location in the rewritten source
+//! has the same line number as in the original file. Columns on a rewritten line
+//! may shift by a few characters.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixKind {
+    PrintStatement,
+    ExecStatement,
+    Backticks,
+    OctalLiteral,
+    LongLiteral,
+    UnicodeRawString,
+    ExceptComma,
+    NotEqual,
+    MixedIndentation,
+    /// The line could not be fixed and was removed before parsing.
+    BlankedLine,
+}
+
+/// A change made to a single line so the file could be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFix {
+    pub line: usize,
+    pub kind: FixKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Name,
+    Number,
+    Str,
+    Comment,
+    Newline,
+    Continuation,
+    Space,
+    Op,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+    kind: FixKind,
+}
+
+const OPERATORS: [&str; 21] = [
+    "**=", "//=", ">>=", "<<=", "<>", ">>", "<<", "**", "//", "==", "!=", "<=", ">=", "->", "+=",
+    "-=", "*=", "/=", "%=", "&=", "|=",
+];
+
+fn is_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c >= 0x80
+}
+
+fn is_string_prefix(prefix: &[u8]) -> bool {
+    matches!(
+        prefix.to_ascii_lowercase().as_slice(),
+        b"r" | b"u" | b"b" | b"ur" | b"br" | b"rb"
+    )
+}
+
+/// The index after the closing quote of the string starting at `i`.
+/// Unterminated single quoted strings end at the end of their line.
+fn string_end(b: &[u8], mut i: usize) -> usize {
+    let quote = b[i];
+    let triple = b.len() >= i + 3 && b[i + 1] == quote && b[i + 2] == quote;
+    i += if triple { 3 } else { 1 };
+
+    while i < b.len() {
+        match b[i] {
+            b'\\' => i += 2,
+            b'\n' | b'\r' if !triple => return i,
+            c if c == quote => {
+                if !triple {
+                    return i + 1;
+                }
+                if b.len() >= i + 3 && b[i + 1] == quote && b[i + 2] == quote {
+                    return i + 3;
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    b.len()
+}
+
+fn number_end(b: &[u8], mut i: usize) -> usize {
+    let hex = b[i] == b'0' && b.len() > i + 1 && b[i + 1].eq_ignore_ascii_case(&b'x');
+    loop {
+        while i < b.len() && (b[i].is_ascii_alphanumeric() || b[i] == b'_' || b[i] == b'.') {
+            i += 1;
+        }
+        // exponents like 1e-05
+        if !hex
+            && i < b.len()
+            && (b[i] == b'+' || b[i] == b'-')
+            && b[i - 1].eq_ignore_ascii_case(&b'e')
+        {
+            i += 1;
+            continue;
+        }
+        return i;
+    }
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let b = source.as_bytes();
+    let mut tokens: Vec<Token> = vec![];
+    let mut i = 0;
+
+    while i < b.len() {
+        let start = i;
+        let kind = match b[i] {
+            b'\n' => {
+                i += 1;
+                TokenKind::Newline
+            }
+            b'\r' => {
+                i += if b.get(i + 1) == Some(&b'\n') { 2 } else { 1 };
+                TokenKind::Newline
+            }
+            b' ' | b'\t' | b'\x0c' => {
+                while i < b.len() && matches!(b[i], b' ' | b'\t' | b'\x0c') {
+                    i += 1;
+                }
+                TokenKind::Space
+            }
+            b'#' => {
+                while i < b.len() && b[i] != b'\n' && b[i] != b'\r' {
+                    i += 1;
+                }
+                TokenKind::Comment
+            }
+            b'\\' if matches!(b.get(i + 1), Some(b'\n') | Some(b'\r')) => {
+                i += if b[i + 1] == b'\r' && b.get(i + 2) == Some(&b'\n') {
+                    3
+                } else {
+                    2
+                };
+                TokenKind::Continuation
+            }
+            b'\'' | b'"' => {
+                i = string_end(b, i);
+                TokenKind::Str
+            }
+            c if c.is_ascii_digit()
+                || (c == b'.' && b.get(i + 1).is_some_and(u8::is_ascii_digit)) =>
+            {
+                i = number_end(b, i);
+                TokenKind::Number
+            }
+            c if is_name_char(c) => {
+                while i < b.len() && is_name_char(b[i]) {
+                    i += 1;
+                }
+                if i < b.len() && (b[i] == b'\'' || b[i] == b'"') && is_string_prefix(&b[start..i])
+                {
+                    i = string_end(b, i);
+                    TokenKind::Str
+                } else {
+                    TokenKind::Name
+                }
+            }
+            _ => {
+                i += OPERATORS
+                    .iter()
+                    .find(|op| b[i..].starts_with(op.as_bytes()))
+                    .map_or(1, |op| op.len());
+                TokenKind::Op
+            }
+        };
+        tokens.push(Token {
+            kind,
+            start,
+            end: i,
+        });
+    }
+
+    tokens
+}
+
+/// Rewrites Python 2 only syntax, returning the new source and the fixes applied.
+/// Strings and comments are never touched.
+pub fn rewrite(source: &str) -> (String, Vec<SourceFix>) {
+    let rewriter = Rewriter {
+        source,
+        tokens: tokenize(source),
+    };
+
+    let mut edits = rewriter.literal_edits();
+    edits.append(&mut rewriter.statement_edits());
+    edits.append(&mut rewriter.indentation_edits());
+
+    apply(source, edits)
+}
+
+struct Rewriter<'s> {
+    source: &'s str,
+    tokens: Vec<Token>,
+}
+
+impl<'s> Rewriter<'s> {
+    fn text(&self, idx: usize) -> &'s str {
+        let token = self.tokens[idx];
+        &self.source[token.start..token.end]
+    }
+
+    fn is_op(&self, idx: usize, op: &str) -> bool {
+        self.tokens[idx].kind == TokenKind::Op && self.text(idx) == op
+    }
+
+    fn is_blank(&self, idx: usize) -> bool {
+        matches!(
+            self.tokens[idx].kind,
+            TokenKind::Space | TokenKind::Continuation
+        )
+    }
+
+    fn next_significant(&self, from: usize, end: usize) -> Option<usize> {
+        (from..end).find(|&idx| !self.is_blank(idx))
+    }
+
+    fn last_significant(&self, from: usize, end: usize) -> Option<usize> {
+        (from..end).rev().find(|&idx| !self.is_blank(idx))
+    }
+
+    fn depth_change(&self, idx: usize) -> i32 {
+        if self.tokens[idx].kind != TokenKind::Op {
+            return 0;
+        }
+        match self.text(idx) {
+            "(" | "[" | "{" => 1,
+            ")" | "]" | "}" => -1,
+            _ => 0,
+        }
+    }
+
+    /// The index of the token ending the simple statement that contains `from`.
+    fn statement_end(&self, from: usize) -> usize {
+        let mut depth = 0;
+        for idx in from..self.tokens.len() {
+            if depth <= 0
+                && (matches!(
+                    self.tokens[idx].kind,
+                    TokenKind::Newline | TokenKind::Comment
+                ) || self.is_op(idx, ";"))
+            {
+                return idx;
+            }
+            depth += self.depth_change(idx);
+        }
+        self.tokens.len()
+    }
+
+    /// Indices of the tokens in `from..end` at bracket depth zero that satisfy `pred`.
+    fn top_level(&self, from: usize, end: usize, pred: impl Fn(usize) -> bool) -> Vec<usize> {
+        let mut depth = 0;
+        let mut found: Vec<usize> = vec![];
+        for idx in from..end {
+            if depth == 0 && pred(idx) {
+                found.push(idx);
+            }
+            depth += self.depth_change(idx);
+        }
+        found
+    }
+
+    fn literal_edits(&self) -> Vec<Edit> {
+        let mut edits: Vec<Edit> = vec![];
+        let mut in_backticks = false;
+
+        for (idx, token) in self.tokens.iter().enumerate() {
+            let text = self.text(idx);
+            match token.kind {
+                TokenKind::Str => {
+                    let prefix = text.find(['\'', '"']).unwrap_or(0);
+                    if prefix == 2 && text[..2].eq_ignore_ascii_case("ur") {
+                        edits.push(Edit {
+                            start: token.start,
+                            end: token.start + 1,
+                            text: String::from(""),
+                            kind: FixKind::UnicodeRawString,
+                        });
+                    }
+                }
+                TokenKind::Number => {
+                    let (digits, long) = match text.strip_suffix(['l', 'L']) {
+                        Some(digits) => (digits, true),
+                        None => (text, false),
+                    };
+                    let octal = digits.len() > 1
+                        && digits.starts_with('0')
+                        && digits.bytes().all(|c| (b'0'..=b'7').contains(&c))
+                        && digits.bytes().any(|c| c != b'0');
+
+                    if octal {
+                        edits.push(Edit {
+                            start: token.start,
+                            end: token.end,
+                            text: format!("0o{}", digits.trim_start_matches('0')),
+                            kind: FixKind::OctalLiteral,
+                        });
+                    } else if long && digits.bytes().all(|c| c.is_ascii_alphanumeric()) {
+                        edits.push(Edit {
+                            start: token.start,
+                            end: token.end,
+                            text: digits.to_owned(),
+                            kind: FixKind::LongLiteral,
+                        });
+                    }
+                }
+                TokenKind::Op if text == "<>" => edits.push(Edit {
+                    start: token.start,
+                    end: token.end,
+                    text: String::from("!="),
+                    kind: FixKind::NotEqual,
+                }),
+                TokenKind::Op if text == "`" => {
+                    edits.push(Edit {
+                        start: token.start,
+                        end: token.end,
+                        text: String::from(if in_backticks { ")" } else { "repr(" }),
+                        kind: FixKind::Backticks,
+                    });
+                    in_backticks = !in_backticks;
+                }
+                _ => {}
+            }
+        }
+
+        edits
+    }
+
+    fn statement_edits(&self) -> Vec<Edit> {
+        let mut edits: Vec<Edit> = vec![];
+        let mut depth = 0;
+        let mut at_start = true;
+
+        for idx in 0..self.tokens.len() {
+            match self.tokens[idx].kind {
+                TokenKind::Space | TokenKind::Continuation | TokenKind::Comment => continue,
+                TokenKind::Newline => {
+                    if depth <= 0 {
+                        depth = 0;
+                        at_start = true;
+                    }
+                    continue;
+                }
+                TokenKind::Name if at_start && depth == 0 => match self.text(idx) {
+                    "print" => edits.append(&mut self.print_statement(idx)),
+                    "exec" => edits.append(&mut self.exec_statement(idx)),
+                    "except" => edits.append(&mut self.except_clause(idx)),
+                    _ => {}
+                },
+                _ => {}
+            }
+
+            at_start = depth == 0 && (self.is_op(idx, ";") || self.is_op(idx, ":"));
+            depth += self.depth_change(idx);
+        }
+
+        edits
+    }
+
+    /// `print a, b,` -> `print(a, b, end=" ")` and `print >>f, a` -> `print(a, file=f)`
+    fn print_statement(&self, idx: usize) -> Vec<Edit> {
+        let print = self.tokens[idx];
+        let end = self.statement_end(idx + 1);
+
+        let first = match self.next_significant(idx + 1, end) {
+            Some(first) => first,
+            None => {
+                return vec![Edit {
+                    start: print.end,
+                    end: print.end,
+                    text: String::from("()"),
+                    kind: FixKind::PrintStatement,
+                }]
+            }
+        };
+
+        if self.tokens[first].kind == TokenKind::Op
+            && !matches!(self.text(first), ">>" | "-" | "+" | "~" | "`" | "[" | "{")
+        {
+            // either already a call, or print is used as a name
+            return vec![];
+        }
+
+        let last = self.last_significant(first, end).unwrap_or(first);
+        let mut kwargs: Vec<String> = vec![];
+        let mut args_start = Some(first);
+
+        if self.is_op(first, ">>") {
+            let target_end = self
+                .top_level(first + 1, last + 1, |i| self.is_op(i, ","))
+                .first()
+                .copied();
+            let target = match target_end {
+                Some(comma) => &self.source[self.tokens[first].end..self.tokens[comma].start],
+                None => &self.source[self.tokens[first].end..self.tokens[last].end],
+            };
+            kwargs.push(format!("file={}", target.trim()));
+            args_start = target_end.and_then(|comma| self.next_significant(comma + 1, last + 1));
+        }
+
+        let trailing_comma = self.is_op(last, ",") && Some(last) != args_start;
+        let args_end = match args_start {
+            Some(_) if trailing_comma => self.last_significant(first, last),
+            Some(_) => Some(last),
+            None => None,
+        };
+
+        if trailing_comma {
+            kwargs.insert(0, String::from("end=\" \""));
+        }
+
+        match (args_start, args_end) {
+            (Some(start), Some(end)) if start <= end => {
+                let suffix: String = kwargs.iter().map(|k| format!(", {}", k)).collect();
+                vec![
+                    Edit {
+                        start: print.end,
+                        end: self.tokens[start].start,
+                        text: String::from("("),
+                        kind: FixKind::PrintStatement,
+                    },
+                    Edit {
+                        start: self.tokens[end].end,
+                        end: self.tokens[last].end,
+                        text: format!("{})", suffix),
+                        kind: FixKind::PrintStatement,
+                    },
+                ]
+            }
+            _ => vec![Edit {
+                start: print.end,
+                end: self.tokens[last].end,
+                text: format!("({})", kwargs.join(", ")),
+                kind: FixKind::PrintStatement,
+            }],
+        }
+    }
+
+    /// `exec code in globals, locals` -> `exec(code, globals, locals)`
+    fn exec_statement(&self, idx: usize) -> Vec<Edit> {
+        let exec = self.tokens[idx];
+        let end = self.statement_end(idx + 1);
+
+        let first = match self.next_significant(idx + 1, end) {
+            Some(first) if matches!(self.tokens[first].kind, TokenKind::Name | TokenKind::Str) => {
+                first
+            }
+            _ => return vec![],
+        };
+        let last = self.last_significant(first, end).unwrap_or(first);
+
+        let mut edits = vec![Edit {
+            start: exec.end,
+            end: self.tokens[first].start,
+            text: String::from("("),
+            kind: FixKind::ExecStatement,
+        }];
+
+        let scopes = self.top_level(first + 1, last + 1, |i| {
+            self.tokens[i].kind == TokenKind::Name && self.text(i) == "in"
+        });
+        if let Some(&scopes) = scopes.first() {
+            let start = match self.tokens[scopes - 1].kind {
+                TokenKind::Space => self.tokens[scopes - 1].start,
+                _ => self.tokens[scopes].start,
+            };
+            edits.push(Edit {
+                start,
+                end: self.tokens[scopes].end,
+                text: String::from(","),
+                kind: FixKind::ExecStatement,
+            });
+        }
+
+        edits.push(Edit {
+            start: self.tokens[last].end,
+            end: self.tokens[last].end,
+            text: String::from(")"),
+            kind: FixKind::ExecStatement,
+        });
+        edits
+    }
+
+    /// `except Error, e:` -> `except Error as e:`
+    fn except_clause(&self, idx: usize) -> Vec<Edit> {
+        let end = self.statement_end(idx + 1);
+        let colon = match self.top_level(idx + 1, end, |i| self.is_op(i, ":")).first() {
+            Some(&colon) => colon,
+            None => return vec![],
+        };
+
+        let commas = self.top_level(idx + 1, colon, |i| self.is_op(i, ","));
+        if commas.len() != 1 {
+            return vec![];
+        }
+
+        let comma = commas[0];
+        let name = self.next_significant(comma + 1, colon).unwrap_or(comma + 1);
+        vec![Edit {
+            start: self.tokens[comma].start,
+            end: self.tokens[name].start,
+            text: String::from(" as "),
+            kind: FixKind::ExceptComma,
+        }]
+    }
+
+    /// Python 2 treats a tab as indentation up to the next multiple of eight, while
+    /// Python 3 refuses files that mix tabs and spaces. Expands the tabs when mixed.
+    fn indentation_edits(&self) -> Vec<Edit> {
+        let in_string = |offset: usize| {
+            self.tokens
+                .iter()
+                .any(|t| t.kind == TokenKind::Str && t.start < offset && offset < t.end)
+        };
+
+        let mut indents: Vec<(usize, &str)> = vec![];
+        let mut offset = 0;
+        for line in self.source.split_inclusive('\n') {
+            let indent_len = line.len() - line.trim_start_matches([' ', '\t']).len();
+            if indent_len > 0 && !in_string(offset) {
+                indents.push((offset, &line[..indent_len]));
+            }
+            offset += line.len();
+        }
+
+        let tabs = indents.iter().any(|(_, indent)| indent.contains('\t'));
+        let spaces = indents.iter().any(|(_, indent)| indent.contains(' '));
+        if !(tabs && spaces) {
+            return vec![];
+        }
+
+        indents
+            .into_iter()
+            .filter(|(_, indent)| indent.contains('\t'))
+            .map(|(start, indent)| {
+                let width = indent.chars().fold(0, |col, c| match c {
+                    '\t' => (col / 8 + 1) * 8,
+                    _ => col + 1,
+                });
+                Edit {
+                    start,
+                    end: start + indent.len(),
+                    text: " ".repeat(width),
+                    kind: FixKind::MixedIndentation,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Applies the edits from the start of the source, skipping any that overlap an
+/// edit already applied.
+fn apply(source: &str, mut edits: Vec<Edit>) -> (String, Vec<SourceFix>) {
+    edits.sort_by_key(|e| (e.start, e.end));
+
+    let mut out = String::with_capacity(source.len());
+    let mut fixes: Vec<SourceFix> = vec![];
+    let mut pos = 0;
+
+    for edit in edits {
+        if edit.start < pos {
+            continue;
+        }
+        out.push_str(&source[pos..edit.start]);
+        out.push_str(&edit.text);
+        pos = edit.end;
+
+        let fix = SourceFix {
+            line: source[..edit.start].matches('\n').count() + 1,
+            kind: edit.kind,
+        };
+        if !fixes.contains(&fix) {
+            fixes.push(fix);
+        }
+    }
+    out.push_str(&source[pos..]);
+
+    (out, fixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rewrite, FixKind};
+
+    fn rewritten(source: &str) -> String {
+        rewrite(source).0
+    }
+
+    #[test]
+    fn test_print_statements() {
+        assert_eq!(rewritten("print 'a', b\n"), "print('a', b)\n");
+        assert_eq!(rewritten("print\n"), "print()\n");
+        assert_eq!(rewritten("print x,  # c\n"), "print(x, end=\" \")  # c\n");
+        assert_eq!(
+            rewritten("print >>sys.stderr, 'a' % b\n"),
+            "print('a' % b, file=sys.stderr)\n"
+        );
+        assert_eq!(rewritten("print >>f\n"), "print(file=f)\n");
+        assert_eq!(
+            rewritten("if x: print x; y = 1\n"),
+            "if x: print(x); y = 1\n"
+        );
+        assert_eq!(rewritten("print(x)\nprint = 1\n"), "print(x)\nprint = 1\n");
+        assert_eq!(
+            rewritten("print 'a', (1,\n    2)\n"),
+            "print('a', (1,\n    2))\n"
+        );
+    }
+
+    #[test]
+    fn test_exec_and_except() {
+        assert_eq!(
+            rewritten("exec \"x = 1\" in g, l\n"),
+            "exec(\"x = 1\", g, l)\n"
+        );
+        assert_eq!(rewritten("exec(code)\n"), "exec(code)\n");
+        assert_eq!(
+            rewritten("try:\n    pass\nexcept (A, B), e:\n    pass\n"),
+            "try:\n    pass\nexcept (A, B) as e:\n    pass\n"
+        );
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(rewritten("os.chmod(p, 0777)\n"), "os.chmod(p, 0o777)\n");
+        assert_eq!(rewritten("x = 10L + 0xffL\n"), "x = 10 + 0xff\n");
+        assert_eq!(rewritten("x = ur'\\d'\n"), "x = r'\\d'\n");
+        assert_eq!(rewritten("x = `y` <> `z`\n"), "x = repr(y) != repr(z)\n");
+        // strings, floats and timestamps are left alone
+        let untouched = "t = '2020-01-01 00:07'\nf = 1.0777 + 1e-05 + 00\n# 0777 `a`\n";
+        assert_eq!(rewritten(untouched), untouched);
+    }
+
+    #[test]
+    fn test_mixed_indentation() {
+        let source = "if x:\n    y = 1\n\tz = '''\n\tkeep'''\n";
+        let (out, fixes) = rewrite(source);
+        assert_eq!(out, "if x:\n    y = 1\n        z = '''\n\tkeep'''\n");
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].line, 3);
+        assert_eq!(fixes[0].kind, FixKind::MixedIndentation);
+    }
+
+    #[test]
+    fn test_line_numbers_are_preserved() {
+        let source = "import os\nprint `os`, 0777\n\nexec 'a'\n";
+        let (out, fixes) = rewrite(source);
+        assert_eq!(out.lines().count(), source.lines().count());
+
+        let lines: Vec<(usize, FixKind)> = fixes.iter().map(|f| (f.line, f.kind)).collect();
+        assert_eq!(
+            lines,
+            vec![
+                (2, FixKind::PrintStatement),
+                (2, FixKind::Backticks),
+                (2, FixKind::OctalLiteral),
+                (4, FixKind::ExecStatement),
+            ]
+        );
+    }
+}
@@ -1,21 +1,30 @@
 use ast_walker::{AstVisitor, AstWalker};
-use regex::Regex;
 use rustpython_parser::ast::{Program, Suite};
 use rustpython_parser::error::ParseError;
 use rustpython_parser::parser;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
-
-use crate::visitors::{CallEntry, CallVisitor, ImportEntry, ImportVisitor, VariableVisitor};
-use crate::Result;
-
+use std::path::{Path, PathBuf};
+
+use super::auto_exec::{self, AutoExec};
+use super::marshal::Marshal;
+use super::native::{self, BinaryArtifact, NativeModule};
+use super::notebook::{self, ShellCommand};
+use super::py2::{self, FixKind, SourceFix};
+use crate::visitors::{
+    BlobVisitor, CallEntry, CallVisitor, Definition, DefinitionVisitor, ImportEntry, ImportVisitor,
+    StringEntry, StringVisitor, VariableVisitor,
+};
+use crate::{Result, ScoutError};
+
+/// Makes a file parsable by first rewriting any Python 2 syntax, and then blanking
+/// the lines the parser still fails on. Lines are never added or removed, so
+/// locations in the parsed program match the original source.
 pub struct ParseErrorFixer {
     attempts: i32,
     current_attempts: i32,
-    regex_py2except: Regex,
-    regex_leading_zero: Regex,
-    tried_common_fixes: bool,
+    tried_py2_rewrite: bool,
+    fixes: Vec<SourceFix>,
 }
 
 impl ParseErrorFixer {
@@ -23,48 +32,43 @@ impl ParseErrorFixer {
         Self {
             attempts,
             current_attempts: 0,
-            regex_py2except: Regex::new(r"except\s[\w]+[\s]*[,][\s]*[\w+]:").unwrap(),
-            regex_leading_zero: Regex::new(r"[0]+\d").unwrap(),
-            tried_common_fixes: false,
+            tried_py2_rewrite: false,
+            fixes: vec![],
         }
     }
 
     pub fn attempts_left(&self) -> bool {
-        self.current_attempts < self.attempts
+        !self.tried_py2_rewrite || self.current_attempts < self.attempts
+    }
+
+    pub fn get_fixes(&self) -> &Vec<SourceFix> {
+        &self.fixes
     }
 
     pub fn attempt_fix(&mut self, err: &ParseError, source: &str) -> String {
-        self.current_attempts += 1;
-        let line = err.location.row() - 1;
-
-        let mut lines = source
-            .lines()
-            .map(|s| s.to_owned())
-            .collect::<Vec<String>>();
-
-        if !self.tried_common_fixes {
-            for line in lines.iter_mut() {
-                if let Some(captures) = self.regex_leading_zero.captures(line) {
-                    if let Some(m) = captures.get(0) {
-                        debug!("Match: {}", &m.as_str());
-                        let new = &m.as_str().replace("0", "");
-                        *line = line.replace(&m.as_str(), new);
-                    }
-                }
-                if self.regex_py2except.is_match(line) {
-                    *line = line.replace(",", " as ");
-                }
-            }
-            self.tried_common_fixes = true;
-        } else {
-            // if we have tried common fixes we just remove some lines to see if that works
-            for (idx, line_content) in lines.iter_mut().enumerate() {
-                if idx == line {
-                    *line_content = String::from("");
-                }
+        if !self.tried_py2_rewrite {
+            self.tried_py2_rewrite = true;
+            let (rewritten, mut fixes) = py2::rewrite(source);
+            if !fixes.is_empty() {
+                debug!("Rewrote Python 2 syntax: {:?}", fixes);
+                self.fixes.append(&mut fixes);
+                return rewritten;
             }
         }
 
+        // if rewriting did not help we just remove the failing line to see if that works
+        self.current_attempts += 1;
+        let row = err.location.row();
+
+        let mut lines = source.lines().collect::<Vec<&str>>();
+        if row > 0 && row <= lines.len() {
+            lines[row - 1] = "";
+            self.fixes.push(SourceFix {
+                line: row,
+                kind: FixKind::BlankedLine,
+            });
+        }
+
         lines.join("\n")
     }
 }
@@ -79,6 +83,26 @@ pub struct SourceFile {
     import_visitor: ImportVisitor,
     call_visitor: CallVisitor,
     pub variable_visitor: VariableVisitor,
+    definition_visitor: DefinitionVisitor,
+    #[serde(default)]
+    string_visitor: StringVisitor,
+    #[serde(default)]
+    blob_visitor: BlobVisitor,
+    /// Rewrites and blanked lines needed to parse the file.
+    #[serde(default)]
+    fixes: Vec<SourceFix>,
+    /// Set when the file runs without being called explicitly, e.g. a `.pth` file.
+    #[serde(default)]
+    auto_exec: Option<AutoExec>,
+    /// `!shell` lines and process magics when the file is a notebook cell.
+    #[serde(default)]
+    shell_commands: Vec<ShellCommand>,
+    /// Set when the file is a native extension module.
+    #[serde(default)]
+    artifact: Option<BinaryArtifact>,
+    /// The classes passed to `setup(cmdclass=...)` when the file is a `setup.py`.
+    #[serde(default)]
+    setup_commands: Vec<String>,
 }
 
 impl SourceFile {
@@ -96,24 +120,26 @@ impl SourceFile {
             }
         } else {
             warn!("last error: {}", init_err);
-            Err(init_err.into())
+            Err(ScoutError::parse_from(init_err.to_string(), init_err))
         }
     }
 
-    fn parse_file(source: &str) -> Result<Box<Program>> {
+    fn parse_file(source: &str) -> Result<(Box<Program>, Vec<SourceFix>)> {
         let result = parser::parse_program(source);
 
         match result {
-            Ok(program) => Ok(Box::new(program)),
+            Ok(program) => Ok((Box::new(program), vec![])),
             Err(err) => {
                 let mut error_fixer = ParseErrorFixer::new(3);
-                Ok(SourceFile::try_parse(&mut error_fixer, source, err)?)
+                let program = SourceFile::try_parse(&mut error_fixer, source, err)?;
+                Ok((program, error_fixer.get_fixes().to_owned()))
             }
         }
     }
 
-    fn get_statements(source: &str) -> Result<Box<Suite>> {
-        Ok(Box::new(SourceFile::parse_file(source)?.statements))
+    fn get_statements(source: &str) -> Result<(Box<Suite>, Vec<SourceFix>)> {
+        let (program, fixes) = SourceFile::parse_file(source)?;
+        Ok((Box::new(program.statements), fixes))
     }
 
     fn visit<T>(statements: &Suite, mut visitor: T) -> T
@@ -124,13 +150,117 @@ impl SourceFile {
         visitor
     }
 
-    pub fn load(path: &PathBuf, source: String) -> Result<SourceFile> {
-        let statements = match SourceFile::get_statements(&source) {
-            Ok(statements) => statements,
+    pub fn load(path: &Path, source: String) -> Result<SourceFile> {
+        let auto_exec = AutoExec::detect(path);
+        let python = match auto_exec {
+            Some(AutoExec::Pth) => auto_exec::pth_to_python(&source),
+            _ => source.to_owned(),
+        };
+        let mut sf = SourceFile::load_python(path, source, &python)?;
+        if auto_exec == Some(AutoExec::SetupScript) {
+            sf.setup_commands = auto_exec::setup_commands(sf.get_entries());
+        }
+        sf.auto_exec = auto_exec;
+        Ok(sf)
+    }
+
+    /// Loads every code cell of a Jupyter notebook as its own file, with a path like
+    /// `notebook.ipynb#cell-3` so line numbers are relative to the cell. Cells that
+    /// don't parse are skipped.
+    pub fn load_notebook(path: &Path, json: &str) -> Result<Vec<SourceFile>> {
+        let cells = notebook::code_cells(json).map_err(|err| err.at(path))?;
+
+        Ok(cells
+            .into_iter()
+            .filter_map(|(n, source)| {
+                let cell_path = PathBuf::from(format!("{}#cell-{}", path.display(), n));
+                let (python, shell_commands) = notebook::strip_magics(&source);
+                match SourceFile::load_python(&cell_path, source, &python) {
+                    Ok(mut sf) => {
+                        sf.shell_commands = shell_commands;
+                        Some(sf)
+                    }
+                    Err(err) => {
+                        warn!("Skipping {}: {}", cell_path.display(), err);
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Loads a `.pyc` file as Python source listing the imports, loaded names and string
+    /// constants of its code objects. The bytecode is read, never run.
+    pub fn load_compiled(path: &Path, data: &[u8]) -> Result<SourceFile> {
+        let python = match Marshal::read_pyc(data) {
+            Ok(marshal) => marshal.to_python(),
+            Err(err) => {
+                return Err(ScoutError::parse_from(
+                    format!("Failed to read compiled file: {}", err),
+                    err,
+                ))
+            }
+        };
+        SourceFile::load_python(path, python.to_owned(), &python)
+    }
+
+    /// Loads an ELF or PE extension module as Python source listing its imported symbols
+    /// as calls and its printable strings as constants.
+    pub fn load_native(path: &Path, data: &[u8]) -> Result<SourceFile> {
+        let module = match NativeModule::parse(data) {
+            Ok(module) => module,
+            Err(err) => {
+                return Err(ScoutError::parse_from(
+                    format!("Failed to read binary: {}", err),
+                    err,
+                ))
+            }
+        };
+        let python = module.to_python();
+        let mut sf = SourceFile::load_python(path, python.to_owned(), &python)?;
+        sf.artifact = Some(BinaryArtifact {
+            format: module.format,
+            shadows: native::shadowed_module(path),
+        });
+        Ok(sf)
+    }
+
+    /// Loads the code objects passed to `marshal.loads` as constant bytes the same way as
+    /// `.pyc` files, with a path like `file.py#marshal-12` for the line of the call.
+    pub fn load_marshal_blobs(&self) -> Vec<SourceFile> {
+        let has_marshal = self.has_import("marshal");
+        self.blob_visitor
+            .get_blobs("marshal.loads")
+            .chain(self.blob_visitor.get_blobs("loads").filter(|_| has_marshal))
+            .filter_map(|blob| {
+                let path = PathBuf::from(format!(
+                    "{}#marshal-{}",
+                    self.source_path.display(),
+                    blob.location.row()
+                ));
+                let python = match Marshal::loads(&blob.data) {
+                    Ok(marshal) => marshal.to_python(),
+                    Err(err) => {
+                        warn!("Skipping {}: {}", path.display(), err);
+                        return None;
+                    }
+                };
+                SourceFile::load_python(&path, python.to_owned(), &python).ok()
+            })
+            .collect()
+    }
+
+    /// Analyses `python`, which has the same lines as `source` but may have parts blanked
+    /// out that aren't Python, e.g. the path lines of a `.pth` file.
+    fn load_python(path: &Path, source: String, python: &str) -> Result<SourceFile> {
+        let parsed = SourceFile::get_statements(python);
+        let (statements, fixes) = match parsed {
+            Ok(parsed) => parsed,
             Err(err) => {
-                return Err(
-                    format!("Failed to get statements from file: {}", err.to_string()).into(),
-                )
+                return Err(ScoutError::parse_from(
+                    format!("Failed to get statements from file: {}", err),
+                    err,
+                ))
             }
         };
 
@@ -140,9 +270,14 @@ impl SourceFile {
         let variable_visitor = SourceFile::visit(&statements, VariableVisitor::new());
         let mut import_visitor = SourceFile::visit(&statements, ImportVisitor::new());
         let mut function_visitor = SourceFile::visit(&statements, CallVisitor::new());
+        let mut definition_visitor = SourceFile::visit(&statements, DefinitionVisitor::new());
+        definition_visitor.resolve_ends(&source);
+        let string_visitor = SourceFile::visit(&statements, StringVisitor::new());
+        let mut blob_visitor = SourceFile::visit(&statements, BlobVisitor::new());
         // debug!("Variable visitor?: {:#?}", variable_visitor.get_variables());
 
         function_visitor.resolve_imports(import_visitor.get_aliases());
+        blob_visitor.resolve_imports(import_visitor.get_aliases());
         function_visitor.resolve_variables(variable_visitor.get_variables());
 
         import_visitor.resolve_dynamic_imports(
@@ -158,6 +293,14 @@ impl SourceFile {
             import_visitor,
             call_visitor: function_visitor,
             variable_visitor,
+            definition_visitor,
+            string_visitor,
+            blob_visitor,
+            fixes,
+            auto_exec: None,
+            shell_commands: vec![],
+            artifact: None,
+            setup_commands: vec![],
         };
 
         Ok(sf)
@@ -177,6 +320,60 @@ impl SourceFile {
             .unwrap_or("<error getting filename>")
     }
 
+    /// Everything that had to be rewritten or blanked for the file to parse.
+    /// Line numbers refer to the original source.
+    pub fn get_fixes(&self) -> &Vec<SourceFix> {
+        &self.fixes
+    }
+
+    pub fn get_auto_exec(&self) -> Option<&AutoExec> {
+        self.auto_exec.as_ref()
+    }
+
+    pub fn set_auto_exec(&mut self, auto_exec: AutoExec) {
+        self.auto_exec = Some(auto_exec);
+    }
+
+    pub fn get_artifact(&self) -> Option<&BinaryArtifact> {
+        self.artifact.as_ref()
+    }
+
+    /// Shell commands run from the notebook cell, empty for regular files.
+    pub fn get_shell_commands(&self) -> &Vec<ShellCommand> {
+        &self.shell_commands
+    }
+
+    /// Whether the code at the line runs automatically, e.g. top-level code in `__init__.py`.
+    pub fn is_auto_executed(&self, line: usize) -> bool {
+        match &self.auto_exec {
+            Some(auto_exec) => {
+                let enclosing: Vec<&str> = self
+                    .get_definitions()
+                    .iter()
+                    .filter(|d| d.contains(line))
+                    .map(|d| d.name.as_str())
+                    .collect();
+                auto_exec.runs(&enclosing)
+                    || auto_exec::runs_in_command(&self.setup_commands, &enclosing)
+            }
+            None => false,
+        }
+    }
+
+    /// Every string constant in the file.
+    pub fn get_strings(&self) -> &Vec<StringEntry> {
+        self.string_visitor.get_strings()
+    }
+
+    pub fn get_definitions(&self) -> &Vec<Definition> {
+        self.definition_visitor.get_definitions()
+    }
+
+    /// The innermost function or class definition containing the given line.
+    pub fn get_definition(&self, line: usize) -> Option<&Definition> {
+        self.definition_visitor.get_definition(line)
+    }
+
     pub fn display_functions(&self) -> String {
         self.call_visitor
             .get_entries()
@@ -204,6 +401,21 @@ impl SourceFile {
         self.import_visitor.get_imports()
     }
 
+    /// The imports ordered by their location in the file, so that evaluating them
+    /// does not depend on the iteration order of the underlying `HashSet`.
+    pub fn get_sorted_imports(&self) -> Vec<&ImportEntry> {
+        let mut imports: Vec<&ImportEntry> = self.get_imports().iter().collect();
+        imports.sort_by(|a, b| {
+            (a.location.row(), a.location.column(), &a.module, &a.symbol).cmp(&(
+                b.location.row(),
+                b.location.column(),
+                &b.module,
+                &b.symbol,
+            ))
+        });
+        imports
+    }
+
     pub fn get_import_counts(&self) -> &HashMap<String, usize> {
         self.import_visitor.get_counts()
     }
@@ -7,13 +7,13 @@ use std::{fs, path::Path};
 
 use walkdir::WalkDir;
 
-use crate::Result;
+use crate::{Result, ScoutError};
 
 pub fn load_from_file<P>(path: P) -> Result<String>
 where
     P: AsRef<Path>,
 {
-    Ok(fs::read_to_string(path)?)
+    fs::read_to_string(&path).map_err(|err| ScoutError::from(err).at(path.as_ref()))
 }
 
 /// Example copied from the Rust By Example book
@@ -23,7 +23,7 @@ pub fn read_lines<P>(filename: P) -> Result<io::Lines<io::BufReader<File>>>
 where
     P: AsRef<Path>,
 {
-    let file = File::open(filename)?;
+    let file = File::open(&filename).map_err(|err| ScoutError::from(err).at(filename.as_ref()))?;
     Ok(io::BufReader::new(file).lines())
 }
 
@@ -54,9 +54,8 @@ pub fn collect_files_matching(
             let p = e.path().to_path_buf();
             if p.is_file() {
                 if let Some(filename) = p.file_name() {
-                    let target = filename.to_str().unwrap();
                     for f_match in filename_match.iter() {
-                        if target == *f_match {
+                        if filename == *f_match {
                             if let None = matches.get(*f_match) {
                                 matches.insert(f_match.to_string(), p.clone());
                             } else {
@@ -205,45 +204,121 @@ pub mod ast {
         }
     }
 
-    pub fn resolve_args(args: &[Expression]) -> Vec<Option<VariableType>> {
-        // trace!("{:#?}", args);
-        let results: Vec<Option<VariableType>> = args
-            .iter()
-            .map(|arg| {
-                // try_to_string(arg)
-                if let Some(str_val) = try_to_string(&arg) {
-                    let val = if is_identifier(&arg) {
-                        VariableType::Identifier(str_val)
-                    } else {
-                        VariableType::Str(str_val)
-                    };
-                    Some(val)
+    /// The dotted name of an identifier or attribute chain, e.g. `os.environ`.
+    pub fn try_dotted_identifier(expr: &Expression) -> Option<String> {
+        match &expr.node {
+            ExpressionType::Identifier { name } => Some(name.to_owned()),
+            ExpressionType::Attribute { value, name } => {
+                Some(format!("{}.{}", try_dotted_identifier(value)?, name))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the expression is a site-packages directory, e.g. `site.getsitepackages()[0]`
+    /// or `sysconfig.get_paths()["purelib"]`.
+    fn is_site_packages(expr: &Expression) -> bool {
+        let is_purelib = |expr: &Expression| {
+            try_to_string(expr).is_some_and(|scheme| scheme == "purelib" || scheme == "platlib")
+        };
+        match &expr.node {
+            ExpressionType::Call { function, args, .. } => try_dotted_identifier(function)
+                .is_some_and(|function| {
+                    function.ends_with("getsitepackages")
+                        || function.ends_with("getusersitepackages")
+                        || (function.ends_with("sysconfig.get_path")
+                            && args.first().is_some_and(is_purelib))
+                }),
+            ExpressionType::Subscript { a, b } => {
+                is_site_packages(a)
+                    || matches!(&a.node, ExpressionType::Call { function, .. }
+                        if try_dotted_identifier(function)
+                            .is_some_and(|function| function.ends_with("sysconfig.get_paths"))
+                            && is_purelib(b))
+            }
+            _ => try_dotted_identifier(expr).is_some_and(|name| name == "site.USER_SITE"),
+        }
+    }
+
+    /// Folds `os.path.join` and `os.path.expanduser` calls into a single path, with `*`
+    /// for the parts that can't be resolved, e.g. `*/.config/autostart/x.desktop`, and
+    /// `site-packages` for the site-packages directory.
+    pub fn try_fold_path(expr: &Expression) -> Option<String> {
+        if is_site_packages(expr) {
+            return Some(String::from("site-packages"));
+        }
+        if let ExpressionType::Call { function, args, .. } = &expr.node {
+            let function = try_dotted_identifier(function)?;
+            if function.ends_with("path.join") {
+                let parts: Vec<String> = args
+                    .iter()
+                    .map(|arg| try_path_part(arg).unwrap_or_else(|| String::from("*")))
+                    .collect();
+                return Some(parts.join("/"));
+            }
+            if function.ends_with("path.expanduser") && args.len() == 1 {
+                return try_path_part(&args[0]);
+            }
+        }
+        None
+    }
+
+    fn try_path_part(expr: &Expression) -> Option<String> {
+        match &expr.node {
+            ExpressionType::String { .. } | ExpressionType::Binop { .. } => try_to_string(expr),
+            _ => try_fold_path(expr),
+        }
+    }
+
+    fn resolve_arg(arg: &Expression) -> Option<VariableType> {
+        match &arg.node {
+            ExpressionType::List { elements } => Some(VariableType::List(
+                elements.iter().map(resolve_arg).collect(),
+            )),
+            ExpressionType::Tuple { elements } => Some(VariableType::Tuple(
+                elements.iter().map(resolve_arg).collect(),
+            )),
+            ExpressionType::Dict { elements } => Some(VariableType::Dict(
+                elements
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        Some((try_to_string(key.as_ref()?)?, resolve_arg(value)?))
+                    })
+                    .collect(),
+            )),
+            // `dict(install=PostInstall)` is the same as a dict display
+            ExpressionType::Call {
+                function, keywords, ..
+            } if try_identifier(function).is_some_and(|name| name == "dict") => {
+                Some(VariableType::Dict(
+                    keywords
+                        .iter()
+                        .filter_map(|keyword| {
+                            Some((keyword.name.to_owned()?, resolve_arg(&keyword.value)?))
+                        })
+                        .collect(),
+                ))
+            }
+            ExpressionType::Call { .. } => try_fold_path(arg).map(VariableType::Str),
+            _ => {
+                let str_val = try_to_string(arg)?;
+                if is_identifier(arg) {
+                    Some(VariableType::Identifier(str_val))
                 } else {
-                    None
+                    Some(VariableType::Str(str_val))
                 }
-            })
-            .collect();
-        results
+            }
+        }
+    }
+
+    pub fn resolve_args(args: &[Expression]) -> Vec<Option<VariableType>> {
+        args.iter().map(resolve_arg).collect()
     }
 
     pub fn resolve_kwargs(args: &[Keyword]) -> Vec<(Option<String>, Option<VariableType>)> {
-        // trace!("{:#?}", args);
-        let results: Vec<(Option<String>, Option<VariableType>)> = args
-            .iter()
-            .map(|arg| {
-                if let Some(str_val) = try_to_string(&arg.value) {
-                    let val = if is_identifier(&arg.value) {
-                        VariableType::Identifier(str_val)
-                    } else {
-                        VariableType::Str(str_val)
-                    };
-                    (arg.name.clone(), Some(val))
-                } else {
-                    (arg.name.clone(), None)
-                }
-            })
-            .collect();
-        results
+        args.iter()
+            .map(|arg| (arg.name.clone(), resolve_arg(&arg.value)))
+            .collect()
     }
 
     pub fn resolve_string_group(value: &StringGroup) -> Option<String> {
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use ast_walker::AstVisitor;
+use flate2::read::ZlibDecoder;
+use rustpython_parser::ast::{Expression, ExpressionType, StringGroup};
+use serde::{Deserialize, Serialize};
+
+use super::Location;
+use crate::utils::ast::try_dotted_identifier;
+
+/// Decompressed payloads larger than this are not read any further.
+const MAX_BLOB_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Bytes passed to a `loads` call, e.g. the blob in `marshal.loads(b"\xe3...")`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlobEntry {
+    pub function: String,
+    pub data: Vec<u8>,
+    pub location: Location,
+}
+
+/// Collects the first argument of `loads` calls when it can be resolved to constant bytes,
+/// including through `base64.b64decode` and `zlib.decompress`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BlobVisitor {
+    blobs: Vec<BlobEntry>,
+}
+
+impl BlobVisitor {
+    pub fn new() -> Self {
+        Self { blobs: vec![] }
+    }
+
+    /// The blobs passed to the function, after aliases have been resolved.
+    pub fn get_blobs<'a>(&'a self, function: &'a str) -> impl Iterator<Item = &'a BlobEntry> {
+        self.blobs
+            .iter()
+            .filter(move |blob| blob.function == function)
+    }
+
+    pub fn resolve_imports(&mut self, aliases: &HashMap<String, String>) {
+        for blob in self.blobs.iter_mut() {
+            let base = blob.function.split('.').next().unwrap_or_default();
+            if let Some(module_identifier) = aliases.get(base) {
+                blob.function = blob.function.replacen(base, module_identifier, 1);
+            }
+        }
+    }
+}
+
+fn decode_b64(value: &[u8]) -> Option<Vec<u8>> {
+    let mut out = vec![];
+    let mut buffer: u32 = 0;
+    let mut buffered = 0;
+
+    for &c in value {
+        let bits = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+        buffer = ((buffer << 6) | bits as u32) & 0xffff;
+        buffered += 6;
+        if buffered >= 8 {
+            buffered -= 8;
+            out.push((buffer >> buffered) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn decompress(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = vec![];
+    ZlibDecoder::new(data)
+        .take(MAX_BLOB_SIZE)
+        .read_to_end(&mut out)
+        .ok()?;
+    Some(out)
+}
+
+fn try_bytes(expr: &Expression) -> Option<Vec<u8>> {
+    match &expr.node {
+        ExpressionType::Bytes { value } => Some(value.to_owned()),
+        ExpressionType::String {
+            value: StringGroup::Constant { value },
+        } => Some(value.as_bytes().to_vec()),
+        ExpressionType::Call { function, args, .. } if args.len() == 1 => {
+            let function = try_dotted_identifier(function)?;
+            let inner = try_bytes(&args[0])?;
+            if function.ends_with("b64decode") {
+                decode_b64(&inner)
+            } else if function.ends_with("decompress") {
+                decompress(&inner)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+impl AstVisitor for BlobVisitor {
+    fn visit_expression(&mut self, expr: &Expression) {
+        if let ExpressionType::Call { function, args, .. } = &expr.node {
+            // `marshal.loads`, or `loads` when it was imported on its own
+            let function = match try_dotted_identifier(function) {
+                Some(function) if function == "loads" || function.ends_with(".loads") => function,
+                _ => return,
+            };
+            let arg = match args.first() {
+                Some(arg) if !matches!(arg.node, ExpressionType::String { .. }) => arg,
+                _ => return,
+            };
+            if let Some(data) = try_bytes(arg) {
+                self.blobs.push(BlobEntry {
+                    function,
+                    data,
+                    location: Location::from_rustpython(expr.location),
+                });
+            }
+        }
+    }
+}
@@ -16,6 +16,9 @@ pub struct CallEntry {
     pub location: Location,
     pub args: Vec<Option<VariableType>>,
     pub keywords: Vec<(Option<String>, Option<VariableType>)>,
+    /// Dotted names passed as positional arguments, e.g. `os.environ` in `dict(os.environ)`.
+    #[serde(default)]
+    pub arg_identifiers: Vec<String>,
 }
 
 impl CallEntry {
@@ -32,8 +35,87 @@ impl CallEntry {
     pub fn get_identifier(&self) -> &String {
         &self.full_identifier
     }
+
+    /// Whether this is a call to the given function. Dotted names, e.g. `marshal.loads`,
+    /// have to match the end of the full identifier, other names only the last attribute.
+    pub fn is_call_to(&self, function: &str) -> bool {
+        if function.contains('.') {
+            self.full_identifier == function
+                || self
+                    .full_identifier
+                    .ends_with(format!(".{}", function).as_str())
+        } else {
+            utils::get_last_attr(self.full_identifier.as_str()) == function
+        }
+    }
+
+    /// A positional argument, or the keyword argument with the given name.
+    pub fn get_arg(&self, idx: usize, keyword: &str) -> Option<&VariableType> {
+        match self.args.get(idx) {
+            Some(arg) => arg.as_ref(),
+            None => self.get_keyword(keyword),
+        }
+    }
+
+    pub fn get_keyword(&self, keyword: &str) -> Option<&VariableType> {
+        self.keywords
+            .iter()
+            .find(|(name, _)| name.as_deref() == Some(keyword))
+            .and_then(|(_, value)| value.as_ref())
+    }
+
+    /// Every string passed to the call, including the items of lists and tuples.
+    pub fn get_arg_strings(&self) -> Vec<&str> {
+        fn collect<'a>(value: &'a VariableType, out: &mut Vec<&'a str>) {
+            match value {
+                VariableType::Str(value) => out.push(value),
+                VariableType::List(items) | VariableType::Tuple(items) => {
+                    items.iter().flatten().for_each(|item| collect(item, out))
+                }
+                _ => {}
+            }
+        }
+
+        let mut out: Vec<&str> = vec![];
+        self.args
+            .iter()
+            .chain(self.keywords.iter().map(|(_, value)| value))
+            .flatten()
+            .for_each(|value| collect(value, &mut out));
+        out
+    }
+
+    /// The path a call writes to, for `open` with a writing mode and for functions that
+    /// copy, move or link a file to a destination.
+    pub fn get_write_target(&self) -> Option<&str> {
+        let target = if self.is_call_to("open") && !self.is_call_to("os.open") {
+            let mode = self.get_arg(1, "mode")?.get_string()?;
+            if !mode.contains(['w', 'a', 'x', '+']) {
+                return None;
+            }
+            self.get_arg(0, "file")?
+        } else if COPY_FUNCTIONS.iter().any(|f| self.is_call_to(f)) {
+            self.get_arg(1, "dst")?
+        } else {
+            return None;
+        };
+        target.get_string().map(|target| target.as_str())
+    }
 }
 
+/// Functions that write a file to the path in their second argument.
+const COPY_FUNCTIONS: [&str; 8] = [
+    "shutil.copy",
+    "shutil.copy2",
+    "shutil.move",
+    "os.rename",
+    "os.replace",
+    "os.symlink",
+    "os.link",
+    // also covers `shutil.copyfile`, as it is often imported on its own
+    "copyfile",
+];
+
 impl Hash for CallEntry {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.full_identifier.hash(state);
@@ -126,6 +208,13 @@ impl CallVisitor {
                     entry.full_identifier
                 );
             }
+
+            for identifier in entry.arg_identifiers.iter_mut() {
+                let base = identifier.split('.').next().unwrap_or_default();
+                if let Some(module_identifier) = aliases.get(base) {
+                    *identifier = identifier.replacen(base, module_identifier, 1);
+                }
+            }
         }
     }
 
@@ -205,6 +294,10 @@ impl AstVisitor for CallVisitor {
         };
 
         if let Some(f) = func {
+            let arg_identifiers = args
+                .iter()
+                .filter_map(utils::ast::try_dotted_identifier)
+                .collect();
             let args = utils::ast::resolve_args(args);
             // trace!("args for func {} = {:?}", f, args);
             let kw = resolve_kwargs(keywords);
@@ -213,6 +306,7 @@ impl AstVisitor for CallVisitor {
                 location: Location::from_rustpython(function.location),
                 args,
                 keywords: kw,
+                arg_identifiers,
             };
 
             self.add_call_entry(entry);
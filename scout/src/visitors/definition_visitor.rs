@@ -0,0 +1,241 @@
+use ast_walker::AstVisitor;
+use rustpython_parser::ast::{Expression, Keyword, Parameters, StatementType, Suite};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DefinitionKind {
+    Function,
+    Class,
+}
+
+impl DefinitionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DefinitionKind::Function => "function",
+            DefinitionKind::Class => "class",
+        }
+    }
+}
+
+/// A function or class definition and the lines it spans, decorators included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Definition {
+    /// The qualified name of the definition, e.g. `Outer.method`.
+    pub name: String,
+    pub kind: DefinitionKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Definition {
+    pub fn contains(&self, line: usize) -> bool {
+        line >= self.start && line <= self.end
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DefinitionVisitor {
+    definitions: Vec<Definition>,
+    scope: Vec<String>,
+}
+
+impl DefinitionVisitor {
+    pub fn new() -> Self {
+        Self {
+            definitions: vec![],
+            scope: vec![],
+        }
+    }
+
+    pub fn get_definitions(&self) -> &Vec<Definition> {
+        &self.definitions
+    }
+
+    /// Returns the innermost definition the given line belongs to.
+    pub fn get_definition(&self, line: usize) -> Option<&Definition> {
+        self.definitions
+            .iter()
+            .filter(|d| d.contains(line))
+            .max_by_key(|d| d.start)
+    }
+
+    /// The AST only gives us the line a statement starts on, so the end of a definition
+    /// is approximated by the start of its last nested statement. This extends that end
+    /// over any following lines that are still indented deeper than the definition,
+    /// e.g. the rest of a call spanning several lines.
+    pub fn resolve_ends(&mut self, source: &str) {
+        let lines: Vec<&str> = source.lines().collect();
+
+        for definition in self.definitions.iter_mut() {
+            let def_indent = match lines.get(definition.start - 1) {
+                Some(line) => indentation(line),
+                None => continue,
+            };
+
+            for (idx, line) in lines.iter().enumerate().skip(definition.end) {
+                let trimmed = line.trim();
+                if trimmed.is_empty() || trimmed.starts_with('#') {
+                    continue;
+                }
+                if indentation(line) <= def_indent {
+                    break;
+                }
+                definition.end = idx + 1;
+            }
+        }
+    }
+
+    fn add_definition(
+        &mut self,
+        name: &str,
+        kind: DefinitionKind,
+        location: &rustpython_parser::ast::Location,
+        body: &Suite,
+        decorator_list: &[Expression],
+    ) {
+        let start = decorator_list
+            .iter()
+            .map(|d| d.location.row())
+            .chain(std::iter::once(location.row()))
+            .min()
+            .unwrap_or_else(|| location.row());
+        let end = last_row(body).unwrap_or_else(|| location.row());
+
+        let mut qualified = self.scope.clone();
+        qualified.push(name.to_owned());
+
+        self.definitions.push(Definition {
+            name: qualified.join("."),
+            kind,
+            start,
+            end: end.max(location.row()),
+        });
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// The row of the last statement in a suite, descending into compound statements.
+fn last_row(body: &Suite) -> Option<usize> {
+    let last = body.last()?;
+    let nested = match &last.node {
+        StatementType::If { body, orelse, .. }
+        | StatementType::While { body, orelse, .. }
+        | StatementType::For { body, orelse, .. } => match orelse {
+            Some(orelse) => last_row(orelse),
+            None => last_row(body),
+        },
+        StatementType::With { body, .. }
+        | StatementType::FunctionDef { body, .. }
+        | StatementType::ClassDef { body, .. } => last_row(body),
+        StatementType::Try {
+            body,
+            handlers,
+            orelse,
+            finalbody,
+        } => finalbody
+            .as_ref()
+            .and_then(last_row)
+            .or_else(|| orelse.as_ref().and_then(last_row))
+            .or_else(|| handlers.last().and_then(|h| last_row(&h.body)))
+            .or_else(|| last_row(body)),
+        _ => None,
+    };
+
+    Some(nested.unwrap_or(0).max(last.location.row()))
+}
+
+impl AstVisitor for DefinitionVisitor {
+    fn visit_function_def(
+        &mut self,
+        location: &rustpython_parser::ast::Location,
+        _is_async: bool,
+        name: &String,
+        _args: &Box<Parameters>,
+        body: &Suite,
+        decorator_list: &Vec<Expression>,
+        _returns: &Option<Expression>,
+    ) {
+        self.add_definition(
+            name,
+            DefinitionKind::Function,
+            location,
+            body,
+            decorator_list,
+        );
+
+        self.scope.push(name.to_owned());
+        self.walk_statements(body);
+        self.scope.pop();
+    }
+
+    fn visit_class_def(
+        &mut self,
+        location: &rustpython_parser::ast::Location,
+        name: String,
+        body: &Suite,
+        _bases: &Vec<Expression>,
+        _keywords: &Vec<Keyword>,
+        decorator_list: &Vec<Expression>,
+    ) {
+        self.add_definition(&name, DefinitionKind::Class, location, body, decorator_list);
+
+        self.scope.push(name);
+        self.walk_statements(body);
+        self.scope.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast_walker::AstWalker;
+    use rustpython_parser::parser;
+
+    use super::{DefinitionKind, DefinitionVisitor};
+
+    #[test]
+    fn test_definition_spans() {
+        let source = r#"import os
+
+@decorator
+def outer(a):
+    def inner():
+        return os.system(
+            "ls",
+        )
+
+    return inner
+
+class Thing:
+    def method(self):
+        pass
+x = 1
+"#;
+        let program = parser::parse_program(source).unwrap();
+        let mut visitor = DefinitionVisitor::new();
+        AstWalker::visit(&mut visitor, &program.statements);
+        visitor.resolve_ends(source);
+
+        let spans: Vec<(&str, DefinitionKind, usize, usize)> = visitor
+            .get_definitions()
+            .iter()
+            .map(|d| (d.name.as_str(), d.kind, d.start, d.end))
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                ("outer", DefinitionKind::Function, 3, 10),
+                ("outer.inner", DefinitionKind::Function, 5, 8),
+                ("Thing", DefinitionKind::Class, 12, 14),
+                ("Thing.method", DefinitionKind::Function, 13, 14),
+            ]
+        );
+
+        assert_eq!(visitor.get_definition(7).unwrap().name, "outer.inner");
+        assert_eq!(visitor.get_definition(10).unwrap().name, "outer");
+        assert!(visitor.get_definition(15).is_none());
+    }
+}
@@ -219,6 +219,7 @@ impl AstVisitor for ImportVisitor {
 
     fn visit_function_def(
         &mut self,
+        _location: &rustpython_parser::ast::Location,
         _is_async: bool,
         _name: &String,
         _args: &Box<Parameters>,
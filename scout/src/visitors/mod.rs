@@ -1,13 +1,19 @@
+mod blob_visitor;
 mod call_visitor;
+mod definition_visitor;
 mod import_visitor;
+mod string_visitor;
 mod variable_visitor;
 
+pub use blob_visitor::BlobVisitor;
 pub use call_visitor::{CallEntry, CallVisitor};
+pub use definition_visitor::{Definition, DefinitionVisitor};
 pub use import_visitor::ImportEntry;
 pub(crate) use import_visitor::ImportVisitor;
 
 use rustpython_parser::location;
 use serde::{Deserialize, Serialize};
+pub use string_visitor::{StringEntry, StringVisitor};
 pub use variable_visitor::{VariableType, VariableVisitor};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+
+use ast_walker::AstVisitor;
+use rustpython_parser::ast::{
+    ComprehensionKind, Expression, ExpressionType, Location as AstLocation, Parameters,
+    StringGroup, Suite,
+};
+use serde::{Deserialize, Serialize};
+
+use super::Location;
+
+/// A string or bytes constant found anywhere in the source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StringEntry {
+    pub value: String,
+    pub location: Location,
+}
+
+/// Collects every string constant in a file, including call arguments, containers,
+/// default values and the literal parts of f-strings.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StringVisitor {
+    strings: Vec<StringEntry>,
+    /// The walker visits call arguments on their own as well, so constants already
+    /// collected are skipped by location.
+    #[serde(skip)]
+    seen: HashSet<(usize, usize)>,
+}
+
+impl StringVisitor {
+    pub fn new() -> Self {
+        Self {
+            strings: vec![],
+            seen: HashSet::new(),
+        }
+    }
+
+    pub fn get_strings(&self) -> &Vec<StringEntry> {
+        &self.strings
+    }
+
+    fn add_string(&mut self, value: String, location: &AstLocation) {
+        if value.is_empty() || !self.seen.insert((location.row(), location.column())) {
+            return;
+        }
+        self.strings.push(StringEntry {
+            value,
+            location: Location::from_rustpython(*location),
+        });
+    }
+
+    /// Returns the constant parts of a string group, and collects the expressions
+    /// inside any formatted values along the way.
+    fn collect_group(&mut self, group: &StringGroup) -> String {
+        match group {
+            StringGroup::Constant { value } => value.to_owned(),
+            StringGroup::FormattedValue { value, spec, .. } => {
+                self.collect(value);
+                if let Some(spec) = spec {
+                    self.collect_group(spec);
+                }
+                String::from("")
+            }
+            StringGroup::Joined { values } => values
+                .iter()
+                .map(|value| self.collect_group(value))
+                .collect(),
+        }
+    }
+
+    fn collect_all(&mut self, exprs: &[Expression]) {
+        exprs.iter().for_each(|expr| self.collect(expr));
+    }
+
+    fn collect_parameters(&mut self, args: &Parameters) {
+        self.collect_all(&args.defaults);
+        args.kw_defaults
+            .iter()
+            .flatten()
+            .for_each(|expr| self.collect(expr));
+    }
+
+    fn collect(&mut self, expr: &Expression) {
+        match &expr.node {
+            ExpressionType::String { value } => {
+                let value = self.collect_group(value);
+                self.add_string(value, &expr.location);
+            }
+            ExpressionType::Bytes { value } => {
+                let value = String::from_utf8_lossy(value).into_owned();
+                self.add_string(value, &expr.location);
+            }
+            ExpressionType::BoolOp { values, .. } => self.collect_all(values),
+            ExpressionType::Compare { vals, .. } => self.collect_all(vals),
+            ExpressionType::List { elements }
+            | ExpressionType::Tuple { elements }
+            | ExpressionType::Set { elements }
+            | ExpressionType::Slice { elements } => self.collect_all(elements),
+            ExpressionType::Binop { a, b, .. } | ExpressionType::Subscript { a, b } => {
+                self.collect(a);
+                self.collect(b);
+            }
+            ExpressionType::NamedExpression { left, right } => {
+                self.collect(left);
+                self.collect(right);
+            }
+            ExpressionType::Unop { a, .. } => self.collect(a),
+            ExpressionType::Await { value }
+            | ExpressionType::YieldFrom { value }
+            | ExpressionType::Attribute { value, .. }
+            | ExpressionType::Starred { value } => self.collect(value),
+            ExpressionType::Yield { value } => {
+                if let Some(value) = value {
+                    self.collect(value);
+                }
+            }
+            ExpressionType::Call {
+                function,
+                args,
+                keywords,
+            } => {
+                self.collect(function);
+                self.collect_all(args);
+                keywords.iter().for_each(|kw| self.collect(&kw.value));
+            }
+            ExpressionType::Dict { elements } => {
+                for (key, value) in elements {
+                    if let Some(key) = key {
+                        self.collect(key);
+                    }
+                    self.collect(value);
+                }
+            }
+            ExpressionType::Comprehension { kind, generators } => {
+                match kind.as_ref() {
+                    ComprehensionKind::GeneratorExpression { element }
+                    | ComprehensionKind::List { element }
+                    | ComprehensionKind::Set { element } => self.collect(element),
+                    ComprehensionKind::Dict { key, value } => {
+                        self.collect(key);
+                        self.collect(value);
+                    }
+                }
+                for generator in generators {
+                    self.collect(&generator.target);
+                    self.collect(&generator.iter);
+                    self.collect_all(&generator.ifs);
+                }
+            }
+            ExpressionType::Lambda { args, body } => {
+                self.collect_parameters(args);
+                self.collect(body);
+            }
+            ExpressionType::IfExpression { test, body, orelse } => {
+                self.collect(test);
+                self.collect(body);
+                self.collect(orelse);
+            }
+            ExpressionType::Number { .. }
+            | ExpressionType::Identifier { .. }
+            | ExpressionType::True
+            | ExpressionType::False
+            | ExpressionType::None
+            | ExpressionType::Ellipsis => {}
+        }
+    }
+}
+
+impl AstVisitor for StringVisitor {
+    fn visit_expression(&mut self, expr: &Expression) {
+        self.collect(expr);
+    }
+
+    fn visit_return(&mut self, value: &Option<Expression>) {
+        self.walk_opt_expression(value);
+    }
+
+    fn visit_function_def(
+        &mut self,
+        _location: &AstLocation,
+        _is_async: bool,
+        _name: &String,
+        args: &Box<Parameters>,
+        body: &Suite,
+        decorator_list: &Vec<Expression>,
+        returns: &Option<Expression>,
+    ) {
+        self.collect_parameters(args);
+        self.walk_statements(body);
+        self.walk_expressions(decorator_list);
+        self.walk_opt_expression(returns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast_walker::AstWalker;
+    use rustpython_parser::parser;
+
+    use super::StringVisitor;
+
+    #[test]
+    fn test_collects_nested_strings() {
+        let source = r#"def f(a="default"):
+    return "returned"
+
+exec(decode("in call", key=b"bytes"))
+payload = ["in list", {"key": "value"}]
+print(f"pre {x!r} post")
+"#;
+        let program = parser::parse_program(source).unwrap();
+        let mut visitor = StringVisitor::new();
+        AstWalker::visit(&mut visitor, &program.statements);
+
+        let strings: Vec<(&str, usize)> = visitor
+            .get_strings()
+            .iter()
+            .map(|s| (s.value.as_str(), s.location.row()))
+            .collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                ("default", 1),
+                ("returned", 2),
+                ("in call", 4),
+                ("bytes", 4),
+                ("in list", 5),
+                ("key", 5),
+                ("value", 5),
+                ("pre  post", 6),
+            ]
+        );
+    }
+}
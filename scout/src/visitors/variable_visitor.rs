@@ -5,7 +5,7 @@ use rustpython_parser::ast::{Expression, ExpressionType, Operator};
 use serde::{Deserialize, Serialize};
 
 use crate::utils::{
-    ast::{do_binop, try_identifier, try_to_string},
+    ast::{do_binop, try_fold_path, try_identifier, try_to_string},
     format_empty_arg,
 };
 
@@ -90,7 +90,7 @@ impl VariableVisitor {
                 function,
                 args,
                 keywords,
-            } => None,
+            } => try_fold_path(expr).map(VariableType::Str),
             // self.visit_call(function, args, keywords);
             ExpressionType::Number { value } => None,
             ExpressionType::List { elements } => Some(VariableType::List(
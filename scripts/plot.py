@@ -12,8 +12,8 @@ args = parser.parse_args()
 
 output = json.loads(sys.stdin.read())
 
-fields = output["fields"]
-combined = output["combined_field"]
+fields = output["files"][0]["fields"]["fields"]
+combined = output["files"][0]["fields"]["combined"]
 
 plt.title("Density")
 plt.xlabel("Line of code")
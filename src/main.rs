@@ -1,9 +1,11 @@
-use clap::Parser;
-use scout::{Engine, Result};
+use clap::{Parser, Subcommand};
+use scout::{AnalysisResult, Canaries, DensityPlot, Engine, JsonLines, ReportOptions};
 
 #[macro_use]
 extern crate log;
 
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
@@ -35,13 +37,166 @@ struct Args {
 
     #[clap(long)]
     fields: Option<bool>,
+
+    /// Downsample each density field in the JSON output to at most this many points
+    #[clap(long)]
+    field_points: Option<usize>,
+
+    /// Explain why each bulletin was or wasn't shown
+    #[clap(long)]
+    explain: Option<bool>,
+
+    /// Write events as JSON Lines while the files are analysed, instead of all results at
+    /// the end
+    #[clap(long)]
+    json_lines: Option<bool>,
+
+    /// Include how long loading and evaluating took in the JSON output
+    #[clap(long)]
+    timings: Option<bool>,
+
+    /// Path to a canary table, overrides the one in the config
+    #[clap(long)]
+    canaries: Option<String>,
+
+    /// Also write a self-contained HTML report to this path
+    #[clap(long)]
+    html: Option<String>,
+
+    /// Also write an SVG plot of the density fields of the file to this path
+    #[clap(long)]
+    plot: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage canary tables
+    Canaries {
+        #[clap(subcommand)]
+        command: CanaryCommand,
+    },
+    /// Report only the risk a new version of a package adds compared to an old one
+    Diff {
+        /// The path to the old version of the package
+        old: String,
+
+        /// The path to the new version of the package
+        new: String,
+    },
+    /// Analyse every distribution installed in a site-packages directory or virtualenv
+    ScanEnv {
+        /// The path to the site-packages directory, or to the root of a virtualenv
+        path: String,
+    },
+    /// Analyse the archive each pinned requirement resolves to in a local wheelhouse
+    ScanRequirements {
+        /// The requirements or lock file, e.g. written by `pip-compile`
+        requirements: String,
+
+        /// The directory with the wheels and source distributions
+        #[clap(long)]
+        wheelhouse: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CanaryCommand {
+    /// Generate a canary table from a list of keywords
+    Generate {
+        /// File with one keyword per line, empty lines and lines starting with '#' are ignored
+        keywords: Option<String>,
+
+        /// Keyword to add to the table, can be given several times
+        #[clap(short, long)]
+        keyword: Vec<String>,
+
+        /// Also include the keywords from the built-in table
+        #[clap(long)]
+        defaults: bool,
+
+        /// Where to write the table, printed when not given
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+}
+
+fn generate_canaries(
+    keywords_path: Option<String>,
+    mut keywords: Vec<String>,
+    defaults: bool,
+    output: Option<String>,
+) -> Result<()> {
+    if let Some(path) = keywords_path {
+        let content = std::fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read keywords from '{}': {}", path, err))?;
+        keywords.extend(
+            content
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| line.to_owned()),
+        );
+    }
+    if defaults {
+        keywords.extend(Canaries::default_keywords()?);
+    }
+    if keywords.is_empty() {
+        return Err("Error: No keywords were given to generate canaries from.".into());
+    }
+
+    let table = Canaries::generate(&keywords)?.to_json()?;
+    match output {
+        Some(path) => std::fs::write(path, table)?,
+        None => println!("{}", table),
+    }
+    Ok(())
+}
+
+fn write_html(path: &str, results: &AnalysisResult) -> Result<()> {
+    std::fs::write(path, results.to_html())
+        .map_err(|err| format!("Failed to write HTML report to '{}': {}", path, err))?;
+    info!("HTML report written to '{}'", path);
+    Ok(())
+}
+
+fn write_plot(path: &str, results: &AnalysisResult) -> Result<()> {
+    let result = results
+        .get_results()
+        .first()
+        .ok_or("Nothing was analysed to plot")?;
+    std::fs::write(path, DensityPlot::from_analysis(result).to_svg())
+        .map_err(|err| format!("Failed to write plot to '{}': {}", path, err))?;
+    info!("Plot written to '{}'", path);
+    Ok(())
 }
 
 fn main() -> Result<()> {
     pretty_env_logger::init();
     let args = Args::parse();
+
+    if let Some(Command::Canaries {
+        command:
+            CanaryCommand::Generate {
+                keywords,
+                keyword,
+                defaults,
+                output,
+            },
+    }) = args.command
+    {
+        return generate_canaries(keywords, keyword, defaults, output);
+    }
+
     let show_all_override = args.all.unwrap_or(false);
-    let include_fields = args.fields.unwrap_or(false);
+    let explain = args.explain.unwrap_or(false);
+    let options = ReportOptions::default()
+        .set_fields(args.fields.unwrap_or(false))
+        .set_max_field_points(args.field_points)
+        .set_explanations(explain)
+        .set_timings(args.timings.unwrap_or(false));
 
     if show_all_override {
         warn!("Show all bulletins override is enabled.");
@@ -50,47 +205,132 @@ fn main() -> Result<()> {
     let mut engine = Engine::new()
         .set_show_all(show_all_override)
         .set_threshold(args.threshold.unwrap_or(0f64))
+        .set_explain(explain)
+        .set_canary_path(args.canaries)
         .set_rule_path(args.rules);
 
+    engine
+        .set_config_path(args.config)
+        .map_err(|err| err.to_string())?;
     if let Some(config_json) = args.config_json {
-        engine.set_config(config_json);
+        engine
+            .set_config(config_json)
+            .map_err(|err| err.to_string())?;
+    }
+
+    if let Some(Command::Diff { old, new }) = args.command {
+        return match engine.analyse_diff(old.as_str(), new.as_str()) {
+            Ok(diff) => {
+                match args.json {
+                    Some(_) => println!("{}", diff.to_json()),
+                    None => print!("{}", diff),
+                }
+                Ok(())
+            }
+            Err(err) => Err(format!("Failed to compare packages: {}", err).into()),
+        };
+    }
+
+    if let Some(Command::ScanEnv { path }) = args.command {
+        return match engine.analyse_installed(path.as_str()) {
+            Ok(installed) => {
+                match args.json {
+                    Some(_) => println!("{}", installed.to_json()),
+                    None => print!("{}", installed),
+                }
+                Ok(())
+            }
+            Err(err) => Err(format!("Failed to scan environment: {}", err).into()),
+        };
+    }
+
+    if let Some(Command::ScanRequirements {
+        requirements,
+        wheelhouse,
+    }) = args.command
+    {
+        return match engine.analyse_requirements(requirements.as_str(), wheelhouse.as_str()) {
+            Ok(scanned) => {
+                match args.json {
+                    Some(_) => println!("{}", scanned.to_json()),
+                    None => print!("{}", scanned),
+                }
+                Ok(())
+            }
+            Err(err) => Err(format!("Failed to scan requirements: {}", err).into()),
+        };
+    }
+
+    if args.json_lines.unwrap_or(false) {
+        if args.html.is_some() || args.plot.is_some() {
+            warn!("reports and plots are not written with JSON Lines output.");
+        }
+        let mut listener = JsonLines::new(std::io::stdout().lock()).set_options(options);
+        return match (args.file, args.package) {
+            (Some(path), _) => match engine.analyse_file_streaming(path.as_str(), &mut listener) {
+                Ok(_) => Ok(()),
+                Err(err) => Err(format!("Failed to analyse file: {}", err).into()),
+            },
+            (None, Some(package)) => {
+                match engine.analyse_package_streaming(package.as_str(), &mut listener) {
+                    Ok(_) => Ok(()),
+                    Err(err) => Err(format!("Failed to analyse package: {}", err).into()),
+                }
+            }
+            (None, None) => {
+                Err("Error: Either a file or a package has to be supplied as arguments.".into())
+            }
+        };
     }
 
     match args.file {
         Some(path) => match engine.analyse_file(path.as_str()) {
-            Ok(results) => match args.json {
-                Some(_) => {
-                    let result = if include_fields {
-                        results.to_json_with_fields()
-                    } else {
-                        results.to_json()
-                    };
-
-                    println!("{}", result);
-                    Ok(())
+            Ok(results) => {
+                if let Some(html) = &args.html {
+                    write_html(html, &results)?;
                 }
-                None => {
-                    println!("{}", results.to_string());
-                    Ok(())
+                if let Some(plot) = &args.plot {
+                    write_plot(plot, &results)?;
                 }
-            },
-            Err(err) => Err(format!("Failed to analyse file: {}", err.to_string()).into()),
-        },
-        None => match args.package {
-            Some(package) => match engine.analyse_package(package.as_str()) {
-                Ok(results) => match args.json {
+                match args.json {
                     Some(_) => {
-                        if include_fields {
-                            warn!("with_fields is only supported for single files only.");
-                        }
-                        println!("{}", results.to_json());
+                        println!("{}", results.to_report(&options).to_json());
                         Ok(())
                     }
                     None => {
                         println!("{}", results.to_string());
+                        if explain {
+                            println!("{}", results.explain());
+                        }
                         Ok(())
                     }
-                },
+                }
+            }
+            Err(err) => Err(format!("Failed to analyse file: {}", err.to_string()).into()),
+        },
+        None => match args.package {
+            Some(package) => match engine.analyse_package(package.as_str()) {
+                Ok(results) => {
+                    if let Some(html) = &args.html {
+                        write_html(html, &results)?;
+                    }
+                    if args.plot.is_some() {
+                        warn!("plots are only written for single files.");
+                    }
+                    match args.json {
+                        Some(_) => {
+                            println!("{}", results.to_report(&options).to_json());
+                            Ok(())
+                        }
+                        None => {
+                            println!("{}", results.to_string());
+                            if explain {
+                                println!("{}", results.explain());
+                            }
+                            Ok(())
+                        }
+                    }
+                }
                 Err(err) => {
                     info!("error debugging re {:?}", err);
                     Err(format!("Failed to analyse package: {}", err.to_string()).into())